@@ -0,0 +1,234 @@
+//! ウィンドウを開かずに外部エージェントと対局するヘッドレスモード。
+//! プロトコルは `bevy_2048::game::protocol` を参照。
+//!
+//! ```sh
+//! # 標準入出力でエージェントと直接やり取りする
+//! bot --seed 42
+//! # エンジンをサブプロセスとして起動し、各ゲームの結果を標準出力に 1 行ずつ出す
+//! bot --seed 42 --games 10 --timeout-ms 500 -- python3 agent.py
+//! ```
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, Command, ExitCode, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bevy_2048::game::protocol::{EndReason, HeadlessGame, Reply, ServerMessage, parse_reply};
+use bevy_2048::game::topology::Topology;
+use rand::prelude::*;
+
+const USAGE: &str =
+    "usage: bot [--seed N] [--games N] [--timeout-ms N] [--hex] [-- ENGINE [ARGS...]]";
+/// 標準入力を閉じてから、エンジンが自分で終わるのを待つ時間
+const ENGINE_EXIT_GRACE: Duration = Duration::from_secs(1);
+
+struct Options {
+    seed: u64,
+    games: usize,
+    timeout: Duration,
+    topology: Topology,
+    /// 空なら標準入出力を使う
+    engine: Vec<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    fn number<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+        value
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| format!("{flag} needs a number"))
+    }
+
+    let mut options = Options {
+        seed: rand::rng().random(),
+        games: 1,
+        timeout: Duration::from_secs(1),
+        topology: Topology::CLASSIC,
+        engine: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => options.seed = number(&arg, args.next())?,
+            "--games" => options.games = number(&arg, args.next())?,
+            "--timeout-ms" => options.timeout = Duration::from_millis(number(&arg, args.next())?),
+            "--hex" => options.topology = Topology::HEX,
+            "--" => {
+                options.engine = args.collect();
+                break;
+            }
+            _ => return Err(format!("unknown argument: {arg}")),
+        }
+    }
+    Ok(options)
+}
+
+/// 対局相手。状態を書き込み、返答は別スレッドで 1 行ずつ受け取る（タイムアウト判定のため）
+struct Agent {
+    writer: Box<dyn Write>,
+    replies: Receiver<String>,
+    child: Option<Child>,
+    /// 最後に送った局面の `id`
+    last_id: u64,
+}
+
+impl Agent {
+    fn stdio() -> Self {
+        Self {
+            writer: Box::new(io::stdout()),
+            replies: spawn_reader(BufReader::new(io::stdin())),
+            child: None,
+            last_id: 0,
+        }
+    }
+
+    fn engine(command: &[String]) -> io::Result<Self> {
+        let mut child = Command::new(&command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+        let writer = child.stdin.take().expect("stdin is piped");
+        let reader = child.stdout.take().expect("stdout is piped");
+        Ok(Self {
+            writer: Box::new(writer),
+            replies: spawn_reader(BufReader::new(reader)),
+            child: Some(child),
+            last_id: 0,
+        })
+    }
+
+    fn send(&mut self, message: &ServerMessage) -> io::Result<()> {
+        writeln!(self.writer, "{}", message.to_line())?;
+        self.writer.flush()
+    }
+
+    /// 次の局面を `id` を付けて送る
+    fn send_state(&mut self, game: &HeadlessGame) -> io::Result<()> {
+        self.last_id += 1;
+        self.send(&game.state(self.last_id))
+    }
+
+    /// 最後に送った局面への返答を待つ。空行と、前の局面の `id` が付いた遅れた返答は読み飛ばす
+    fn receive(&self, timeout: Duration) -> Result<Result<Reply, String>, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let line = self.replies.recv_timeout(remaining)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let reply = parse_reply(&line);
+            if let Ok(Reply { id: Some(id), .. }) = reply
+                && id != self.last_id
+            {
+                continue;
+            }
+            return Ok(reply);
+        }
+    }
+}
+
+fn spawn_reader(reader: impl BufRead + Send + 'static) -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in reader.lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+fn play_game(game: &mut HeadlessGame, agent: &mut Agent, timeout: Duration) -> EndReason {
+    loop {
+        if agent.send_state(game).is_err() {
+            return EndReason::Disconnected;
+        }
+        if game.is_over() {
+            return EndReason::GameOver;
+        }
+
+        let reply = match agent.receive(timeout) {
+            Ok(reply) => reply,
+            Err(RecvTimeoutError::Timeout) => return EndReason::Timeout,
+            Err(RecvTimeoutError::Disconnected) => return EndReason::Disconnected,
+        };
+        let (reason, result) = match reply {
+            Ok(reply) => (EndReason::IllegalMove, game.play(reply.direction)),
+            Err(message) => (EndReason::InvalidInput, Err(message)),
+        };
+        if let Err(message) = result {
+            let _ = agent.send(&game.error(message));
+            return reason;
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{message}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut agent = if options.engine.is_empty() {
+        Agent::stdio()
+    } else {
+        match Agent::engine(&options.engine) {
+            Ok(agent) => agent,
+            Err(error) => {
+                eprintln!("failed to start {}: {error}", options.engine[0]);
+                return ExitCode::FAILURE;
+            }
+        }
+    };
+
+    let mut timed_out = false;
+    for index in 0..options.games {
+        // 前のゲームでタイムアウトした返答が残っていれば捨てる。
+        // `id` のある返答は受け取るときにも見分けるが、方向だけの返答はここでしか捨てられない
+        if timed_out {
+            while agent.replies.try_recv().is_ok() {}
+        }
+
+        let seed = options.seed.wrapping_add(index as u64);
+        let mut game = HeadlessGame::new(index, options.topology, seed);
+        let started = Instant::now();
+        let reason = play_game(&mut game, &mut agent, options.timeout);
+        timed_out = reason == EndReason::Timeout;
+
+        let summary = game.summary(reason, started.elapsed());
+        let _ = agent.send(&summary);
+        if agent.child.is_some() {
+            println!("{}", summary.to_line());
+        }
+        if reason == EndReason::Disconnected {
+            break;
+        }
+    }
+
+    if let Some(mut child) = agent.child.take() {
+        // 標準入力を閉じ、エンジンが最後の結果と EOF を読んで自分で終わるのを少し待つ。
+        // その間も標準出力は読み続けて、書き込みで詰まらないようにする。終わらなければ止める
+        let Agent {
+            writer, replies, ..
+        } = agent;
+        drop(writer);
+        let deadline = Instant::now() + ENGINE_EXIT_GRACE;
+        while matches!(child.try_wait(), Ok(None)) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+        drop(replies);
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,106 @@
+//! 小さな盤面の到達可能な全局面を解析し、完全解析テーブルを書き出す。
+//! テーブルは `assets/ai/<幅>x<高さ>.tablebase` に書き出す。
+//! ゲームのヒント機能が読み込むのは、Mini の盤面で使う `assets/ai/3x3.tablebase` だけ。
+//!
+//! ```sh
+//! cargo run --release --bin solve -- --width 3 --height 3 --target 1024
+//! ```
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process::ExitCode;
+use std::time::Instant;
+
+use bevy_2048::game::board::exp_to_value;
+use bevy_2048::game::tablebase::Tablebase;
+use bevy_2048::game::topology::Topology;
+
+const USAGE: &str = "usage: solve --width N --height N [--target VALUE] [--out PATH]";
+
+struct Options {
+    width: usize,
+    height: usize,
+    target: u32,
+    out: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    fn value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+        value
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| format!("{flag} needs a value"))
+    }
+
+    let mut options = Options {
+        width: 0,
+        height: 0,
+        // 3×3 以下の盤面では 2048 にまず届かない
+        target: 1024,
+        out: None,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" => options.width = value(&arg, args.next())?,
+            "--height" => options.height = value(&arg, args.next())?,
+            "--target" => options.target = value(&arg, args.next())?,
+            "--out" => options.out = Some(value(&arg, args.next())?),
+            _ => return Err(format!("unknown argument: {arg}")),
+        }
+    }
+
+    if options.width == 0 || options.height == 0 || options.width * options.height > 9 {
+        return Err("board must have between 1 and 9 cells".to_string());
+    }
+    if !options.target.is_power_of_two() || options.target < 4 || options.target > 1 << 15 {
+        return Err("target must be a power of two between 4 and 32768".to_string());
+    }
+    Ok(options)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{message}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let topology = Topology::Grid {
+        width: options.width,
+        height: options.height,
+    };
+    let target = options.target.trailing_zeros() as u8;
+    let started = Instant::now();
+    let table = Tablebase::solve(topology, target);
+
+    let start = table.start_value().expect("start positions are solved");
+    println!(
+        "{}x{}: {} positions in {:.1?}",
+        options.width,
+        options.height,
+        table.len(),
+        started.elapsed()
+    );
+    println!("expected score: {:.3}", start.expected_score);
+    println!(
+        "P(reach {}): {:.6}",
+        exp_to_value(target),
+        start.win_probability
+    );
+
+    let out = options
+        .out
+        .unwrap_or_else(|| format!("assets/ai/{}x{}.tablebase", options.width, options.height));
+    let saved = File::create(&out).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        table.write_to(&mut writer)?;
+        writer.flush()
+    });
+    if let Err(error) = saved {
+        eprintln!("failed to save {out}: {error}");
+        return ExitCode::FAILURE;
+    }
+    println!("saved {out}");
+    ExitCode::SUCCESS
+}
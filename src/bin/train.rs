@@ -0,0 +1,177 @@
+//! n-tuple ネットワークを TD(0) / TD(λ) の自己対戦で学習し、重みをバイナリファイルに保存する。
+//! 保存した重みはゲームのヒント機能が `assets/ai/weights.ntuple` から読み込む。
+//!
+//! ```sh
+//! # 10 万ゲーム学習して保存する（最適化ビルド推奨）
+//! cargo run --release --bin train -- --games 100000 --lambda 0.5
+//! # 保存済みの重みを学習せずに評価する
+//! cargo run --release --bin train -- --resume assets/ai/weights.ntuple --games 0 --eval 1000
+//! ```
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::process::ExitCode;
+use std::time::Instant;
+
+use bevy_2048::game::board::{WIN_EXP, exp_to_value};
+use bevy_2048::game::ntuple::{
+    EpisodeStats, LARGE_PATTERNS, NTupleNetwork, SMALL_PATTERNS, TdLearner,
+};
+use rand::prelude::*;
+use rand::rngs::ChaCha8Rng;
+
+const USAGE: &str = "usage: train [--games N] [--alpha F] [--lambda F] [--horizon N] [--large] \
+[--seed N] [--resume PATH] [--out PATH] [--eval N] [--report N]";
+
+struct Options {
+    games: usize,
+    learner: TdLearner,
+    large: bool,
+    seed: u64,
+    resume: Option<String>,
+    out: String,
+    eval: usize,
+    report: usize,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    fn value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+        value
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| format!("{flag} needs a value"))
+    }
+
+    let mut options = Options {
+        games: 10_000,
+        learner: TdLearner {
+            alpha: 0.1,
+            lambda: 0.0,
+            horizon: 5,
+        },
+        large: false,
+        seed: rand::rng().random(),
+        resume: None,
+        out: "assets/ai/weights.ntuple".to_string(),
+        eval: 1000,
+        report: 1000,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => options.games = value(&arg, args.next())?,
+            "--alpha" => options.learner.alpha = value(&arg, args.next())?,
+            "--lambda" => options.learner.lambda = value(&arg, args.next())?,
+            "--horizon" => options.learner.horizon = value(&arg, args.next())?,
+            "--large" => options.large = true,
+            "--seed" => options.seed = value(&arg, args.next())?,
+            "--resume" => options.resume = Some(value(&arg, args.next())?),
+            "--out" => options.out = value(&arg, args.next())?,
+            "--eval" => options.eval = value(&arg, args.next())?,
+            "--report" => options.report = value::<usize>(&arg, args.next())?.max(1),
+            _ => return Err(format!("unknown argument: {arg}")),
+        }
+    }
+    Ok(options)
+}
+
+/// 平均スコアと、各タイル以上に到達した割合
+#[derive(Default)]
+struct Summary {
+    games: u32,
+    total_score: u64,
+    best_score: u32,
+    reached: [u32; 18],
+}
+
+impl Summary {
+    fn add(&mut self, stats: EpisodeStats) {
+        self.games += 1;
+        self.total_score += u64::from(stats.score);
+        self.best_score = self.best_score.max(stats.score);
+        for exp in 0..=usize::from(stats.max_exp).min(self.reached.len() - 1) {
+            self.reached[exp] += 1;
+        }
+    }
+
+    fn print(&self, label: &str) {
+        if self.games == 0 {
+            return;
+        }
+        let rate =
+            |exp: u8| f64::from(self.reached[usize::from(exp)]) * 100.0 / f64::from(self.games);
+        print!(
+            "{label}: {} games, mean {:.0}, best {}, 2048 {:.1}%",
+            self.games,
+            self.total_score as f64 / f64::from(self.games),
+            self.best_score,
+            rate(WIN_EXP),
+        );
+        for exp in WIN_EXP + 1..self.reached.len() as u8 {
+            if self.reached[usize::from(exp)] > 0 {
+                print!(", {} {:.1}%", exp_to_value(exp), rate(exp));
+            }
+        }
+        println!();
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{message}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut network = match &options.resume {
+        Some(path) => match File::open(path)
+            .and_then(|file| NTupleNetwork::read_from(&mut BufReader::new(file)))
+        {
+            Ok(network) => network,
+            Err(error) => {
+                eprintln!("failed to load {path}: {error}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => {
+            let patterns = if options.large {
+                LARGE_PATTERNS
+            } else {
+                SMALL_PATTERNS
+            };
+            NTupleNetwork::new(4, 4, patterns)
+        }
+    };
+
+    let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
+    let started = Instant::now();
+    let mut window = Summary::default();
+    for game in 1..=options.games {
+        window.add(options.learner.play(&mut network, &mut rng, true));
+        if game % options.report == 0 {
+            window.print(&format!("train {game} ({:.0?})", started.elapsed()));
+            window = Summary::default();
+        }
+    }
+
+    if options.games > 0 {
+        let saved = File::create(&options.out).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            network.write_to(&mut writer)?;
+            writer.flush()
+        });
+        if let Err(error) = saved {
+            eprintln!("failed to save {}: {error}", options.out);
+            return ExitCode::FAILURE;
+        }
+        println!("saved {}", options.out);
+    }
+
+    let mut evaluation = Summary::default();
+    for _ in 0..options.eval {
+        evaluation.add(options.learner.play(&mut network, &mut rng, false));
+    }
+    evaluation.print("eval");
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,236 @@
+//! ターミナル版 2048。SSH 越しのプレイやルールのデバッグに使う。
+//! ルールはゲーム本体と同じ `Board::compute_slide` / `place_random_tile` をそのまま使う。
+
+use std::io::{self, Write};
+use std::num::NonZero;
+
+use bevy::color::{Color, ColorToPacked};
+use bevy_2048::game::board::{Board, Direction, exp_to_value};
+use bevy_2048::game::topology::Topology;
+use bevy_2048::game::{text_color, tile_color};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{self, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, execute, queue};
+use rand::rng;
+
+/// 1 セルの幅と高さ（文字数）
+const CELL_WIDTH: u16 = 8;
+const CELL_HEIGHT: u16 = 3;
+/// スコア表示の行数
+const HEADER_ROWS: u16 = 2;
+/// 取り消せる手数の上限
+const UNDO_LIMIT: usize = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Playing,
+    Won,
+    GameOver,
+}
+
+struct Game {
+    board: Board,
+    score: u32,
+    /// 直前までの盤面とスコア（取り消し用）
+    history: Vec<(Board, u32)>,
+    /// 2048 到達済みフラグ。続行後に再び勝利画面を出さないために使う
+    has_won: bool,
+    phase: Phase,
+}
+
+impl Game {
+    fn new() -> Self {
+        Self {
+            board: Board::with_two_tiles(Topology::CLASSIC, &mut rng()),
+            score: 0,
+            history: Vec::new(),
+            has_won: false,
+            phase: Phase::Playing,
+        }
+    }
+
+    fn slide(&mut self, direction: Direction) {
+        if self.phase != Phase::Playing {
+            return;
+        }
+        let result = self.board.compute_slide(direction);
+        if !result.changed {
+            return;
+        }
+
+        if self.history.len() == UNDO_LIMIT {
+            self.history.remove(0);
+        }
+        self.history.push((self.board.clone(), self.score));
+
+        self.board = result.new_board;
+        self.score += result.score_gained;
+        self.board.place_random_tile(&mut rng());
+        self.check_game_state();
+    }
+
+    fn undo(&mut self) {
+        if let Some((board, score)) = self.history.pop() {
+            self.board = board;
+            self.score = score;
+            self.phase = Phase::Playing;
+        }
+    }
+
+    fn continue_playing(&mut self) {
+        if self.phase == Phase::Won {
+            self.phase = Phase::Playing;
+            self.check_game_state();
+        }
+    }
+
+    /// `state::check_game_state` と同じく、初回の 2048 到達で勝利、動かせなくなったら終了
+    fn check_game_state(&mut self) {
+        if !self.has_won && self.board.has_won() {
+            self.has_won = true;
+            self.phase = Phase::Won;
+            return;
+        }
+        if !self.board.can_move() {
+            self.phase = Phase::GameOver;
+        }
+    }
+}
+
+/// `input::handle_input` の正方格子用キー割り当てと同じ（WASD・矢印・HJKL）
+fn key_direction(code: KeyCode) -> Option<Direction> {
+    match code {
+        KeyCode::Up | KeyCode::Char('w' | 'k') => Some(Direction::Up),
+        KeyCode::Down | KeyCode::Char('s' | 'j') => Some(Direction::Down),
+        KeyCode::Left | KeyCode::Char('a' | 'h') => Some(Direction::Left),
+        KeyCode::Right | KeyCode::Char('d' | 'l') => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+fn terminal_color(color: Color) -> style::Color {
+    let [r, g, b, _] = color.to_srgba().to_u8_array();
+    style::Color::Rgb { r, g, b }
+}
+
+fn draw(out: &mut impl Write, game: &Game) -> io::Result<()> {
+    queue!(
+        out,
+        terminal::Clear(ClearType::All),
+        cursor::MoveTo(0, 0),
+        Print(format!("2048    Score: {}", game.score)),
+    )?;
+
+    let topology = game.board.topology();
+    let top_row = (0..game.board.len())
+        .map(|index| topology.coords(index).y)
+        .max()
+        .unwrap_or(0);
+    for (index, cell) in game.board.iter().enumerate() {
+        draw_cell(out, topology, top_row, index, *cell)?;
+    }
+
+    let rows = top_row as u16 + 1;
+    let status = match game.phase {
+        Phase::Playing => "",
+        Phase::Won => "You Win!   c: continue",
+        Phase::GameOver => "Game Over",
+    };
+    queue!(
+        out,
+        ResetColor,
+        cursor::MoveTo(0, HEADER_ROWS + rows * (CELL_HEIGHT + 1)),
+        Print(status),
+        cursor::MoveToNextLine(1),
+        Print("arrows / wasd / hjkl: move   u: undo   n: new game   q: quit"),
+    )?;
+    out.flush()
+}
+
+fn draw_cell(
+    out: &mut impl Write,
+    topology: Topology,
+    top_row: i32,
+    index: usize,
+    exp: Option<NonZero<u8>>,
+) -> io::Result<()> {
+    // 盤面座標は y が上向きなので、画面では上の行から描く
+    let coords = topology.coords(index);
+    let left = coords.x as u16 * (CELL_WIDTH + 1);
+    let top = HEADER_ROWS + (top_row - coords.y) as u16 * (CELL_HEIGHT + 1);
+    let label = exp.map_or_else(String::new, |exp| exp_to_value(exp.get()).to_string());
+
+    queue!(
+        out,
+        SetBackgroundColor(terminal_color(tile_color(exp))),
+        SetForegroundColor(terminal_color(text_color(exp))),
+    )?;
+    for line in 0..CELL_HEIGHT {
+        let text = if line == CELL_HEIGHT / 2 {
+            label.as_str()
+        } else {
+            ""
+        };
+        queue!(
+            out,
+            cursor::MoveTo(left, top + line),
+            Print(format!("{text:^width$}", width = CELL_WIDTH as usize)),
+        )?;
+    }
+    queue!(out, ResetColor)
+}
+
+fn run(out: &mut impl Write) -> io::Result<()> {
+    let mut game = Game::new();
+    loop {
+        draw(out, &game)?;
+
+        // リサイズなどキー以外のイベントでは描き直すだけ
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        else {
+            continue;
+        };
+
+        match code {
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('u') | KeyCode::Backspace => game.undo(),
+            KeyCode::Char('n') => game = Game::new(),
+            KeyCode::Char('c') => game.continue_playing(),
+            code => {
+                if let Some(direction) = key_direction(code) {
+                    game.slide(direction);
+                }
+            }
+        }
+    }
+}
+
+/// raw モードと代替スクリーンを有効にし、終了時（パニック時も含む）に元へ戻す
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn main() -> io::Result<()> {
+    let _guard = TerminalGuard::enter()?;
+    run(&mut io::stdout())
+}
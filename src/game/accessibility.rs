@@ -0,0 +1,272 @@
+//! 色覚の多様性への配慮。1 型（P）・2 型（D）・3 型（T）色覚でも隣り合う値を見分けやすいタイルの配色と、
+//! 色に頼らずに値を見分けるための目印（タイル上端に並ぶ形と数）を選べるようにする。
+
+use std::num::NonZero;
+
+use bevy::prelude::*;
+
+use super::board::Board;
+use super::render::{VisualTile, themed_cell_size};
+use super::theme::Theme;
+
+/// 配色の明るさの範囲（Oklab の L）。値が大きいほど暗くなるので、白黒でも順に並ぶ
+const RAMP_LIGHTNESS: std::ops::RangeInclusive<f32> = 0.93..=0.40;
+const RAMP_CHROMA: f32 = 0.13;
+const RAMP_STEPS: usize = 11;
+/// この値以下のタイルは、明るい配色の上なので暗い文字で書く
+const RAMP_DARK_TEXT_UP_TO: u32 = 32;
+const RAMP_TEXT_DARK: Color = Color::srgb(0.12, 0.12, 0.12);
+const RAMP_TEXT_LIGHT: Color = Color::WHITE;
+
+/// 目印の大きさと間隔
+const CUE_SIZE: f32 = 9.0;
+const CUE_SPACING: f32 = 13.0;
+/// タイルの上端から目印の中心までの距離
+const CUE_MARGIN: f32 = 13.0;
+const CUE_ALPHA: f32 = 0.85;
+
+/// タイルの配色
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TilePalette {
+    /// テーマの配色のまま
+    #[default]
+    Theme,
+    /// 1 型色覚（赤を感じにくい）向け。青と黄の 2 系統で塗り分ける
+    Protanopia,
+    /// 2 型色覚（緑を感じにくい）向け。青と橙の 2 系統で塗り分ける
+    Deuteranopia,
+    /// 3 型色覚（青を感じにくい）向け。赤と青緑の 2 系統で塗り分ける
+    Tritanopia,
+}
+
+impl TilePalette {
+    pub(super) fn label(self) -> &'static str {
+        match self {
+            Self::Theme => "Colors",
+            Self::Protanopia => "Protan",
+            Self::Deuteranopia => "Deutan",
+            Self::Tritanopia => "Tritan",
+        }
+    }
+
+    /// 交互に使う 2 つの色相（度）。その色覚でも見分けられる向きに選ぶ
+    fn hues(self) -> Option<[f32; 2]> {
+        match self {
+            Self::Theme => None,
+            Self::Protanopia => Some([245.0, 95.0]),
+            Self::Deuteranopia => Some([255.0, 60.0]),
+            Self::Tritanopia => Some([20.0, 195.0]),
+        }
+    }
+
+    /// 2, 4, 8, …, 2048 の色。隣り合う値は色相の系統を変え、明るさは値が大きいほど暗くする
+    fn ramp(self) -> Option<Vec<Color>> {
+        let hues = self.hues()?;
+        let (start, end) = (*RAMP_LIGHTNESS.start(), *RAMP_LIGHTNESS.end());
+        let colors = (0..RAMP_STEPS)
+            .map(|step| {
+                let t = step as f32 / (RAMP_STEPS - 1) as f32;
+                let lightness = start + (end - start) * t;
+                // 明るい端と暗い端は sRGB に収まらないので彩度を落とす
+                let chroma = RAMP_CHROMA * (1.0 - (2.0 * t - 1.0).powi(2) * 0.5);
+                to_gamut(Oklcha::lch(lightness, chroma, hues[step % 2]).into())
+            })
+            .collect();
+        Some(colors)
+    }
+}
+
+/// sRGB の範囲に収める
+fn to_gamut(color: Color) -> Color {
+    let srgba = color.to_srgba();
+    Color::srgb(
+        srgba.red.clamp(0.0, 1.0),
+        srgba.green.clamp(0.0, 1.0),
+        srgba.blue.clamp(0.0, 1.0),
+    )
+}
+
+/// 色覚に合わせた配色と、色以外の目印の設定。
+/// `GamePlugin` を追加する前にリソースとして挿入すれば既定値を変えられる
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct TileAccessibility {
+    pub palette: TilePalette,
+    /// タイルの上端に値ごとに違う形と数の目印を出す
+    pub cues: bool,
+}
+
+impl TileAccessibility {
+    /// テーマのタイルの配色を、選んだ配色で置き換える
+    pub(super) fn apply(&self, theme: &mut Theme) {
+        let Some(ramp) = self.palette.ramp() else {
+            return;
+        };
+        theme.tile_beyond = to_gamut(Oklcha::lch(0.32, 0.06, 300.0).into());
+        theme.tiles = ramp;
+        theme.text_dark = RAMP_TEXT_DARK;
+        theme.text_light = RAMP_TEXT_LIGHT;
+        theme.dark_text_up_to = RAMP_DARK_TEXT_UP_TO;
+    }
+}
+
+/// 目印の形
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum CueShape {
+    Square,
+    Bar,
+    Diamond,
+}
+
+/// 値ごとの目印。形 3 種類 × 1〜4 個で、2 から 4096 までがすべて違う
+fn tile_cue(exp: NonZero<u8>) -> (CueShape, usize) {
+    let index = exp.get() as usize - 1;
+    let shape = match (index / 4) % 3 {
+        0 => CueShape::Square,
+        1 => CueShape::Bar,
+        _ => CueShape::Diamond,
+    };
+    (shape, index % 4 + 1)
+}
+
+#[derive(Component)]
+pub(super) struct TileCue;
+
+fn cue_sprite(shape: CueShape, color: Color) -> (Sprite, Quat) {
+    let (size, rotation) = match shape {
+        CueShape::Square => (Vec2::splat(CUE_SIZE), Quat::IDENTITY),
+        CueShape::Bar => (Vec2::new(CUE_SIZE * 0.45, CUE_SIZE * 1.4), Quat::IDENTITY),
+        CueShape::Diamond => (
+            Vec2::splat(CUE_SIZE * 0.8),
+            Quat::from_rotation_z(std::f32::consts::FRAC_PI_4),
+        ),
+    };
+    let sprite = Sprite {
+        color: color.with_alpha(CUE_ALPHA),
+        custom_size: Some(size),
+        ..default()
+    };
+    (sprite, rotation)
+}
+
+/// タイルの目印を付け直す。値が変わったタイルと新しいタイルはその場で、
+/// 設定やテーマが変わったらすべてのタイルを付け直す
+pub(super) fn sync_tile_cues(
+    mut commands: Commands,
+    settings: Res<TileAccessibility>,
+    theme: Res<Theme>,
+    fields: Query<&Board>,
+    tiles: Query<(Entity, Ref<VisualTile>, &ChildOf, Option<&Children>)>,
+    cues: Query<(), With<TileCue>>,
+) {
+    let all = settings.is_changed() || theme.is_changed();
+    for (entity, tile, child_of, children) in &tiles {
+        if !all && !tile.is_changed() {
+            continue;
+        }
+        for child in children.into_iter().flatten() {
+            if cues.contains(*child) {
+                commands.entity(*child).despawn();
+            }
+        }
+        if !settings.cues {
+            continue;
+        }
+        let Ok(board) = fields.get(child_of.parent()) else {
+            continue;
+        };
+
+        let (shape, count) = tile_cue(tile.exp);
+        let top = themed_cell_size(board.topology(), &theme).y / 2.0 - CUE_MARGIN;
+        let color = theme.text(tile.exp);
+        for i in 0..count {
+            let x = (i as f32 - (count as f32 - 1.0) / 2.0) * CUE_SPACING;
+            let (sprite, rotation) = cue_sprite(shape, color);
+            commands.spawn((
+                TileCue,
+                ChildOf(entity),
+                sprite,
+                Transform::from_xyz(x, top, 0.5).with_rotation(rotation),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Machado ら（2009）の色覚シミュレーション行列（重症度 1.0、線形 RGB）
+    fn simulate(palette: TilePalette, color: Color) -> Color {
+        let matrix = match palette {
+            TilePalette::Theme => return color,
+            TilePalette::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            TilePalette::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            TilePalette::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        };
+        let linear = color.to_linear();
+        let rgb = [linear.red, linear.green, linear.blue];
+        let [r, g, b] =
+            matrix.map(|row| (row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]).clamp(0.0, 1.0));
+        LinearRgba::rgb(r, g, b).into()
+    }
+
+    fn distance(a: Color, b: Color) -> f32 {
+        let (a, b) = (Oklaba::from(a), Oklaba::from(b));
+        Vec3::new(a.lightness - b.lightness, a.a - b.a, a.b - b.b).length()
+    }
+
+    const PALETTES: [TilePalette; 3] = [
+        TilePalette::Protanopia,
+        TilePalette::Deuteranopia,
+        TilePalette::Tritanopia,
+    ];
+
+    #[test]
+    fn adjacent_values_stay_apart_for_the_target_vision() {
+        for palette in PALETTES {
+            let ramp = palette.ramp().unwrap();
+            for pair in ramp.windows(2) {
+                let d = distance(simulate(palette, pair[0]), simulate(palette, pair[1]));
+                assert!(d > 0.1, "{palette:?}: {pair:?} too close ({d})");
+            }
+        }
+    }
+
+    #[test]
+    fn ramps_darken_monotonically_for_grayscale() {
+        for palette in PALETTES {
+            let lightness: Vec<f32> = palette
+                .ramp()
+                .unwrap()
+                .iter()
+                .map(|color| Oklaba::from(*color).lightness)
+                .collect();
+            assert!(
+                lightness.windows(2).all(|pair| pair[0] > pair[1]),
+                "{palette:?}: {lightness:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn cues_differ_for_every_value_up_to_4096() {
+        let cues: Vec<_> = (1..=12)
+            .map(|exp| tile_cue(NonZero::new(exp).unwrap()))
+            .collect();
+        for (i, a) in cues.iter().enumerate() {
+            assert!(cues[i + 1..].iter().all(|b| a != b), "{a:?} repeats");
+        }
+    }
+}
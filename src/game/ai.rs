@@ -0,0 +1,228 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+
+use super::animation::AnimationPhase;
+use super::board::{Board, Direction};
+use super::input::ActionInput;
+use super::keybindings::Action;
+use super::ntuple::NTupleNetwork;
+use super::session::Player;
+use super::spawner::evaluate;
+use super::tablebase::{Objective, Tablebase};
+use super::topology::Topology;
+use super::ui::HintText;
+
+/// `train` ツールが出力する学習済みの重み
+const WEIGHTS_PATH: &str = "ai/weights.ntuple";
+/// `solve` ツールが出力する 3×3 盤面の完全解析テーブル。
+/// 大きいので同梱せず、手元で作ったときだけ Mini の盤面で読み込む
+const TABLEBASE_PATH: &str = "ai/3x3.tablebase";
+
+/// 学習済みの n-tuple ネットワーク
+#[derive(Asset, TypePath)]
+pub(super) struct NTupleWeights(NTupleNetwork);
+
+#[derive(Default, TypePath)]
+pub(super) struct NTupleWeightsLoader;
+
+impl AssetLoader for NTupleWeightsLoader {
+    type Asset = NTupleWeights;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        NTupleNetwork::read_from(&mut bytes.as_slice()).map(NTupleWeights)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ntuple"]
+    }
+}
+
+/// 小さな盤面の完全解析テーブル
+#[derive(Asset, TypePath)]
+pub(super) struct TablebaseAsset(Tablebase);
+
+#[derive(Default, TypePath)]
+pub(super) struct TablebaseLoader;
+
+impl AssetLoader for TablebaseLoader {
+    type Asset = TablebaseAsset;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Tablebase::read_from(&mut bytes.as_slice()).map(TablebaseAsset)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tablebase"]
+    }
+}
+
+#[derive(Resource)]
+pub(super) struct HintWeights {
+    network: Handle<NTupleWeights>,
+    /// Mini の盤面が出るまでは読み込まない
+    tablebase: Option<Handle<TablebaseAsset>>,
+}
+
+pub(super) fn load_hint_weights(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(HintWeights {
+        network: asset_server.load(WEIGHTS_PATH),
+        tablebase: None,
+    });
+}
+
+/// Mini の盤面が出たら完全解析テーブルを読み込む。
+/// ファイルがなければ学習済みの重みと静的評価でヒントを出す
+pub(super) fn load_tablebase_for_mini(
+    mut weights: ResMut<HintWeights>,
+    asset_server: Res<AssetServer>,
+    boards: Query<&Board, Added<Board>>,
+) {
+    if weights.tablebase.is_some()
+        || !boards
+            .iter()
+            .any(|board| board.topology() == Topology::MINI)
+        || !tablebase_exists()
+    {
+        return;
+    }
+    weights.tablebase = Some(asset_server.load(TABLEBASE_PATH));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn tablebase_exists() -> bool {
+    use bevy::asset::io::file::FileAssetReader;
+
+    FileAssetReader::get_base_path()
+        .join("assets")
+        .join(TABLEBASE_PATH)
+        .is_file()
+}
+
+/// Web 版では存在を確かめられず、取りに行くと 404 になるので使わない
+#[cfg(target_arch = "wasm32")]
+fn tablebase_exists() -> bool {
+    false
+}
+
+/// 次の一手の候補。完全解析テーブルに載っている局面なら期待スコア最大の手を、
+/// 盤面に合う学習済みの重みがあればそれを使い、
+/// なければ得点と静的評価（空きセル・隣接タイルの近さ）で選ぶ
+pub(super) fn suggest_move(
+    board: &Board,
+    tablebase: Option<&Tablebase>,
+    network: Option<&NTupleNetwork>,
+) -> Option<Direction> {
+    if let Some(direction) = tablebase.and_then(|table| table.best_move(board, Objective::Score)) {
+        return Some(direction);
+    }
+    if let Some(network) = network.filter(|network| network.supports(board.topology())) {
+        return network.best_move(board).map(|(direction, _)| direction);
+    }
+
+    board
+        .topology()
+        .directions()
+        .iter()
+        .filter_map(|&direction| {
+            let result = board.compute_slide(direction);
+            result.changed.then(|| {
+                let value = result.score_gained as f32 + evaluate(&result.new_board);
+                (direction, value)
+            })
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(direction, _)| direction)
+}
+
+fn direction_label(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "Up",
+        Direction::Down => "Down",
+        Direction::Left => "Left",
+        Direction::Right => "Right",
+        Direction::UpLeft => "Up-Left",
+        Direction::UpRight => "Up-Right",
+        Direction::DownLeft => "Down-Left",
+        Direction::DownRight => "Down-Right",
+    }
+}
+
+/// ヒントキーが押されたら、止まっている盤面ごとに次の一手を表示する
+pub(super) fn show_hint(
+    input: ActionInput,
+    weights: Res<HintWeights>,
+    networks: Res<Assets<NTupleWeights>>,
+    tablebases: Res<Assets<TablebaseAsset>>,
+    fields: Query<(&Player, &Board, &AnimationPhase)>,
+    mut hints: Query<(&mut Text, &mut Visibility), With<HintText>>,
+) {
+    if !input.just_pressed(Action::Hint) {
+        return;
+    }
+
+    let network = networks.get(&weights.network).map(|weights| &weights.0);
+    let tablebase = weights
+        .tablebase
+        .as_ref()
+        .and_then(|handle| tablebases.get(handle))
+        .map(|table| &table.0);
+    let mut fields: Vec<_> = fields
+        .iter()
+        .filter(|(_, _, phase)| **phase == AnimationPhase::Idle)
+        .collect();
+    fields.sort_by_key(|(player, ..)| **player);
+
+    let versus = fields.len() > 1;
+    let line = fields
+        .iter()
+        .filter_map(|(player, board, _)| {
+            let label = direction_label(suggest_move(board, tablebase, network)?);
+            Some(if versus {
+                format!("{}: {label}", player.label())
+            } else {
+                format!("Hint: {label}")
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("  ");
+    if line.is_empty() {
+        return;
+    }
+
+    for (mut text, mut visibility) in &mut hints {
+        text.0 = line.clone();
+        *visibility = Visibility::Inherited;
+    }
+}
+
+/// 盤面が動いたら古いヒントを隠す
+pub(super) fn clear_hint_on_move(
+    moved: Query<(), Changed<Board>>,
+    mut hints: Query<&mut Visibility, With<HintText>>,
+) {
+    if moved.is_empty() {
+        return;
+    }
+    for mut visibility in &mut hints {
+        visibility.set_if_neq(Visibility::Hidden);
+    }
+}
@@ -1,29 +1,211 @@
+use bevy::ecs::entity::EntityHashSet;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task, futures::check_ready};
 use rand::rng;
 
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZero;
+
 use super::GameFont;
-use super::board::{Board, Score, SlideResult};
+use super::board::{Board, Direction, Score, SlideResult, non_zero_exp};
 use super::input::Slide;
-use super::render::{VisualTile, board_index_to_position, spawn_visual_tile};
+use super::render::{
+    CellBackground, HexagonImage, VisualTile, board_index_to_position, spawn_visual_tile,
+};
+use super::session::{MoveLog, UndoHistory};
+use super::skin::TileSkin;
+use super::spawner::Spawner;
+use super::topology::Topology;
 
 use std::time::Duration;
 
 const SLIDE_DURATION: f32 = 0.08;
 const EFFECT_DURATION: f32 = 0.05;
-const MERGE_SCALE_PEAK: f32 = 1.2;
+pub(super) const MERGE_SCALE_PEAK: f32 = 1.2;
 /// Reactive モードでアイドル後の大きな delta がアニメーションを一瞬で完了させるのを防ぐ
 const MAX_ANIM_DELTA: Duration = Duration::from_millis(16);
+/// 再生速度の倍率の下限（0 ではアニメーションが終わらなくなる）
+const MIN_SPEED: f32 = 0.1;
 
-#[derive(Resource, Default, PartialEq, Debug)]
+#[derive(Component, Default, PartialEq, Debug)]
 pub(super) enum AnimationPhase {
     #[default]
     Idle,
     Sliding,
+    /// ホットシートモードで、もう一人のプレイヤーが次のタイルを置くのを待っている
+    AwaitingPlacement,
     Settling,
 }
 
+impl AnimationPhase {
+    /// タイルが動いている最中か（配置待ちはアニメーションしない）
+    pub(super) fn is_animating(&self) -> bool {
+        matches!(self, Self::Sliding | Self::Settling)
+    }
+}
+
+#[derive(Component, Default)]
+pub(super) struct PendingSlide(Option<(Direction, SlideResult)>);
+
+/// アニメーション中に届いた入力の扱い。
+/// `GamePlugin` を追加する前にリソースとして挿入すれば既定値を変えられる
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct InputBuffer {
+    /// 盤面ごとに溜めておける手数。0 ならアニメーション中の入力は捨てる
+    pub depth: usize,
+    /// 次の手が溜まっていれば、再生中のアニメーションを待たずに終わらせる
+    pub fast_forward: bool,
+}
+
+impl Default for InputBuffer {
+    fn default() -> Self {
+        Self {
+            depth: 2,
+            fast_forward: true,
+        }
+    }
+}
+
+/// アニメーションの見せ方。
+/// `GamePlugin` を追加する前にリソースとして挿入すれば既定値を変えられる
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct AnimationSettings {
+    /// 再生速度の倍率（2.0 で倍速）
+    pub speed: f32,
+    /// スライドと出現のイージング
+    pub easing: EaseFunction,
+    /// アニメーションせず、入力した瞬間に盤面を更新する
+    pub instant: bool,
+    /// マージと出現の拡大縮小をやめる。`None` なら OS の「動きを減らす」設定に従う
+    pub reduced_motion: Option<bool>,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            easing: EaseFunction::CubicOut,
+            instant: false,
+            reduced_motion: None,
+        }
+    }
+}
+
+/// OS で「動きを減らす」が有効になっているか（起動時に 1 回だけ調べる）。
+/// 調べ終わるまでは動きを減らさない
 #[derive(Resource, Default)]
-pub(super) struct PendingSlide(Option<SlideResult>);
+pub(super) struct SystemReducedMotion(bool);
+
+/// 調べている途中の OS の設定。外部コマンドを待つので起動を止めないよう IO 用のスレッドで調べる
+#[derive(Resource)]
+pub(super) struct ReducedMotionQuery(Task<bool>);
+
+pub(super) fn query_system_reduced_motion(mut commands: Commands) {
+    let task = IoTaskPool::get().spawn(async { system_prefers_reduced_motion() });
+    commands.insert_resource(ReducedMotionQuery(task));
+}
+
+pub(super) fn receive_system_reduced_motion(
+    mut commands: Commands,
+    mut query: ResMut<ReducedMotionQuery>,
+    mut system: ResMut<SystemReducedMotion>,
+) {
+    if let Some(reduced) = check_ready(&mut query.0) {
+        system.0 = reduced;
+        commands.remove_resource::<ReducedMotionQuery>();
+    }
+}
+
+/// GNOME の「アニメーション」がオフになっているか
+#[cfg(target_os = "linux")]
+fn system_prefers_reduced_motion() -> bool {
+    std::process::Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "enable-animations"])
+        .output()
+        .is_ok_and(|output| output.stdout.trim_ascii() == b"false")
+}
+
+/// macOS の「視差効果を減らす」が有効か
+#[cfg(target_os = "macos")]
+fn system_prefers_reduced_motion() -> bool {
+    std::process::Command::new("defaults")
+        .args(["read", "com.apple.universalaccess", "reduceMotion"])
+        .output()
+        .is_ok_and(|output| output.stdout.trim_ascii() == b"1")
+}
+
+/// ブラウザの `prefers-reduced-motion` メディアクエリ
+#[cfg(target_arch = "wasm32")]
+fn system_prefers_reduced_motion() -> bool {
+    web_sys::window()
+        .and_then(|window| {
+            window
+                .match_media("(prefers-reduced-motion: reduce)")
+                .ok()
+                .flatten()
+        })
+        .is_some_and(|query| query.matches())
+}
+
+/// 設定を読み取れないプラットフォームでは動きを減らさない
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_arch = "wasm32")))]
+fn system_prefers_reduced_motion() -> bool {
+    false
+}
+
+/// アニメーションの設定を OS の設定と合わせて読む
+#[derive(SystemParam)]
+pub(super) struct Motion<'w> {
+    settings: Res<'w, AnimationSettings>,
+    system: Res<'w, SystemReducedMotion>,
+}
+
+impl Motion<'_> {
+    /// 今回のフレームで進める時間
+    pub(super) fn delta(&self, time: &Time) -> Duration {
+        time.delta()
+            .min(MAX_ANIM_DELTA)
+            .mul_f32(self.settings.speed.max(MIN_SPEED))
+    }
+
+    pub(super) fn ease(&self, t: f32) -> f32 {
+        self.settings.easing.sample_clamped(t)
+    }
+
+    pub(super) fn instant(&self) -> bool {
+        self.settings.instant
+    }
+
+    pub(super) fn reduced(&self) -> bool {
+        self.settings.reduced_motion.unwrap_or(self.system.0)
+    }
+}
+
+/// 盤面が止まるのを待っているスライド
+#[derive(Component, Default)]
+pub(super) struct SlideQueue(VecDeque<Direction>);
+
+impl SlideQueue {
+    /// 溜めていた入力を捨てる
+    pub(super) fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// 早送りでアニメーションを飛ばすか
+    fn skips(&self, buffer: &InputBuffer) -> bool {
+        buffer.fast_forward && !self.0.is_empty()
+    }
+}
+
+/// 1 手で起きたマージと、その手で増えたスコア
+#[derive(Message)]
+pub(super) struct SlideScored {
+    pub(super) field: Entity,
+    /// マージ先のボードインデックスと、マージ後の指数
+    pub(super) merges: Vec<(usize, NonZero<u8>)>,
+    pub(super) gained: u32,
+}
 
 #[derive(Component)]
 pub(super) struct SlideAnim {
@@ -38,173 +220,409 @@ pub(super) struct MergeAnim(Timer);
 #[derive(Component)]
 pub(super) struct SpawnAnim(Timer);
 
-fn ease_out_cubic(t: f32) -> f32 {
-    1.0 - (1.0 - t).powi(3)
+/// タイマーを進める。`skip` なら一気に終わらせる
+fn advance(timer: &mut Timer, delta: Duration, skip: bool) {
+    if skip {
+        timer.finish();
+    } else {
+        timer.tick(delta);
+    }
 }
 
-/// Slide メッセージを受け取り、アニメーションを開始する
+/// Slide メッセージを受け取り、対象の盤面のアニメーションを開始する。
+/// アニメーション中の盤面への入力は `InputBuffer` の手数まで溜めておき、止まってから順に消化する
 pub(super) fn prepare_slide(
     mut move_reader: MessageReader<Slide>,
-    board: Res<Board>,
-    mut phase: ResMut<AnimationPhase>,
-    mut pending: ResMut<PendingSlide>,
-    tiles: Query<(Entity, &VisualTile)>,
+    motion: Motion,
+    buffer: Res<InputBuffer>,
+    mut fields: Query<(
+        Entity,
+        &Board,
+        &mut AnimationPhase,
+        &mut PendingSlide,
+        &mut SlideQueue,
+    )>,
+    tiles: Query<(Entity, &VisualTile, &ChildOf, &Transform)>,
     mut commands: Commands,
 ) {
-    if *phase != AnimationPhase::Idle {
-        move_reader.read().for_each(drop);
-        return;
+    // 動かない手は飛ばして、溜まっている入力を古い順に試す
+    for (field, board, mut phase, mut pending, mut queue) in &mut fields {
+        while *phase == AnimationPhase::Idle
+            && let Some(direction) = queue.0.pop_front()
+        {
+            if let Some(result) =
+                begin_slide(&mut commands, &tiles, &motion, field, board, direction)
+            {
+                pending.0 = Some((direction, result));
+                *phase = AnimationPhase::Sliding;
+            }
+        }
     }
 
-    for Slide(direction) in move_reader.read() {
-        let result = board.compute_slide(*direction);
-        if !result.changed {
+    for &Slide { field, direction } in move_reader.read() {
+        let Ok((_, board, mut phase, mut pending, mut queue)) = fields.get_mut(field) else {
+            continue;
+        };
+        if *phase != AnimationPhase::Idle {
+            // 溢れた入力は捨てる
+            if queue.0.len() < buffer.depth {
+                queue.0.push_back(direction);
+            }
             continue;
         }
 
-        for movement in &result.movements {
-            for (entity, tile) in &tiles {
-                if tile.board_index == movement.from {
-                    let from_pos = board_index_to_position(movement.from);
-                    let to_pos = board_index_to_position(movement.to);
-
-                    if from_pos != to_pos {
-                        commands.entity(entity).insert(SlideAnim {
-                            from: from_pos,
-                            to: to_pos,
-                            timer: Timer::from_seconds(SLIDE_DURATION, TimerMode::Once),
-                        });
-                    }
-                    break;
-                }
-            }
+        if let Some(result) = begin_slide(&mut commands, &tiles, &motion, field, board, direction) {
+            pending.0 = Some((direction, result));
+            *phase = AnimationPhase::Sliding;
         }
+    }
+}
+
+/// 盤面が動くなら、動くタイルにスライドアニメーションを付けて結果を返す。
+/// スワイプのプレビューで途中まで動いているタイルは、今の位置から動かす。
+/// アニメーションしない設定では何も付けず、同じフレームの `resolve_slide` がそのまま確定させる
+fn begin_slide(
+    commands: &mut Commands,
+    tiles: &Query<(Entity, &VisualTile, &ChildOf, &Transform)>,
+    motion: &Motion,
+    field: Entity,
+    board: &Board,
+    direction: Direction,
+) -> Option<SlideResult> {
+    let result = board.compute_slide(direction);
+    if !result.changed || motion.instant() {
+        return result.changed.then_some(result);
+    }
+
+    let field_tiles: HashMap<usize, (Entity, Vec2)> = tiles
+        .iter()
+        .filter(|(_, _, child_of, _)| child_of.parent() == field)
+        .map(|(entity, tile, _, transform)| {
+            (tile.board_index, (entity, transform.translation.truncate()))
+        })
+        .collect();
+
+    for movement in &result.movements {
+        let Some(&(entity, from_pos)) = field_tiles.get(&movement.from) else {
+            continue;
+        };
+        let to_pos = board_index_to_position(board.topology(), movement.to);
+        if from_pos != to_pos {
+            commands.entity(entity).insert(SlideAnim {
+                from: from_pos,
+                to: to_pos,
+                timer: Timer::from_seconds(SLIDE_DURATION, TimerMode::Once),
+            });
+        }
+    }
+
+    Some(result)
+}
 
-        pending.0 = Some(result);
-        *phase = AnimationPhase::Sliding;
-        break;
+/// ゲームが止まったら（一時停止・決着など）溜めていた入力を捨てる
+pub(super) fn clear_slide_queues(mut queues: Query<&mut SlideQueue>) {
+    for mut queue in &mut queues {
+        queue.clear();
     }
 }
 
 /// スライドアニメーションを進行する
 pub(super) fn animate_slide(
     time: Res<Time>,
-    phase: Res<AnimationPhase>,
-    mut tiles: Query<(&mut Transform, &mut SlideAnim)>,
+    motion: Motion,
+    buffer: Res<InputBuffer>,
+    fields: Query<(&AnimationPhase, &SlideQueue)>,
+    mut tiles: Query<(&ChildOf, &mut Transform, &mut SlideAnim)>,
 ) {
-    if *phase != AnimationPhase::Sliding {
-        return;
-    }
+    let delta = motion.delta(&time);
+    for (child_of, mut transform, mut anim) in &mut tiles {
+        let Ok((phase, queue)) = fields.get(child_of.parent()) else {
+            continue;
+        };
+        if *phase != AnimationPhase::Sliding {
+            continue;
+        }
 
-    let delta = time.delta().min(MAX_ANIM_DELTA);
-    for (mut transform, mut anim) in &mut tiles {
-        anim.timer.tick(delta);
-        let t = ease_out_cubic(anim.timer.fraction());
+        let skip = motion.instant() || queue.skips(&buffer);
+        advance(&mut anim.timer, delta, skip);
+        let t = motion.ease(anim.timer.fraction());
         let pos = anim.from.lerp(anim.to, t);
         transform.translation.x = pos.x;
         transform.translation.y = pos.y;
     }
 }
 
-/// スライド完了後に Board を更新し、マージ/出現エフェクトを開始する
+/// 出現アニメーション付きでタイルをスポーンする。`animate` でなければ等倍で置く
+fn spawn_appearing_tile(
+    commands: &mut Commands,
+    field: Entity,
+    topology: Topology,
+    index: usize,
+    exp: NonZero<u8>,
+    animate: bool,
+    font: &GameFont,
+    hexagon: &HexagonImage,
+    skin: &TileSkin,
+) {
+    let scale = if animate { Vec3::ZERO } else { Vec3::ONE };
+    let entity = spawn_visual_tile(
+        commands, field, topology, index, exp, scale, font, hexagon, skin,
+    );
+    if animate {
+        commands
+            .entity(entity)
+            .insert(SpawnAnim(Timer::from_seconds(
+                EFFECT_DURATION,
+                TimerMode::Once,
+            )));
+    }
+}
+
+/// スライド完了後に Board を更新し、マージ/出現エフェクトを開始する。
+/// タイルのエンティティは使い回し、マージで吸収されたタイルだけをここで消す。
+/// アニメーションしない設定では、タイルを移動先に置いてエフェクトなしで止まった状態に戻す
 pub(super) fn resolve_slide(
     mut commands: Commands,
-    mut phase: ResMut<AnimationPhase>,
-    mut board: ResMut<Board>,
-    mut score: ResMut<Score>,
-    mut pending: ResMut<PendingSlide>,
+    motion: Motion,
+    mut fields: Query<(
+        Entity,
+        &mut Board,
+        &mut Score,
+        &mut AnimationPhase,
+        &mut PendingSlide,
+        &mut UndoHistory,
+        &mut MoveLog,
+        &Spawner,
+    )>,
     font: Res<GameFont>,
-    tiles_with_anim: Query<&SlideAnim, With<VisualTile>>,
-    all_tiles: Query<Entity, With<VisualTile>>,
+    hexagon: Res<HexagonImage>,
+    skin: Res<TileSkin>,
+    mut tiles: Query<(
+        Entity,
+        &ChildOf,
+        &mut VisualTile,
+        &mut Transform,
+        Option<&SlideAnim>,
+    )>,
+    mut scored: MessageWriter<SlideScored>,
 ) {
-    if *phase != AnimationPhase::Sliding {
-        return;
-    }
-
-    if tiles_with_anim.iter().any(|anim| !anim.timer.is_finished()) {
-        return;
-    }
+    let animate = !motion.instant();
+    for (field, mut board, mut score, mut phase, mut pending, mut history, mut log, spawner) in
+        &mut fields
+    {
+        if *phase != AnimationPhase::Sliding {
+            continue;
+        }
 
-    let Some(result) = pending.0.take() else {
-        return;
-    };
+        let sliding = tiles.iter().any(|(_, child_of, .., anim)| {
+            child_of.parent() == field && anim.is_some_and(|anim| !anim.timer.is_finished())
+        });
+        if sliding {
+            continue;
+        }
 
-    let merge_dests = result.merge_destinations;
+        let Some((direction, result)) = pending.0.take() else {
+            continue;
+        };
 
-    // Board 更新
-    *board = result.new_board;
-    **score += result.score_gained;
+        // Board 更新（取り消せるように直前の盤面を残す）。出たタイルは置いたときに記録に足す
+        history.push(&board, *score);
+        log.0.push(direction, None);
+        *board = result.new_board;
+        **score += result.score_gained;
 
-    // 既存タイルをすべて削除
-    for entity in &all_tiles {
-        commands.entity(entity).despawn();
-    }
+        // タイルを移動先のセルに付け替える。マージで残るタイルは値を更新してパルスさせる
+        let movements: HashMap<usize, _> = result
+            .movements
+            .iter()
+            .map(|movement| (movement.from, *movement))
+            .collect();
+        for (entity, child_of, mut tile, mut transform, _) in &mut tiles {
+            if child_of.parent() != field {
+                continue;
+            }
+            let Some(movement) = movements.get(&tile.board_index) else {
+                continue;
+            };
+            if movement.consumed {
+                commands.entity(entity).despawn();
+                continue;
+            }
 
-    // 新しいタイルをスポーン（マージ先にはパルスアニメーション）
-    for (index, cell) in board.iter().enumerate() {
-        if let Some(exp) = cell {
-            let entity = spawn_visual_tile(&mut commands, index, *exp, Vec3::ONE, &font);
-            if merge_dests.contains(&index) {
-                commands
-                    .entity(entity)
-                    .insert(MergeAnim(Timer::from_seconds(
-                        EFFECT_DURATION,
-                        TimerMode::Once,
-                    )));
+            tile.board_index = movement.to;
+            commands.entity(entity).remove::<SlideAnim>();
+            if !animate {
+                let to = board_index_to_position(board.topology(), movement.to);
+                transform.translation.x = to.x;
+                transform.translation.y = to.y;
             }
+            if result.merge_destinations.contains(&movement.to)
+                && let Some(exp) = board[movement.to]
+            {
+                // 色と数字は値が変わったのを見て描き直される
+                tile.exp = exp;
+                if animate {
+                    commands
+                        .entity(entity)
+                        .insert(MergeAnim(Timer::from_seconds(
+                            EFFECT_DURATION,
+                            TimerMode::Once,
+                        )));
+                }
+            }
+        }
+        let merges: Vec<_> = result
+            .merge_destinations
+            .iter()
+            .filter_map(|&index| board[index].map(|exp| (index, exp)))
+            .collect();
+        if !merges.is_empty() {
+            scored.write(SlideScored {
+                field,
+                merges,
+                gained: result.score_gained,
+            });
         }
+
+        let topology = board.topology();
+
+        // ホットシートでは、もう一人がセルをクリックするまで新しいタイルを置かない
+        if *spawner == Spawner::Human {
+            *phase = AnimationPhase::AwaitingPlacement;
+            continue;
+        }
+
+        // 新しいタイルを配置（出現アニメーション付き）
+        if let Some(idx) = spawner.place_tile(&mut board, &mut rng())
+            && let Some(exp) = board[idx]
+        {
+            log.0.set_last_spawn(idx, exp);
+            spawn_appearing_tile(
+                &mut commands,
+                field,
+                topology,
+                idx,
+                exp,
+                animate,
+                &font,
+                &hexagon,
+                &skin,
+            );
+        }
+
+        *phase = if animate {
+            AnimationPhase::Settling
+        } else {
+            AnimationPhase::Idle
+        };
     }
+}
 
-    // ランダムタイルを配置（出現アニメーション付き）
-    if let Some(idx) = board.place_random_tile(&mut rng())
-        && let Some(exp) = board[idx]
-    {
-        let entity = spawn_visual_tile(&mut commands, idx, exp, Vec3::ZERO, &font);
-        commands
-            .entity(entity)
-            .insert(SpawnAnim(Timer::from_seconds(
-                EFFECT_DURATION,
-                TimerMode::Once,
-            )));
+/// 配置待ちの盤面で空きセルがクリックされたら、そこに新しいタイルを置く。
+/// 左クリックで 2、右クリックまたは Shift + クリックで 4 を置く。
+pub(super) fn on_cell_click(
+    mut click: On<Pointer<Click>>,
+    mut commands: Commands,
+    motion: Motion,
+    keys: Res<ButtonInput<KeyCode>>,
+    cells: Query<(&CellBackground, &ChildOf)>,
+    mut fields: Query<(&mut Board, &mut AnimationPhase, &mut MoveLog)>,
+    font: Res<GameFont>,
+    hexagon: Res<HexagonImage>,
+    skin: Res<TileSkin>,
+) {
+    let Ok((cell, child_of)) = cells.get(click.entity) else {
+        return;
+    };
+    click.propagate(false);
+
+    let field = child_of.parent();
+    let Ok((mut board, mut phase, mut log)) = fields.get_mut(field) else {
+        return;
+    };
+    if *phase != AnimationPhase::AwaitingPlacement || board[cell.board_index].is_some() {
+        return;
     }
 
-    *phase = AnimationPhase::Settling;
+    let four = click.button == PointerButton::Secondary
+        || keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let exp = non_zero_exp(if four { 2 } else { 1 });
+    board[cell.board_index] = Some(exp);
+    log.0.set_last_spawn(cell.board_index, exp);
+    spawn_appearing_tile(
+        &mut commands,
+        field,
+        board.topology(),
+        cell.board_index,
+        exp,
+        !motion.instant(),
+        &font,
+        &hexagon,
+        &skin,
+    );
+    *phase = if motion.instant() {
+        AnimationPhase::Idle
+    } else {
+        AnimationPhase::Settling
+    };
 }
 
-/// マージパルスと出現アニメーションを進行する
+/// マージパルスと出現アニメーションを進行する。動きを減らす設定ではどちらも等倍で表示する。
+/// タイルは次の手でも使い回すので、終わったエフェクトは外す
 pub(super) fn animate_effects(
+    mut commands: Commands,
     time: Res<Time>,
-    mut phase: ResMut<AnimationPhase>,
-    mut merge_tiles: Query<(&mut Transform, &mut MergeAnim), Without<SpawnAnim>>,
-    mut spawn_tiles: Query<(&mut Transform, &mut SpawnAnim), Without<MergeAnim>>,
+    motion: Motion,
+    buffer: Res<InputBuffer>,
+    mut fields: Query<(Entity, &mut AnimationPhase, &SlideQueue)>,
+    mut merge_tiles: Query<(Entity, &ChildOf, &mut Transform, &mut MergeAnim), Without<SpawnAnim>>,
+    mut spawn_tiles: Query<(Entity, &ChildOf, &mut Transform, &mut SpawnAnim), Without<MergeAnim>>,
 ) {
-    if *phase != AnimationPhase::Settling {
-        return;
-    }
-
-    let delta = time.delta().min(MAX_ANIM_DELTA);
-    let mut all_done = true;
+    let delta = motion.delta(&time);
+    let still = motion.reduced();
+    // エフェクト中の盤面なら、早送りするかどうかを返す
+    let settling = |fields: &Query<(Entity, &mut AnimationPhase, &SlideQueue)>, field: Entity| {
+        fields
+            .get(field)
+            .ok()
+            .filter(|(_, phase, _)| **phase == AnimationPhase::Settling)
+            .map(|(_, _, queue)| still || motion.instant() || queue.skips(&buffer))
+    };
+    let mut unfinished = EntityHashSet::default();
 
-    for (mut transform, mut anim) in &mut merge_tiles {
-        anim.0.tick(delta);
+    for (entity, child_of, mut transform, mut anim) in &mut merge_tiles {
+        let Some(skip) = settling(&fields, child_of.parent()) else {
+            continue;
+        };
+        advance(&mut anim.0, delta, skip);
+        if anim.0.is_finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<MergeAnim>();
+            continue;
+        }
         let t = anim.0.fraction();
         let scale = 1.0 + (MERGE_SCALE_PEAK - 1.0) * (t * std::f32::consts::PI).sin();
         transform.scale = Vec3::splat(scale);
-        if !anim.0.is_finished() {
-            all_done = false;
-        }
+        unfinished.insert(child_of.parent());
     }
 
-    for (mut transform, mut anim) in &mut spawn_tiles {
-        anim.0.tick(delta);
-        let t = ease_out_cubic(anim.0.fraction());
-        transform.scale = Vec3::splat(t);
-        if !anim.0.is_finished() {
-            all_done = false;
+    for (entity, child_of, mut transform, mut anim) in &mut spawn_tiles {
+        let Some(skip) = settling(&fields, child_of.parent()) else {
+            continue;
+        };
+        advance(&mut anim.0, delta, skip);
+        if anim.0.is_finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<SpawnAnim>();
+            continue;
         }
+        transform.scale = Vec3::splat(motion.ease(anim.0.fraction()));
+        unfinished.insert(child_of.parent());
     }
 
-    if all_done {
-        *phase = AnimationPhase::Idle;
+    for (field, mut phase, _) in &mut fields {
+        if *phase == AnimationPhase::Settling && !unfinished.contains(&field) {
+            *phase = AnimationPhase::Idle;
+        }
     }
 }
@@ -2,70 +2,106 @@ use std::{fmt, num::NonZero};
 
 use bevy::prelude::*;
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 
-pub(super) static BOARD_SIZE: usize = 4;
+use super::topology::Topology;
 
-pub(super) fn non_zero_exp(exp: u8) -> NonZero<u8> {
+/// 勝利となるタイルの指数（2^11 = 2048）
+pub const WIN_EXP: u8 = 11;
+
+pub fn non_zero_exp(exp: u8) -> NonZero<u8> {
     NonZero::new(exp).expect("tile exponent must be non-zero")
 }
 
-pub(super) fn exp_to_value(exp: u8) -> u32 {
+pub fn exp_to_value(exp: u8) -> u32 {
     2u32.pow(u32::from(exp))
 }
 
-#[derive(Debug, Clone, Copy)]
-pub(super) struct SlideMovement {
-    pub(super) from: usize,
-    pub(super) to: usize,
+/// 1 枚のタイルの動き。`from` はスライド前の位置で、その手の間はタイルの識別子になる
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlideMovement {
+    pub from: usize,
+    pub to: usize,
+    /// マージで相方に吸収されて消えるタイルか。
+    /// 同じ `to` へ動くもう一方（壁側のタイル）が、値を倍にして残る
+    pub consumed: bool,
 }
 
 #[derive(Debug, Clone)]
-pub(super) struct SlideResult {
-    pub(super) changed: bool,
-    pub(super) movements: Vec<SlideMovement>,
-    pub(super) merge_destinations: Vec<usize>,
-    pub(super) new_board: Board,
-    pub(super) score_gained: u32,
+pub struct SlideResult {
+    pub changed: bool,
+    pub movements: Vec<SlideMovement>,
+    pub merge_destinations: Vec<usize>,
+    pub new_board: Board,
+    pub score_gained: u32,
 }
 
-#[derive(Resource, Default, Clone, Copy, Deref, DerefMut, Reflect, Debug)]
-#[reflect(Resource)]
-pub(super) struct Score(pub(super) u32);
+#[derive(Component, Default, Clone, Copy, Deref, DerefMut, Reflect, Debug)]
+#[reflect(Component)]
+pub struct Score(pub u32);
 
-#[derive(Clone, Copy, Debug)]
-pub(super) enum Direction {
+/// スライド方向。正方格子は上下左右、六角形盤面は上下と斜め 4 方向を使う
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Direction {
     Up,
     Down,
     Left,
     Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
 }
 
 impl Direction {
-    /// スライド先の端が先頭になるよう、行/列 `i` のインデックス列を返す。
-    /// `slide_line` は先頭に向かってタイルを詰めるため、この順序でインデックスを並べる。
-    fn line_indices(&self, i: usize) -> [usize; BOARD_SIZE] {
-        std::array::from_fn(|j| match self {
-            Self::Left => Board::index(j, i),
-            Self::Right => Board::index(BOARD_SIZE - 1 - j, i),
-            Self::Up => Board::index(i, BOARD_SIZE - 1 - j),
-            Self::Down => Board::index(i, j),
-        })
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Up => Self::Down,
+            Self::Down => Self::Up,
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+            Self::UpLeft => Self::DownRight,
+            Self::UpRight => Self::DownLeft,
+            Self::DownLeft => Self::UpRight,
+            Self::DownRight => Self::UpLeft,
+        }
     }
 }
 
-#[derive(Resource, Default, Clone, Deref, DerefMut, Reflect, Debug)]
-#[reflect(Resource)]
-pub(super) struct Board([Option<NonZero<u8>>; BOARD_SIZE * BOARD_SIZE]);
+#[derive(Component, Clone, PartialEq, Eq, Deref, DerefMut, Reflect, Debug)]
+#[reflect(Component)]
+pub struct Board {
+    topology: Topology,
+    #[deref]
+    cells: Vec<Option<NonZero<u8>>>,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new(Topology::default())
+    }
+}
 
 impl Board {
-    pub(super) fn with_two_tiles<R: Rng + ?Sized>(rng: &mut R) -> Self {
-        let mut board = Self::default();
+    pub fn new(topology: Topology) -> Self {
+        Self {
+            topology,
+            cells: vec![None; topology.cell_count()],
+        }
+    }
+
+    pub fn with_two_tiles<R: Rng + ?Sized>(topology: Topology, rng: &mut R) -> Self {
+        let mut board = Self::new(topology);
         board.place_random_tile(rng);
         board.place_random_tile(rng);
         board
     }
 
-    pub(super) fn place_random_tile<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<usize> {
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn place_random_tile<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<usize> {
         let mut selected = None;
         let mut empty_count = 0usize;
         for (index, cell) in self.iter().enumerate() {
@@ -84,42 +120,37 @@ impl Board {
         selected
     }
 
-    fn index(x: usize, y: usize) -> usize {
-        x + y * BOARD_SIZE
+    /// 2048 のタイルに到達したか
+    pub fn has_won(&self) -> bool {
+        self.iter()
+            .any(|cell| cell.map(NonZero::get) == Some(WIN_EXP))
     }
 
-    pub(super) fn can_move(&self) -> bool {
+    pub fn can_move(&self) -> bool {
         if self.iter().any(Option::is_none) {
             return true;
         }
 
-        for x in 0..BOARD_SIZE {
-            for y in 0..BOARD_SIZE {
-                let i = Self::index(x, y);
-                let current = self[i];
-                if x + 1 < BOARD_SIZE && self[i + 1] == current {
-                    return true;
-                }
-                if y + 1 < BOARD_SIZE && self[i + BOARD_SIZE] == current {
-                    return true;
-                }
-            }
-        }
-        false
+        (0..self.len()).any(|index| {
+            self.topology.directions().iter().any(|&direction| {
+                self.topology
+                    .neighbor(index, direction)
+                    .is_some_and(|neighbor| self[neighbor] == self[index])
+            })
+        })
     }
 
-    pub(super) fn compute_slide(&self, direction: Direction) -> SlideResult {
+    pub fn compute_slide(&self, direction: Direction) -> SlideResult {
         let mut new_board = self.clone();
         let mut all_movements = Vec::new();
         let mut all_merge_dests = Vec::new();
         let mut total_score = 0u32;
         let mut changed = false;
 
-        for i in 0..BOARD_SIZE {
-            let indices = direction.line_indices(i);
-            let line = indices.map(|idx| self[idx]);
+        for indices in self.topology.lines(direction) {
+            let line: Vec<_> = indices.iter().map(|&idx| self[idx]).collect();
             let (c, new_line, score, movements, merge_dests) =
-                slide_line_with_movements(line, indices);
+                slide_line_with_movements(&line, indices);
 
             all_movements.extend(movements);
 
@@ -127,7 +158,7 @@ impl Board {
                 changed = true;
                 total_score += score;
                 all_merge_dests.extend(merge_dests);
-                for (idx, value) in indices.into_iter().zip(new_line) {
+                for (&idx, value) in indices.iter().zip(new_line) {
                     new_board[idx] = value;
                 }
             }
@@ -144,11 +175,11 @@ impl Board {
 }
 
 fn slide_line_with_movements(
-    line: [Option<NonZero<u8>>; BOARD_SIZE],
-    indices: [usize; BOARD_SIZE],
+    line: &[Option<NonZero<u8>>],
+    indices: &[usize],
 ) -> (
     bool,
-    [Option<NonZero<u8>>; BOARD_SIZE],
+    Vec<Option<NonZero<u8>>>,
     u32,
     Vec<SlideMovement>,
     Vec<usize>,
@@ -159,7 +190,7 @@ fn slide_line_with_movements(
         .filter_map(|(cell, &idx)| cell.map(|v| (v, idx)))
         .collect();
 
-    let mut result = [None; BOARD_SIZE];
+    let mut result = vec![None; line.len()];
     let mut score = 0u32;
     let mut movements = Vec::new();
     let mut merge_dests = Vec::new();
@@ -179,10 +210,12 @@ fn slide_line_with_movements(
             movements.push(SlideMovement {
                 from: orig_idx,
                 to: dest,
+                consumed: false,
             });
             movements.push(SlideMovement {
                 from: orig_idx2,
                 to: dest,
+                consumed: true,
             });
             merge_dests.push(dest);
 
@@ -192,6 +225,7 @@ fn slide_line_with_movements(
             movements.push(SlideMovement {
                 from: orig_idx,
                 to: dest,
+                consumed: false,
             });
             i += 1;
         }
@@ -203,9 +237,13 @@ fn slide_line_with_movements(
 
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for row in self.chunks(BOARD_SIZE).rev() {
-            for cell in row {
-                match cell {
+        let rows = self.topology.rows();
+        let widest = rows.iter().map(Vec::len).max().unwrap_or(0);
+        for row in rows.iter().rev() {
+            // 六角形盤面では短い行を中央寄せする
+            write!(f, "{:1$}", "", (widest - row.len()) * 7 / 2)?;
+            for &index in row {
+                match self[index] {
                     Some(value) => write!(f, "{:6} ", exp_to_value(value.get()))?,
                     None => write!(f, "     . ")?,
                 }
@@ -224,8 +262,18 @@ mod tests {
         Some(non_zero_exp(exp))
     }
 
+    fn index(x: i32, y: i32) -> usize {
+        Topology::CLASSIC
+            .index_of(IVec2::new(x, y))
+            .expect("coordinates must be on the board")
+    }
+
     fn board_with(entries: &[(usize, u8)]) -> Board {
-        let mut board = Board::default();
+        board_on(Topology::CLASSIC, entries)
+    }
+
+    fn board_on(topology: Topology, entries: &[(usize, u8)]) -> Board {
+        let mut board = Board::new(topology);
         for &(index, exponent) in entries {
             board[index] = cell_exp(exponent);
         }
@@ -234,96 +282,112 @@ mod tests {
 
     #[test]
     fn slide_left_merges_once_for_three_equal_tiles() {
-        let board = board_with(&[
-            (Board::index(0, 0), 1),
-            (Board::index(1, 0), 1),
-            (Board::index(2, 0), 1),
-        ]);
+        let board = board_with(&[(index(0, 0), 1), (index(1, 0), 1), (index(2, 0), 1)]);
 
         let result = board.compute_slide(Direction::Left);
 
         assert!(result.changed);
         assert_eq!(result.score_gained, 4);
-        assert_eq!(result.new_board[Board::index(0, 0)], cell_exp(2));
-        assert_eq!(result.new_board[Board::index(1, 0)], cell_exp(1));
-        assert_eq!(result.new_board[Board::index(2, 0)], None);
-        assert_eq!(result.new_board[Board::index(3, 0)], None);
-        assert_eq!(result.merge_destinations, vec![Board::index(0, 0)]);
+        assert_eq!(result.new_board[index(0, 0)], cell_exp(2));
+        assert_eq!(result.new_board[index(1, 0)], cell_exp(1));
+        assert_eq!(result.new_board[index(2, 0)], None);
+        assert_eq!(result.new_board[index(3, 0)], None);
+        assert_eq!(result.merge_destinations, vec![index(0, 0)]);
+    }
+
+    #[test]
+    fn slide_merge_keeps_tile_nearest_the_wall() {
+        let board = board_with(&[(index(1, 0), 1), (index(3, 0), 1)]);
+
+        let result = board.compute_slide(Direction::Left);
+
+        assert_eq!(
+            result.movements,
+            vec![
+                SlideMovement {
+                    from: index(1, 0),
+                    to: index(0, 0),
+                    consumed: false,
+                },
+                SlideMovement {
+                    from: index(3, 0),
+                    to: index(0, 0),
+                    consumed: true,
+                },
+            ]
+        );
     }
 
     #[test]
     fn slide_left_double_merge_for_four_equal_tiles() {
         let board = board_with(&[
-            (Board::index(0, 1), 1),
-            (Board::index(1, 1), 1),
-            (Board::index(2, 1), 1),
-            (Board::index(3, 1), 1),
+            (index(0, 1), 1),
+            (index(1, 1), 1),
+            (index(2, 1), 1),
+            (index(3, 1), 1),
         ]);
 
         let result = board.compute_slide(Direction::Left);
 
         assert!(result.changed);
         assert_eq!(result.score_gained, 8);
-        assert_eq!(result.new_board[Board::index(0, 1)], cell_exp(2));
-        assert_eq!(result.new_board[Board::index(1, 1)], cell_exp(2));
-        assert_eq!(result.new_board[Board::index(2, 1)], None);
-        assert_eq!(result.new_board[Board::index(3, 1)], None);
-        assert_eq!(
-            result.merge_destinations,
-            vec![Board::index(0, 1), Board::index(1, 1)]
-        );
+        assert_eq!(result.new_board[index(0, 1)], cell_exp(2));
+        assert_eq!(result.new_board[index(1, 1)], cell_exp(2));
+        assert_eq!(result.new_board[index(2, 1)], None);
+        assert_eq!(result.new_board[index(3, 1)], None);
+        assert_eq!(result.merge_destinations, vec![index(0, 1), index(1, 1)]);
     }
 
     #[test]
     fn slide_left_no_change_on_already_compacted_line() {
-        let board = board_with(&[(Board::index(0, 0), 1), (Board::index(1, 0), 2)]);
+        let board = board_with(&[(index(0, 0), 1), (index(1, 0), 2)]);
 
         let result = board.compute_slide(Direction::Left);
 
         assert!(!result.changed);
         assert_eq!(result.score_gained, 0);
-        assert_eq!(result.new_board[Board::index(0, 0)], cell_exp(1));
-        assert_eq!(result.new_board[Board::index(1, 0)], cell_exp(2));
+        assert_eq!(result.new_board[index(0, 0)], cell_exp(1));
+        assert_eq!(result.new_board[index(1, 0)], cell_exp(2));
     }
 
     #[test]
     fn slide_vertical_moves_to_expected_edge() {
-        let board = board_with(&[(Board::index(0, 0), 1), (Board::index(3, 3), 2)]);
+        let board = board_with(&[(index(0, 0), 1), (index(3, 3), 2)]);
 
         let up = board.compute_slide(Direction::Up);
-        assert_eq!(up.new_board[Board::index(0, 3)], cell_exp(1));
-        assert_eq!(up.new_board[Board::index(3, 3)], cell_exp(2));
+        assert_eq!(up.new_board[index(0, 3)], cell_exp(1));
+        assert_eq!(up.new_board[index(3, 3)], cell_exp(2));
 
         let down = board.compute_slide(Direction::Down);
-        assert_eq!(down.new_board[Board::index(0, 0)], cell_exp(1));
-        assert_eq!(down.new_board[Board::index(3, 0)], cell_exp(2));
+        assert_eq!(down.new_board[index(0, 0)], cell_exp(1));
+        assert_eq!(down.new_board[index(3, 0)], cell_exp(2));
     }
 
     #[test]
     fn can_move_true_when_board_has_empty_cell() {
-        let board = board_with(&[(Board::index(0, 0), 1)]);
+        let board = board_with(&[(index(0, 0), 1)]);
         assert!(board.can_move());
     }
 
     #[test]
     fn can_move_true_when_adjacent_equal_tiles_exist() {
         let board = board_with(&[
-            (Board::index(0, 0), 1),
-            (Board::index(1, 0), 1),
-            (Board::index(2, 0), 2),
-            (Board::index(3, 0), 3),
-            (Board::index(0, 1), 4),
-            (Board::index(1, 1), 5),
-            (Board::index(2, 1), 6),
-            (Board::index(3, 1), 7),
-            (Board::index(0, 2), 8),
-            (Board::index(1, 2), 9),
-            (Board::index(2, 2), 10),
-            (Board::index(3, 2), 11),
-            (Board::index(0, 3), 12),
-            (Board::index(1, 3), 13),
-            (Board::index(2, 3), 14),
-            (Board::index(3, 3), 15),
+            (index(0, 0), 1),
+            (index(1, 0), 1),
+            (index(2, 0), 2),
+            (index(3, 0), 3),
+            (index(0, 1), 4),
+            (index(1, 1), 5),
+            (index(2, 1), 6),
+            (index(3, 1), 7),
+            (index(0, 2), 8),
+            (index(1, 2), 9),
+            (index(2, 2), 10),
+            (index(3, 2), 11),
+            (index(0, 3), 12),
+            (index(1, 3), 13),
+            (index(2, 3), 14),
+            (index(3, 3), 15),
         ]);
         assert!(board.can_move());
     }
@@ -331,23 +395,81 @@ mod tests {
     #[test]
     fn can_move_false_when_board_is_full_and_blocked() {
         let board = board_with(&[
-            (Board::index(0, 0), 1),
-            (Board::index(1, 0), 2),
-            (Board::index(2, 0), 3),
-            (Board::index(3, 0), 4),
-            (Board::index(0, 1), 5),
-            (Board::index(1, 1), 6),
-            (Board::index(2, 1), 7),
-            (Board::index(3, 1), 8),
-            (Board::index(0, 2), 9),
-            (Board::index(1, 2), 10),
-            (Board::index(2, 2), 11),
-            (Board::index(3, 2), 12),
-            (Board::index(0, 3), 13),
-            (Board::index(1, 3), 14),
-            (Board::index(2, 3), 15),
-            (Board::index(3, 3), 16),
+            (index(0, 0), 1),
+            (index(1, 0), 2),
+            (index(2, 0), 3),
+            (index(3, 0), 4),
+            (index(0, 1), 5),
+            (index(1, 1), 6),
+            (index(2, 1), 7),
+            (index(3, 1), 8),
+            (index(0, 2), 9),
+            (index(1, 2), 10),
+            (index(2, 2), 11),
+            (index(3, 2), 12),
+            (index(0, 3), 13),
+            (index(1, 3), 14),
+            (index(2, 3), 15),
+            (index(3, 3), 16),
         ]);
         assert!(!board.can_move());
     }
+
+    #[test]
+    fn has_won_only_with_2048_tile() {
+        assert!(!board_with(&[(index(0, 0), WIN_EXP - 1)]).has_won());
+        assert!(board_with(&[(index(0, 0), WIN_EXP)]).has_won());
+    }
+
+    fn hex_index(q: i32, r: i32) -> usize {
+        Topology::HEX
+            .index_of(IVec2::new(q, r))
+            .expect("coordinates must be on the board")
+    }
+
+    #[test]
+    fn hex_slide_up_merges_along_column() {
+        let board = board_on(
+            Topology::HEX,
+            &[(hex_index(0, -2), 1), (hex_index(0, 1), 1)],
+        );
+
+        let result = board.compute_slide(Direction::Up);
+
+        assert!(result.changed);
+        assert_eq!(result.score_gained, 4);
+        assert_eq!(result.new_board[hex_index(0, 2)], cell_exp(2));
+        assert_eq!(result.new_board[hex_index(0, -2)], None);
+        assert_eq!(result.merge_destinations, vec![hex_index(0, 2)]);
+    }
+
+    #[test]
+    fn hex_slide_diagonal_moves_to_expected_edge() {
+        let board = board_on(Topology::HEX, &[(hex_index(-1, 0), 3)]);
+
+        let up_right = board.compute_slide(Direction::UpRight);
+        assert_eq!(up_right.new_board[hex_index(2, 0)], cell_exp(3));
+
+        let down_right = board.compute_slide(Direction::DownRight);
+        assert_eq!(down_right.new_board[hex_index(1, -2)], cell_exp(3));
+
+        let left = board.compute_slide(Direction::Left);
+        assert!(!left.changed);
+    }
+
+    #[test]
+    fn hex_can_move_false_when_full_and_blocked() {
+        let topology = Topology::HEX;
+        let mut board = Board::new(topology);
+        for index in 0..board.len() {
+            // 隣接セルが同じ値にならないよう、q と r から 3 色塗り分けする
+            let coords = topology.coords(index);
+            let color = (coords.x - coords.y).rem_euclid(3) as u8;
+            board[index] = cell_exp(color + 1);
+        }
+        assert!(!board.can_move());
+
+        board[hex_index(0, 0)] = board[hex_index(0, 1)];
+        assert!(board.can_move());
+    }
 }
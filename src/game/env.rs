@@ -0,0 +1,297 @@
+//! 強化学習用の環境（Gym 形式）。
+//! `reset(seed)` で観測を返し、`step(direction)` で観測・報酬・終了フラグ・付加情報を返す。
+//! 出現タイルの乱数はシードから決まるので、同じシードと同じ行動列ならエピソードを完全に再現できる。
+
+use rand::prelude::*;
+use rand::rngs::ChaCha8Rng;
+
+use super::board::{Board, Direction};
+use super::topology::Topology;
+
+/// 観測の指数プレーン数。プレーン 0 は空きセル、プレーン k は 2^k のタイル（最後のプレーンはそれ以上も含む）
+pub const EXP_PLANES: usize = 16;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Observation {
+    /// 各セルの指数（空きは 0）
+    pub exponents: Vec<u8>,
+    /// 指数の one-hot 表現。`planes[plane * cell_count + cell]` の順に並ぶ
+    pub planes: Vec<f32>,
+    /// `Env::action_space` と同じ順の合法手マスク
+    pub action_mask: Vec<bool>,
+}
+
+impl Observation {
+    fn new(board: &Board) -> Self {
+        let cells = board.len();
+        let exponents: Vec<u8> = board
+            .iter()
+            .map(|cell| cell.map_or(0, |exp| exp.get()))
+            .collect();
+
+        let mut planes = vec![0.0; EXP_PLANES * cells];
+        for (cell, &exp) in exponents.iter().enumerate() {
+            let plane = usize::from(exp).min(EXP_PLANES - 1);
+            planes[plane * cells + cell] = 1.0;
+        }
+
+        let action_mask = board
+            .topology()
+            .directions()
+            .iter()
+            .map(|&direction| board.compute_slide(direction).changed)
+            .collect();
+
+        Self {
+            exponents,
+            planes,
+            action_mask,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct StepInfo {
+    /// 盤面が変化しない手だった（盤面・スコアはそのまま）
+    pub illegal_move: bool,
+    /// 新しいタイルが置かれたセル
+    pub spawned: Option<usize>,
+    /// エピソード開始からの合計スコアと手数
+    pub score: u32,
+    pub moves: u32,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Step {
+    pub observation: Observation,
+    /// その手で得たスコア（`SlideResult::score_gained`）
+    pub reward: u32,
+    /// 動かせる方向がなくなった
+    pub done: bool,
+    pub info: StepInfo,
+}
+
+pub struct Env {
+    board: Board,
+    score: u32,
+    moves: u32,
+    /// シードからの乱数列がプラットフォームや rand の版によらず同じになる生成器
+    rng: ChaCha8Rng,
+}
+
+impl Env {
+    /// シード 0 でリセット済みの環境を作る
+    pub fn new(topology: Topology) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        Self {
+            board: Board::with_two_tiles(topology, &mut rng),
+            score: 0,
+            moves: 0,
+            rng,
+        }
+    }
+
+    pub fn action_space(&self) -> &'static [Direction] {
+        self.board.topology().directions()
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn observation(&self) -> Observation {
+        Observation::new(&self.board)
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+        self.board = Board::with_two_tiles(self.board.topology(), &mut self.rng);
+        self.score = 0;
+        self.moves = 0;
+        self.observation()
+    }
+
+    pub fn step(&mut self, direction: Direction) -> Step {
+        let result = self.board.compute_slide(direction);
+        let mut info = StepInfo {
+            illegal_move: !result.changed,
+            ..StepInfo::default()
+        };
+
+        if result.changed {
+            self.board = result.new_board;
+            self.score += result.score_gained;
+            self.moves += 1;
+            info.spawned = self.board.place_random_tile(&mut self.rng);
+        }
+        info.score = self.score;
+        info.moves = self.moves;
+
+        Step {
+            observation: self.observation(),
+            reward: result.score_gained,
+            done: !self.board.can_move(),
+            info,
+        }
+    }
+}
+
+/// 同じトポロジーの環境をまとめて進めるバッチ。
+/// 終了した環境は次のシードで自動的にリセットされる（返す `Step` は終了時の観測のまま）。
+pub struct VecEnv {
+    envs: Vec<Env>,
+    next_seed: u64,
+}
+
+impl VecEnv {
+    pub fn new(topology: Topology, count: usize) -> Self {
+        Self {
+            envs: (0..count).map(|_| Env::new(topology)).collect(),
+            next_seed: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.envs.is_empty()
+    }
+
+    pub fn envs(&self) -> &[Env] {
+        &self.envs
+    }
+
+    /// 環境 i をシード `seed + i` でリセットする。以降の自動リセットはその続きのシードを使う
+    pub fn reset(&mut self, seed: u64) -> Vec<Observation> {
+        self.next_seed = seed;
+        self.envs
+            .iter_mut()
+            .map(|env| {
+                let observation = env.reset(self.next_seed);
+                self.next_seed = self.next_seed.wrapping_add(1);
+                observation
+            })
+            .collect()
+    }
+
+    /// 環境ごとに 1 手ずつ進める。`actions` は環境と同じ数だけ渡す
+    pub fn step(&mut self, actions: &[Direction]) -> Vec<Step> {
+        assert_eq!(actions.len(), self.envs.len(), "one action per environment");
+
+        self.envs
+            .iter_mut()
+            .zip(actions)
+            .map(|(env, &direction)| {
+                let step = env.step(direction);
+                if step.done {
+                    env.reset(self.next_seed);
+                    self.next_seed = self.next_seed.wrapping_add(1);
+                }
+                step
+            })
+            .collect()
+    }
+
+    /// 現在の全環境の指数プレーンを `[env][plane][cell]` の順に連結する
+    pub fn planes(&self) -> Vec<f32> {
+        self.envs
+            .iter()
+            .flat_map(|env| env.observation().planes)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn planes_are_one_hot_per_cell() {
+        let mut env = Env::new(Topology::CLASSIC);
+        let observation = env.reset(3);
+        let cells = observation.exponents.len();
+
+        for cell in 0..cells {
+            let hot: Vec<usize> = (0..EXP_PLANES)
+                .filter(|plane| observation.planes[plane * cells + cell] == 1.0)
+                .collect();
+            assert_eq!(hot, vec![usize::from(observation.exponents[cell])]);
+        }
+        assert_eq!(observation.planes.iter().sum::<f32>(), cells as f32);
+    }
+
+    #[test]
+    fn same_seed_and_actions_replay_exactly() {
+        let episode = || {
+            let mut env = Env::new(Topology::CLASSIC);
+            let mut trajectory = vec![(env.reset(11), 0, false)];
+            for turn in 0..200 {
+                let direction = env.action_space()[turn % 4];
+                let step = env.step(direction);
+                trajectory.push((step.observation, step.reward, step.done));
+                if step.done {
+                    break;
+                }
+            }
+            trajectory
+        };
+
+        assert_eq!(episode(), episode());
+    }
+
+    #[test]
+    fn illegal_move_is_flagged_and_leaves_board_unchanged() {
+        // 初期配置で動かせない方向があるシードを探す
+        let mut env = Env::new(Topology::CLASSIC);
+        let (observation, direction) = (0..)
+            .find_map(|seed| {
+                let observation = env.reset(seed);
+                let direction = env
+                    .action_space()
+                    .iter()
+                    .zip(&observation.action_mask)
+                    .find_map(|(&direction, &legal)| (!legal).then_some(direction))?;
+                Some((observation, direction))
+            })
+            .expect("some seed starts with an illegal direction");
+
+        let step = env.step(direction);
+
+        assert!(step.info.illegal_move);
+        assert_eq!(step.reward, 0);
+        assert_eq!(step.info.spawned, None);
+        assert_eq!(step.observation, observation);
+    }
+
+    #[test]
+    fn vec_env_resets_finished_environments() {
+        let mut envs = VecEnv::new(
+            Topology::Grid {
+                width: 2,
+                height: 2,
+            },
+            4,
+        );
+        envs.reset(0);
+
+        let mut finished = 0;
+        for _ in 0..100 {
+            let actions: Vec<Direction> = envs
+                .envs()
+                .iter()
+                .map(|env| {
+                    let mask = env.observation().action_mask;
+                    let legal = mask.iter().position(|&legal| legal).unwrap_or(0);
+                    env.action_space()[legal]
+                })
+                .collect();
+            finished += envs.step(&actions).iter().filter(|step| step.done).count();
+        }
+
+        assert!(finished > 0);
+        assert!(envs.envs().iter().all(|env| env.board().can_move()));
+        assert_eq!(envs.planes().len(), 4 * EXP_PLANES * 4);
+    }
+}
@@ -0,0 +1,1058 @@
+//! 盤面の画像の書き出し。今の盤面をスコアと日付といっしょに PNG に、対局の記録をアニメーション GIF に、
+//! GPU を使わず CPU だけで描く。ウィンドウのない環境やテストでも同じ画像になる。
+//! Web 版ではダウンロードさせ、ネイティブ版では画像フォルダに保存する。
+
+use std::collections::HashMap;
+use std::num::NonZero;
+
+use ab_glyph::{Font as _, FontRef, PxScale, ScaleFont, point};
+use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task, futures::check_ready};
+
+use super::GameFont;
+use super::animation::MERGE_SCALE_PEAK;
+use super::board::{Board, Score};
+use super::render::{
+    LabelSettings, board_index_to_position, cell_contains, font_size_for_tile, label_fill,
+    themed_board_extent, themed_cell_size, tile_label,
+};
+use super::replay::Replay;
+use super::session::{MoveLog, Player};
+use super::theme::Theme;
+use super::topology::Topology;
+use super::ui::Press;
+
+/// 画像の外周の余白
+const MARGIN: f32 = 24.0;
+/// 盤面の上に書くスコアの行の高さと文字の大きさ
+const CAPTION_HEIGHT: f32 = 48.0;
+const CAPTION_FONT_SIZE: f32 = 28.0;
+/// 画像の下に書く日付の行の高さと文字の大きさ
+const FOOTER_HEIGHT: f32 = 32.0;
+const FOOTER_FONT_SIZE: f32 = 18.0;
+/// 対戦で盤面を並べるときの間隔
+const BOARD_SPACING: f32 = 40.0;
+/// 縁を滑らかにするための 1 ピクセルあたりの縦横のサンプル数
+const SAMPLES: u32 = 4;
+
+/// GIF でスライドとマージ・出現を見せる時間。ゲーム画面より遅くして、1 手ずつ追えるようにする
+const GIF_SLIDE_SECONDS: f32 = 0.15;
+const GIF_EFFECT_SECONDS: f32 = 0.1;
+/// GIF の最初と最後の盤面を止めて見せる時間
+const GIF_HOLD_SECONDS: f32 = 1.5;
+
+/// アニメーション GIF の書き出しの設定。`GamePlugin` を追加する前にリソースとして挿入すれば既定値を変えられる
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct GifSettings {
+    /// 1 秒あたりのコマ数
+    pub fps: u32,
+    /// 最後の何手を書き出すか。`None` なら対局のすべて
+    pub last_moves: Option<usize>,
+}
+
+impl Default for GifSettings {
+    fn default() -> Self {
+        Self {
+            fps: 15,
+            last_moves: Some(50),
+        }
+    }
+}
+
+/// 書き出す盤面 1 枚分
+pub(super) struct ExportedBoard<'a> {
+    pub(super) board: &'a Board,
+    pub(super) score: u32,
+}
+
+/// sRGB の RGBA（0〜1）で描くキャンバス
+#[derive(Clone)]
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32, background: Color) -> Self {
+        let color = background.to_srgba().to_f32_array();
+        Self {
+            width,
+            height,
+            pixels: vec![color; (width * height) as usize],
+        }
+    }
+
+    /// `coverage` の割合だけ色を重ねる
+    fn blend(&mut self, x: i32, y: i32, color: [f32; 4], coverage: f32) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+        let alpha = color[3] * coverage.clamp(0.0, 1.0);
+        let pixel = &mut self.pixels[(y as u32 * self.width + x as u32) as usize];
+        for channel in 0..3 {
+            pixel[channel] = color[channel] * alpha + pixel[channel] * (1.0 - alpha);
+        }
+        pixel[3] = alpha + pixel[3] * (1.0 - alpha);
+    }
+
+    /// `center` を中心とする `size` の範囲のうち、`inside`（中心からのずれを受け取る）が真になる部分を塗る
+    fn fill(&mut self, center: Vec2, size: Vec2, color: Color, inside: impl Fn(Vec2) -> bool) {
+        let color = color.to_srgba().to_f32_array();
+        let min = (center - size / 2.0).floor();
+        let max = (center + size / 2.0).ceil();
+        for y in min.y as i32..max.y as i32 {
+            for x in min.x as i32..max.x as i32 {
+                let mut covered = 0;
+                for sy in 0..SAMPLES {
+                    for sx in 0..SAMPLES {
+                        let sample = Vec2::new(
+                            x as f32 + (sx as f32 + 0.5) / SAMPLES as f32,
+                            y as f32 + (sy as f32 + 0.5) / SAMPLES as f32,
+                        );
+                        let offset = sample - center;
+                        if offset.abs().cmple(size / 2.0).all() && inside(offset) {
+                            covered += 1;
+                        }
+                    }
+                }
+                if covered > 0 {
+                    self.blend(x, y, color, covered as f32 / (SAMPLES * SAMPLES) as f32);
+                }
+            }
+        }
+    }
+
+    /// `center` を中心とする `size` の長方形を塗る。縁のピクセルは重なった面積の割合だけ塗る
+    fn fill_rect(&mut self, center: Vec2, size: Vec2, color: Color) {
+        let color = color.to_srgba().to_f32_array();
+        let (min, max) = (center - size / 2.0, center + size / 2.0);
+        let overlap = |pixel: i32, low: f32, high: f32| {
+            (high.min(pixel as f32 + 1.0) - low.max(pixel as f32)).max(0.0)
+        };
+        for y in min.y.floor() as i32..max.y.ceil() as i32 {
+            let cover_y = overlap(y, min.y, max.y);
+            for x in min.x.floor() as i32..max.x.ceil() as i32 {
+                self.blend(x, y, color, overlap(x, min.x, max.x) * cover_y);
+            }
+        }
+    }
+
+    /// セルの形に塗る
+    fn fill_cell(&mut self, topology: Topology, center: Vec2, size: Vec2, color: Color) {
+        match topology {
+            Topology::Grid { .. } => self.fill_rect(center, size, color),
+            Topology::Hex { .. } => {
+                self.fill(center, size, color, |offset| {
+                    cell_contains(topology, size, offset)
+                });
+            }
+        }
+    }
+
+    /// `x` から右へ、行の中心を `center_y` にそろえて文字を書く
+    fn text(&mut self, font: &FontRef, text: &str, size: f32, x: f32, center_y: f32, color: Color) {
+        let color = color.to_srgba().to_f32_array();
+        let scale = px_scale(font, size);
+        let scaled = font.as_scaled(scale);
+        let baseline = center_y + (scaled.ascent() + scaled.descent()) / 2.0;
+        let mut caret = x;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += scaled.kern(previous, id);
+            }
+            let glyph = id.with_scale_and_position(scale, point(caret, baseline));
+            if let Some(outline) = font.outline_glyph(glyph) {
+                let bounds = outline.px_bounds();
+                outline.draw(|gx, gy, coverage| {
+                    let px = bounds.min.x as i32 + gx as i32;
+                    let py = bounds.min.y as i32 + gy as i32;
+                    self.blend(px, py, color, coverage);
+                });
+            }
+            caret += scaled.h_advance(id);
+            previous = Some(id);
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let data: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|pixel| pixel.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8))
+            .collect();
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        writer.finish()?;
+        Ok(bytes)
+    }
+}
+
+/// 1 em が `size` ピクセルになる大きさ。ゲーム画面の文字の大きさと合わせる
+fn px_scale(font: &FontRef, size: f32) -> PxScale {
+    let units_per_em = font.units_per_em().unwrap_or(1000.0);
+    PxScale::from(size * font.height_unscaled() / units_per_em)
+}
+
+fn text_width(font: &FontRef, text: &str, size: f32) -> f32 {
+    let scaled = font.as_scaled(px_scale(font, size));
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            width += scaled.kern(previous, id);
+        }
+        width += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+/// 盤面を並べた画像の大きさ
+fn canvas_size(extents: &[Vec2]) -> UVec2 {
+    let boards_width = extents.iter().map(|extent| extent.x).sum::<f32>()
+        + BOARD_SPACING * extents.len().saturating_sub(1) as f32;
+    let boards_height = extents.iter().map(|extent| extent.y).fold(0.0, f32::max);
+    Vec2::new(
+        boards_width + MARGIN * 2.0,
+        MARGIN * 2.0 + CAPTION_HEIGHT + boards_height + FOOTER_HEIGHT,
+    )
+    .ceil()
+    .as_uvec2()
+}
+
+/// 盤面の上にスコアを書く
+fn draw_caption(canvas: &mut Canvas, font: &FontRef, caption: &str, left: f32, theme: &Theme) {
+    let y = MARGIN + CAPTION_HEIGHT / 2.0;
+    canvas.text(font, caption, CAPTION_FONT_SIZE, left, y, theme.score);
+}
+
+/// 画像の右下に書く
+fn draw_footer(canvas: &mut Canvas, font: &FontRef, text: &str, theme: &Theme) {
+    let x = canvas.width as f32 - MARGIN - text_width(font, text, FOOTER_FONT_SIZE);
+    let y = canvas.height as f32 - MARGIN - FOOTER_HEIGHT / 2.0;
+    canvas.text(font, text, FOOTER_FONT_SIZE, x, y, theme.score);
+}
+
+/// 盤面を PNG に描く。盤面が複数あれば（対戦）左から順に並べる
+pub(super) fn render_png(
+    boards: &[ExportedBoard],
+    theme: &Theme,
+    labels: &LabelSettings,
+    font: &[u8],
+    date: &str,
+) -> Result<Vec<u8>, String> {
+    let font = FontRef::try_from_slice(font).map_err(|err| err.to_string())?;
+    let extents: Vec<Vec2> = boards
+        .iter()
+        .map(|exported| themed_board_extent(exported.board.topology(), theme))
+        .collect();
+    let size = canvas_size(&extents);
+    let mut canvas = Canvas::new(size.x, size.y, theme.background);
+
+    let mut left = MARGIN;
+    for (index, (exported, extent)) in boards.iter().zip(&extents).enumerate() {
+        let caption = if boards.len() > 1 {
+            format!("P{} {}", index + 1, exported.score)
+        } else {
+            format!("Score {}", exported.score)
+        };
+        draw_caption(&mut canvas, &font, &caption, left, theme);
+
+        let center = Vec2::new(
+            left + extent.x / 2.0,
+            MARGIN + CAPTION_HEIGHT + extent.y / 2.0,
+        );
+        let topology = exported.board.topology();
+        draw_cells(&mut canvas, topology, center, theme);
+        for (index, exp) in exported.board.iter().enumerate() {
+            if let Some(exp) = *exp {
+                let position = board_index_to_position(topology, index);
+                let tile = PlacedTile::new(position, exp, 1.0);
+                draw_tile(&mut canvas, &font, topology, center, tile, theme, labels);
+            }
+        }
+        left += extent.x + BOARD_SPACING;
+    }
+    draw_footer(&mut canvas, &font, date, theme);
+
+    canvas.encode().map_err(|err| err.to_string())
+}
+
+/// 盤面の背景と、空のセルを `center` を盤面の中心として描く
+fn draw_cells(canvas: &mut Canvas, topology: Topology, center: Vec2, theme: &Theme) {
+    let extent = themed_board_extent(topology, theme);
+    canvas.fill_rect(center, extent, theme.board);
+
+    let cell = themed_cell_size(topology, theme);
+    for index in 0..topology.cell_count() {
+        let position = board_index_to_position(topology, index);
+        canvas.fill_cell(
+            topology,
+            to_canvas(center, position),
+            cell,
+            theme.empty_cell,
+        );
+    }
+}
+
+/// 盤面の座標（y が上向き）を画像の座標（y が下向き）にする
+fn to_canvas(center: Vec2, position: Vec2) -> Vec2 {
+    center + Vec2::new(position.x, -position.y)
+}
+
+/// 描く位置と大きさを決めたタイル
+#[derive(Clone, Copy)]
+struct PlacedTile {
+    /// 盤面の中心を原点とする位置（`board_index_to_position` と同じ座標）
+    position: Vec2,
+    exp: NonZero<u8>,
+    scale: f32,
+}
+
+impl PlacedTile {
+    fn new(position: Vec2, exp: NonZero<u8>, scale: f32) -> Self {
+        Self {
+            position,
+            exp,
+            scale,
+        }
+    }
+}
+
+/// タイルと数字を描く
+fn draw_tile(
+    canvas: &mut Canvas,
+    font: &FontRef,
+    topology: Topology,
+    center: Vec2,
+    tile: PlacedTile,
+    theme: &Theme,
+    labels: &LabelSettings,
+) {
+    if tile.scale <= 0.0 {
+        return;
+    }
+    let cell = themed_cell_size(topology, theme) * tile.scale;
+    let tile_center = to_canvas(center, tile.position);
+    canvas.fill_cell(topology, tile_center, cell, theme.tile(tile.exp));
+
+    let label = tile_label(tile.exp, labels);
+    let mut size = font_size_for_tile(Some(tile.exp)) * tile.scale;
+    let available = cell.x * label_fill(topology);
+    let label_width = text_width(font, &label, size);
+    if label_width > available {
+        size *= available / label_width;
+    }
+    let label_x = tile_center.x - text_width(font, &label, size) / 2.0;
+    canvas.text(
+        font,
+        &label,
+        size,
+        label_x,
+        tile_center.y,
+        theme.text(tile.exp),
+    );
+}
+
+/// 対局の記録を、スライド・マージ・出現が動くアニメーション GIF に描く。
+/// 色は組み込みの Classic テーマ（`tile_color`）を使う
+pub fn replay_gif(replay: &Replay, settings: &GifSettings, font: &[u8]) -> Result<Vec<u8>, String> {
+    render_gif(
+        replay,
+        settings,
+        &Theme::default(),
+        &LabelSettings::default(),
+        font,
+    )
+}
+
+pub(super) fn render_gif(
+    replay: &Replay,
+    settings: &GifSettings,
+    theme: &Theme,
+    labels: &LabelSettings,
+    font: &[u8],
+) -> Result<Vec<u8>, String> {
+    let font = FontRef::try_from_slice(font).map_err(|err| err.to_string())?;
+    let replay = match settings.last_moves {
+        Some(count) => replay.last_moves(count)?,
+        None => replay.clone(),
+    };
+    let steps = replay.steps()?;
+    let topology = replay.topology;
+    let extent = themed_board_extent(topology, theme);
+    let size = canvas_size(&[extent]);
+    let center = Vec2::new(
+        MARGIN + extent.x / 2.0,
+        MARGIN + CAPTION_HEIGHT + extent.y / 2.0,
+    );
+
+    // 盤面の背景とセルはどのコマでも同じなので、一度だけ描いて使い回す
+    let mut background = Canvas::new(size.x, size.y, theme.background);
+    draw_cells(&mut background, topology, center, theme);
+    let draw = |tiles: &[PlacedTile], score: u32, turn: usize| {
+        let mut canvas = background.clone();
+        draw_caption(&mut canvas, &font, &format!("Score {score}"), MARGIN, theme);
+        draw_footer(
+            &mut canvas,
+            &font,
+            &format!("{turn} / {}", steps.len()),
+            theme,
+        );
+        for &tile in tiles {
+            draw_tile(&mut canvas, &font, topology, center, tile, theme, labels);
+        }
+        canvas
+    };
+    let board_tiles = |board: &Board, scale: &dyn Fn(usize) -> f32| -> Vec<PlacedTile> {
+        board
+            .iter()
+            .enumerate()
+            .filter_map(|(index, exp)| {
+                let position = board_index_to_position(topology, index);
+                Some(PlacedTile::new(position, (*exp)?, scale(index)))
+            })
+            .collect()
+    };
+
+    let fps = settings.fps.clamp(1, 50);
+    // GIF の待ち時間は 1/100 秒単位で、2 未満は多くのビューアが遅く再生する
+    let delay = (100 / fps).max(2) as u16;
+    let hold = (GIF_HOLD_SECONDS * 100.0) as u16;
+    let frames = |seconds: f32| ((seconds * fps as f32).round() as usize).max(1);
+    let mut gif = GifEncoder::new(size)?;
+
+    let initial = replay.initial_board()?;
+    gif.frame(
+        &draw(&board_tiles(&initial, &|_| 1.0), replay.initial_score, 0),
+        hold,
+    )?;
+    let mut last = None;
+    for (turn, step) in steps.iter().enumerate() {
+        // 吸収されるタイルを先に描き、残るタイルをその上に重ねる
+        let mut movements = step.slide.movements.clone();
+        movements.sort_by_key(|movement| !movement.consumed);
+        let slide_frames = frames(GIF_SLIDE_SECONDS);
+        for frame in 1..=slide_frames {
+            let t = EaseFunction::CubicOut.sample_clamped(frame as f32 / slide_frames as f32);
+            let tiles: Vec<PlacedTile> = movements
+                .iter()
+                .filter_map(|movement| {
+                    let from = board_index_to_position(topology, movement.from);
+                    let to = board_index_to_position(topology, movement.to);
+                    Some(PlacedTile::new(
+                        from.lerp(to, t),
+                        step.before[movement.from]?,
+                        1.0,
+                    ))
+                })
+                .collect();
+            gif.frame(&draw(&tiles, step.score, turn + 1), delay)?;
+        }
+
+        let score = step.score + step.slide.score_gained;
+        let effect_frames = frames(GIF_EFFECT_SECONDS);
+        for frame in 1..=effect_frames {
+            let t = frame as f32 / effect_frames as f32;
+            let pulse = 1.0 + (MERGE_SCALE_PEAK - 1.0) * (t * std::f32::consts::PI).sin();
+            let scale = |index: usize| {
+                if step.slide.merge_destinations.contains(&index) {
+                    pulse
+                } else {
+                    1.0
+                }
+            };
+            let mut tiles = board_tiles(&step.slide.new_board, &scale);
+            if let Some((index, exp)) = step.spawn {
+                let position = board_index_to_position(topology, index);
+                tiles.push(PlacedTile::new(position, exp, t));
+            }
+            let canvas = draw(&tiles, score, turn + 1);
+            gif.frame(&canvas, delay)?;
+            last = Some(canvas);
+        }
+    }
+    // 最後の盤面を止めて見せる
+    if let Some(canvas) = last {
+        gif.frame(&canvas, hold)?;
+    }
+    Ok(gif.finish())
+}
+
+/// 256 色のアニメーション GIF（GIF89a）を組み立てる。コマごとに色表を持ち、繰り返し再生する
+struct GifEncoder {
+    bytes: Vec<u8>,
+    width: u16,
+    height: u16,
+}
+
+impl GifEncoder {
+    fn new(size: UVec2) -> Result<Self, String> {
+        let (Ok(width), Ok(height)) = (u16::try_from(size.x), u16::try_from(size.y)) else {
+            return Err(format!("{}x{} is too large for a GIF", size.x, size.y));
+        };
+        let mut bytes = b"GIF89a".to_vec();
+        bytes.extend(width.to_le_bytes());
+        bytes.extend(height.to_le_bytes());
+        // 全体の色表なし、背景色 0、縦横比の指定なし
+        bytes.extend([0, 0, 0]);
+        // 無限に繰り返す（NETSCAPE2.0 拡張）
+        bytes.extend([0x21, 0xFF, 0x0B]);
+        bytes.extend(b"NETSCAPE2.0");
+        bytes.extend([0x03, 0x01, 0x00, 0x00, 0x00]);
+        Ok(Self {
+            bytes,
+            width,
+            height,
+        })
+    }
+
+    /// `delay`（1/100 秒）だけ表示するコマを足す
+    fn frame(&mut self, canvas: &Canvas, delay: u16) -> Result<(), String> {
+        let (palette, indices) = quantize(canvas);
+
+        // 表示時間。前のコマは残したまま上書きする
+        self.bytes.extend([0x21, 0xF9, 0x04, 0x04]);
+        self.bytes.extend(delay.to_le_bytes());
+        self.bytes.extend([0x00, 0x00]);
+
+        // 画像全体を、256 色の色表付きで置く
+        self.bytes.push(0x2C);
+        self.bytes.extend([0, 0, 0, 0]);
+        self.bytes.extend(self.width.to_le_bytes());
+        self.bytes.extend(self.height.to_le_bytes());
+        self.bytes.push(0x87);
+        for color in palette {
+            self.bytes.extend(color);
+        }
+
+        const MIN_CODE_SIZE: u8 = 8;
+        let data = weezl::encode::Encoder::new(weezl::BitOrder::Lsb, MIN_CODE_SIZE)
+            .encode(&indices)
+            .map_err(|err| err.to_string())?;
+        self.bytes.push(MIN_CODE_SIZE);
+        for block in data.chunks(255) {
+            self.bytes.push(block.len() as u8);
+            self.bytes.extend(block);
+        }
+        self.bytes.push(0x00);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.bytes.push(0x3B);
+        self.bytes
+    }
+}
+
+/// キャンバスを 256 色に減らす。よく使われる色から順に色表に入れ、残りの色（文字や縁のにじみ）は一番近い色で置き換える
+fn quantize(canvas: &Canvas) -> ([[u8; 3]; 256], Vec<u8>) {
+    let pixels: Vec<[u8; 3]> = canvas
+        .pixels
+        .iter()
+        .map(|pixel| {
+            let [r, g, b, _] = pixel.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+            [r, g, b]
+        })
+        .collect();
+
+    let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+    for pixel in &pixels {
+        *counts.entry(*pixel).or_default() += 1;
+    }
+    let mut colors: Vec<([u8; 3], usize)> = counts.into_iter().collect();
+    colors.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let used = colors.len().min(256);
+
+    let mut palette = [[0; 3]; 256];
+    let mut lookup: HashMap<[u8; 3], u8> = HashMap::new();
+    for (index, (color, _)) in colors.iter().take(used).enumerate() {
+        palette[index] = *color;
+        lookup.insert(*color, index as u8);
+    }
+    let nearest = |color: [u8; 3]| {
+        let distance = |entry: &[u8; 3]| {
+            (0..3)
+                .map(|channel| (i32::from(entry[channel]) - i32::from(color[channel])).pow(2))
+                .sum::<i32>()
+        };
+        (0..used)
+            .min_by_key(|&index| distance(&palette[index]))
+            .unwrap_or(0) as u8
+    };
+    let indices = pixels
+        .iter()
+        .map(|pixel| *lookup.entry(*pixel).or_insert_with(|| nearest(*pixel)))
+        .collect();
+    (palette, indices)
+}
+
+/// ローカル時刻の年・月・日・時・分・秒
+type LocalTime = [u32; 6];
+
+/// 今の日付（`2026-01-31`）と、ファイル名に使う日時（`20260131-235959`）。どちらもローカル時刻
+fn now() -> (String, String) {
+    format_time(local_time())
+}
+
+fn format_time([year, month, day, hour, minute, second]: LocalTime) -> (String, String) {
+    (
+        format!("{year:04}-{month:02}-{day:02}"),
+        format!("{year:04}{month:02}{day:02}-{hour:02}{minute:02}{second:02}"),
+    )
+}
+
+#[cfg(unix)]
+fn local_time() -> LocalTime {
+    // SAFETY: `localtime_r` は渡したバッファだけに書き込み、失敗したら null を返す
+    let tm = unsafe {
+        let seconds = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&seconds, &mut tm).is_null() {
+            return [1970, 1, 1, 0, 0, 0];
+        }
+        tm
+    };
+    [
+        (tm.tm_year + 1900) as u32,
+        (tm.tm_mon + 1) as u32,
+        tm.tm_mday as u32,
+        tm.tm_hour as u32,
+        tm.tm_min as u32,
+        tm.tm_sec as u32,
+    ]
+}
+
+#[cfg(windows)]
+fn local_time() -> LocalTime {
+    /// Win32 の `SYSTEMTIME`
+    #[repr(C)]
+    #[derive(Default)]
+    struct SystemTime {
+        year: u16,
+        month: u16,
+        day_of_week: u16,
+        day: u16,
+        hour: u16,
+        minute: u16,
+        second: u16,
+        milliseconds: u16,
+    }
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetLocalTime(time: *mut SystemTime);
+    }
+
+    let mut time = SystemTime::default();
+    // SAFETY: `GetLocalTime` は渡した構造体に書き込むだけで失敗しない
+    unsafe { GetLocalTime(&mut time) };
+    [
+        time.year,
+        time.month,
+        time.day,
+        time.hour,
+        time.minute,
+        time.second,
+    ]
+    .map(u32::from)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_time() -> LocalTime {
+    let date = web_sys::js_sys::Date::new_0();
+    [
+        date.get_full_year(),
+        date.get_month() + 1,
+        date.get_date(),
+        date.get_hours(),
+        date.get_minutes(),
+        date.get_seconds(),
+    ]
+}
+
+/// 書き出した画像を保存するフォルダ
+#[cfg(not(target_arch = "wasm32"))]
+fn export_dir() -> Option<std::path::PathBuf> {
+    let base = std::env::var_os("XDG_PICTURES_DIR")
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("USERPROFILE").map(|home| std::path::Path::new(&home).join("Pictures"))
+        })
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join("Pictures"))
+        })?;
+    Some(base.join("bevy_2048"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save(name: &str, _mime: &str, bytes: &[u8]) -> Result<(), String> {
+    let dir = export_dir().ok_or("no pictures directory")?;
+    std::fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    let path = dir.join(name);
+    std::fs::write(&path, bytes).map_err(|err| err.to_string())?;
+    info!("exported {}", path.display());
+    Ok(())
+}
+
+/// ダウンロードを始めてから、書き出した画像の URL を破棄するまでの時間
+#[cfg(target_arch = "wasm32")]
+const REVOKE_URL_DELAY_MS: i32 = 10_000;
+
+/// ブラウザにダウンロードさせる
+#[cfg(target_arch = "wasm32")]
+fn save(name: &str, mime: &str, bytes: &[u8]) -> Result<(), String> {
+    use web_sys::wasm_bindgen::JsCast;
+    use web_sys::wasm_bindgen::closure::Closure;
+
+    let error = |err: web_sys::wasm_bindgen::JsValue| format!("{err:?}");
+    let parts = web_sys::js_sys::Array::of1(&web_sys::js_sys::Uint8Array::from(bytes));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(mime);
+    let blob =
+        web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options).map_err(error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(error)?;
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("no document")?;
+    let anchor: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(error)?
+        .dyn_into()
+        .map_err(|_| "not an anchor element")?;
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+
+    // すぐに破棄するとダウンロードが始まる前に取り消すブラウザがあるので、少し待ってから破棄する
+    let revoke = Closure::once_into_js(move || {
+        let _ = web_sys::Url::revoke_object_url(&url);
+    });
+    web_sys::window()
+        .ok_or("no window")?
+        .set_timeout_with_callback_and_timeout_and_arguments_0(
+            revoke.unchecked_ref(),
+            REVOKE_URL_DELAY_MS,
+        )
+        .map(drop)
+        .map_err(error)
+}
+
+/// 今の盤面を画像に書き出す
+pub(super) fn export_image(
+    _press: On<Press>,
+    theme: Res<Theme>,
+    labels: Res<LabelSettings>,
+    font: Res<GameFont>,
+    fonts: Res<Assets<Font>>,
+    fields: Query<(&Board, &Score, &Player)>,
+) {
+    let Some(font) = fonts.get(&font.0) else {
+        warn!("could not export the board: the font is still loading");
+        return;
+    };
+    let mut fields: Vec<_> = fields.iter().collect();
+    fields.sort_by_key(|(.., player)| player.0);
+    let boards: Vec<ExportedBoard> = fields
+        .iter()
+        .map(|(board, score, _)| ExportedBoard {
+            board,
+            score: score.0,
+        })
+        .collect();
+
+    let (date, stamp) = now();
+    let result = render_png(&boards, &theme, &labels, font.data.as_ref(), &date)
+        .and_then(|png| save(&format!("bevy_2048-{stamp}.png"), "image/png", &png));
+    if let Err(err) = result {
+        warn!("could not export the board: {err}");
+    }
+}
+
+/// 書き出し中の GIF。コマの描画と減色は重いので `AsyncComputeTaskPool` で進め、
+/// 終わったら `finish_gif_exports` がファイルに保存する
+#[derive(Component)]
+pub(super) struct GifExport {
+    /// 拡張子を除いたファイル名
+    name: String,
+    /// GIF の隣に `*.replay.ron` として保存する記録（ネイティブ版のみ）
+    replay: Option<Replay>,
+    /// 結果を書き込む文字のエンティティ
+    status: Option<Entity>,
+    task: Task<Result<Vec<u8>, String>>,
+}
+
+impl GifExport {
+    /// 描画に使う設定とフォントを写し取って書き出しを始める
+    fn start(
+        name: String,
+        replay: &Replay,
+        settings: &GifSettings,
+        theme: &Theme,
+        labels: &LabelSettings,
+        font: &Font,
+    ) -> Self {
+        let (replay, settings, theme, labels, font) = (
+            replay.clone(),
+            *settings,
+            theme.clone(),
+            *labels,
+            font.data.as_ref().to_vec(),
+        );
+        let task = AsyncComputeTaskPool::get()
+            .spawn(async move { render_gif(&replay, &settings, &theme, &labels, &font) });
+        Self {
+            name,
+            replay: None,
+            status: None,
+            task,
+        }
+    }
+}
+
+/// 対局の記録をアニメーション GIF に書き出す。ネイティブ版では記録も `*.replay.ron` として隣に保存する
+pub(super) fn export_gif(
+    _press: On<Press>,
+    mut commands: Commands,
+    settings: Res<GifSettings>,
+    theme: Res<Theme>,
+    labels: Res<LabelSettings>,
+    font: Res<GameFont>,
+    fonts: Res<Assets<Font>>,
+    fields: Query<(&MoveLog, &Player)>,
+) {
+    let Some(font) = fonts.get(&font.0) else {
+        warn!("could not export the game: the font is still loading");
+        return;
+    };
+    let mut fields: Vec<_> = fields.iter().collect();
+    fields.sort_by_key(|(_, player)| player.0);
+
+    let (_, stamp) = now();
+    for (log, player) in &fields {
+        // 対戦では盤面ごとに 1 枚ずつ書き出す
+        let name = if fields.len() > 1 {
+            format!("bevy_2048-{stamp}-p{}", player.0 + 1)
+        } else {
+            format!("bevy_2048-{stamp}")
+        };
+        let export = GifExport::start(name, &log.0, &settings, &theme, &labels, font);
+        commands.spawn(GifExport {
+            replay: cfg!(not(target_arch = "wasm32")).then(|| log.0.clone()),
+            ..export
+        });
+    }
+}
+
+/// 描き終わった GIF を保存し、結果をログと状態表示に出す
+pub(super) fn finish_gif_exports(
+    mut commands: Commands,
+    mut exports: Query<(Entity, &mut GifExport)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (entity, mut export) in &mut exports {
+        let Some(result) = check_ready(&mut export.task) else {
+            continue;
+        };
+        commands.entity(entity).despawn();
+
+        let name = &export.name;
+        let result = result
+            .and_then(|gif| save(&format!("{name}.gif"), "image/gif", &gif))
+            .and_then(|()| match &export.replay {
+                Some(replay) => save(
+                    &format!("{name}.{}", super::replay::REPLAY_EXTENSION),
+                    "text/plain",
+                    replay.to_ron().as_bytes(),
+                ),
+                None => Ok(()),
+            });
+        let message = match result {
+            Ok(()) => format!("Saved {name}.gif"),
+            Err(err) => {
+                warn!("could not export the game: {err}");
+                err
+            }
+        };
+        if let Some(mut text) = export.status.and_then(|status| texts.get_mut(status).ok()) {
+            text.0 = message;
+        }
+    }
+}
+
+/// 画像フォルダに保存した記録。名前（拡張子なし）の新しいものから順に並べる。読めない記録は飛ばす
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) fn saved_replays() -> Vec<(String, Replay)> {
+    let suffix = format!(".{}", super::replay::REPLAY_EXTENSION);
+    let Some(entries) = export_dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut replays: Vec<(String, Replay)> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path
+                .file_name()?
+                .to_str()?
+                .strip_suffix(&suffix)?
+                .to_string();
+            let text = std::fs::read_to_string(&path).ok()?;
+            match Replay::from_ron(&text) {
+                Ok(replay) => Some((name, replay)),
+                Err(err) => {
+                    warn!("skipped {}: {err}", path.display());
+                    None
+                }
+            }
+        })
+        .collect();
+    replays.sort_by(|a, b| b.0.cmp(&a.0));
+    replays
+}
+
+/// 保存した記録を、今のテーマと設定でアニメーション GIF に書き出し直す。結果は `status` の文字に出す
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) fn export_saved_replay(
+    name: &str,
+    replay: &Replay,
+    settings: &GifSettings,
+    theme: &Theme,
+    labels: &LabelSettings,
+    font: &Font,
+    status: Entity,
+) -> GifExport {
+    GifExport {
+        status: Some(status),
+        ..GifExport::start(name.to_string(), replay, settings, theme, labels, font)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZero;
+
+    use super::*;
+    use crate::game::board::Direction;
+    use crate::game::testing::assert_close;
+
+    const FONT: &[u8] = include_bytes!("../../assets/fonts/DotGothic16-Regular.ttf");
+
+    fn decode(bytes: &[u8]) -> (png::OutputInfo, Vec<u8>) {
+        let mut reader = png::Decoder::new(std::io::Cursor::new(bytes))
+            .read_info()
+            .unwrap();
+        let mut data = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut data).unwrap();
+        data.truncate(info.buffer_size());
+        (info, data)
+    }
+
+    fn pixel(info: &png::OutputInfo, data: &[u8], at: Vec2) -> Color {
+        let index = (at.y as usize * info.width as usize + at.x as usize) * 4;
+        Color::srgba_u8(
+            data[index],
+            data[index + 1],
+            data[index + 2],
+            data[index + 3],
+        )
+    }
+
+    #[test]
+    fn exported_board_shows_tiles_and_empty_cells() {
+        let topology = Topology::Grid {
+            width: 4,
+            height: 4,
+        };
+        let mut board = Board::new(topology);
+        board[0] = NonZero::new(11);
+        let theme = Theme::default();
+        let bytes = render_png(
+            &[ExportedBoard {
+                board: &board,
+                score: 20_000,
+            }],
+            &theme,
+            &LabelSettings::default(),
+            FONT,
+            "2026-01-31",
+        )
+        .unwrap();
+
+        let (info, data) = decode(&bytes);
+        let extent = themed_board_extent(topology, &theme);
+        assert_eq!(info.width, (extent.x + MARGIN * 2.0).ceil() as u32);
+
+        // 数字にかからないセルの隅で色を見る
+        let center = Vec2::new(
+            MARGIN + extent.x / 2.0,
+            MARGIN + CAPTION_HEIGHT + extent.y / 2.0,
+        );
+        let corner = themed_cell_size(topology, &theme) * 0.4;
+        let at = |index: usize| {
+            let position = board_index_to_position(topology, index);
+            center + Vec2::new(position.x, -position.y) - corner
+        };
+        assert_close(
+            pixel(&info, &data, at(0)),
+            theme.tile(NonZero::new(11).unwrap()),
+        );
+        assert_close(pixel(&info, &data, at(1)), theme.empty_cell);
+        assert_close(pixel(&info, &data, Vec2::splat(1.0)), theme.background);
+    }
+
+    #[test]
+    fn replay_gif_animates_every_move() {
+        let topology = Topology::CLASSIC;
+        let mut board = Board::new(topology);
+        board[0] = NonZero::new(1);
+        board[1] = NonZero::new(1);
+        let mut replay = Replay::new(&board);
+        replay.push(Direction::Right, NonZero::new(2).map(|exp| (0, exp)));
+        let settings = GifSettings {
+            fps: 10,
+            last_moves: None,
+        };
+        let bytes = replay_gif(&replay, &settings, FONT).unwrap();
+
+        assert!(bytes.starts_with(b"GIF89a"));
+        assert_eq!(bytes.last(), Some(&0x3B));
+        let size = canvas_size(&[themed_board_extent(topology, &Theme::default())]);
+        assert_eq!(u16::from_le_bytes([bytes[6], bytes[7]]), size.x as u16);
+        assert_eq!(u16::from_le_bytes([bytes[8], bytes[9]]), size.y as u16);
+
+        // 最初の盤面、スライド 2 コマ、マージと出現 1 コマ、最後の盤面
+        let frames = bytes
+            .windows(4)
+            .filter(|w| w == &[0x21, 0xF9, 0x04, 0x04])
+            .count();
+        assert!(frames >= 5, "{frames} frames");
+    }
+
+    #[test]
+    fn quantize_keeps_up_to_256_colors_exact() {
+        let mut canvas = Canvas::new(20, 20, Color::BLACK);
+        canvas.fill_rect(
+            Vec2::splat(15.0),
+            Vec2::splat(10.0),
+            Color::srgb_u8(237, 194, 46),
+        );
+        let (palette, indices) = quantize(&canvas);
+        assert_eq!(palette[usize::from(indices[0])], [0, 0, 0]);
+        assert_eq!(palette[usize::from(indices[12 * 20 + 12])], [237, 194, 46]);
+    }
+
+    #[test]
+    fn time_is_formatted_for_captions_and_file_names() {
+        assert_eq!(
+            format_time([2026, 1, 31, 23, 59, 9]),
+            ("2026-01-31".to_string(), "20260131-235909".to_string())
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! ゲームパッド操作。
+//! プレイ中は十字キーと左スティックで盤面をスライドし、X で一手戻す・Y で新しいゲーム・
+//! B でヒント・Start で一時停止する。一時停止やゲームオーバーなどの画面では、
+//! 十字キー / スティックでボタンのフォーカスを移動して A で押す。
+
+use bevy::ecs::entity::EntityHashSet;
+use bevy::input::gamepad::GamepadConnectionEvent;
+use bevy::input_focus::{FocusCause, InputFocus, InputFocusVisible};
+use bevy::math::CompassOctant;
+use bevy::prelude::*;
+use bevy::ui::auto_directional_navigation::{AutoDirectionalNavigation, AutoDirectionalNavigator};
+
+use super::board::Board;
+use super::input::{Slide, drag_direction};
+use super::keybindings::Action;
+use super::menu::ScreenRoot;
+use super::session::Player;
+use super::ui::{OverlayRoot, Press};
+
+/// スティックをこれ以上倒したら 1 回の入力とみなす
+const STICK_PUSH: f32 = 0.6;
+/// いったんここまで戻すまで次の入力を受け付けない（1 回倒して 1 回だけスライドする）
+const STICK_RELEASE: f32 = 0.3;
+const FOCUS_OUTLINE: Color = Color::WHITE;
+
+/// 1 回分の方向入力（十字キーを押した / スティックを倒した）
+#[derive(Message)]
+pub(super) struct GamepadPush {
+    gamepad: Entity,
+    direction: Vec2,
+}
+
+/// ゲームパッドをつないだ順。対戦ではこの順に 1P・2P を割り当てる
+#[derive(Resource, Default)]
+pub(super) struct GamepadOrder(Vec<Entity>);
+
+impl GamepadOrder {
+    fn player_index(&self, gamepad: Entity) -> Option<usize> {
+        self.0.iter().position(|&connected| connected == gamepad)
+    }
+}
+
+/// 接続・切断に合わせてつないだ順を更新する
+pub(super) fn track_gamepad_order(
+    mut connections: MessageReader<GamepadConnectionEvent>,
+    mut order: ResMut<GamepadOrder>,
+) {
+    for event in connections.read() {
+        order.0.retain(|&gamepad| gamepad != event.gamepad);
+        if event.connected() {
+            order.0.push(event.gamepad);
+        }
+    }
+}
+
+/// 十字キー・スティック以外のボタンに割り当てた操作
+pub(super) fn action_button(action: Action) -> Option<GamepadButton> {
+    match action {
+        Action::Move(_) => None,
+        Action::Undo => Some(GamepadButton::West),
+        Action::NewGame => Some(GamepadButton::North),
+        Action::Hint => Some(GamepadButton::East),
+        Action::Pause => Some(GamepadButton::Start),
+    }
+}
+
+/// 十字キーの押下とスティックのはじきを方向入力に変換する
+pub(super) fn detect_gamepad_pushes(
+    gamepads: Query<(Entity, &Gamepad)>,
+    mut latched: Local<EntityHashSet>,
+    mut pushes: MessageWriter<GamepadPush>,
+) {
+    for (entity, gamepad) in &gamepads {
+        let dpad_pressed = [
+            GamepadButton::DPadUp,
+            GamepadButton::DPadDown,
+            GamepadButton::DPadLeft,
+            GamepadButton::DPadRight,
+        ]
+        .into_iter()
+        .any(|button| gamepad.just_pressed(button));
+        if dpad_pressed && gamepad.dpad() != Vec2::ZERO {
+            pushes.write(GamepadPush {
+                gamepad: entity,
+                direction: gamepad.dpad(),
+            });
+        }
+
+        let stick = gamepad.left_stick();
+        if latched.contains(&entity) {
+            if stick.length() < STICK_RELEASE {
+                latched.remove(&entity);
+            }
+        } else if stick.length() >= STICK_PUSH {
+            latched.insert(entity);
+            pushes.write(GamepadPush {
+                gamepad: entity,
+                direction: stick,
+            });
+        }
+    }
+    latched.retain(|entity| gamepads.contains(*entity));
+}
+
+/// プレイ中の方向入力を盤面のスライドにする。対戦では接続順に 1P・2P を割り当てる
+pub(super) fn slide_with_gamepad(
+    mut pushes: MessageReader<GamepadPush>,
+    order: Res<GamepadOrder>,
+    fields: Query<(Entity, &Player, &Board)>,
+    mut slides: MessageWriter<Slide>,
+) {
+    let solo = fields.iter().len() == 1;
+
+    for push in pushes.read() {
+        let Some(index) = order.player_index(push.gamepad) else {
+            continue;
+        };
+        let field = fields
+            .iter()
+            .find(|(_, player, _)| solo || player.0 == index);
+        if let Some((field, _, board)) = field
+            && let Some(direction) = drag_direction(board.topology(), push.direction)
+        {
+            slides.write(Slide { field, direction });
+        }
+    }
+}
+
+/// 画面上のボタン間でフォーカスを移動する
+pub(super) fn navigate_with_gamepad(
+    mut pushes: MessageReader<GamepadPush>,
+    mut navigator: AutoDirectionalNavigator,
+    mut visible: ResMut<InputFocusVisible>,
+) {
+    for push in pushes.read() {
+        if let Ok(direction) = Dir2::new(push.direction) {
+            let _ = navigator.navigate(CompassOctant::from(direction));
+            visible.0 = true;
+        }
+    }
+}
+
+/// A でフォーカス中のボタンを押す
+pub(super) fn confirm_with_gamepad(
+    mut commands: Commands,
+    gamepads: Query<&Gamepad>,
+    focus: Res<InputFocus>,
+) {
+    let Some(focused) = focus.get() else {
+        return;
+    };
+    if !gamepads
+        .iter()
+        .any(|gamepad| gamepad.just_pressed(GamepadButton::South))
+    {
+        return;
+    }
+
+    commands.trigger(Press { entity: focused });
+}
+
+/// フォーカス移動の対象を表示中の画面のボタンに絞る。
+/// メニューやキー設定などの画面ではその画面のボタンだけ、それ以外ではヘッダーとオーバーレイのボタンすべて
+pub(super) fn sync_navigable_buttons(
+    mut commands: Commands,
+    screens: Query<(), With<ScreenRoot>>,
+    buttons: Query<(Entity, Has<AutoDirectionalNavigation>), With<Button>>,
+    parents: Query<&ChildOf>,
+) {
+    let modal = !screens.is_empty();
+    for (button, navigable) in &buttons {
+        let wanted = !modal
+            || parents
+                .iter_ancestors(button)
+                .any(|ancestor| screens.contains(ancestor));
+        if wanted && !navigable {
+            commands
+                .entity(button)
+                .insert(AutoDirectionalNavigation::default());
+        } else if !wanted && navigable {
+            commands
+                .entity(button)
+                .remove::<AutoDirectionalNavigation>();
+        }
+    }
+}
+
+/// 画面が切り替わったら、新しい画面の最初のボタンにフォーカスを置く。
+/// プレイ中は十字キーを盤面の操作に使うのでフォーカスを外す
+pub(super) fn focus_first_button(
+    mut focus: ResMut<InputFocus>,
+    mut visible: ResMut<InputFocusVisible>,
+    gamepads: Query<(), With<Gamepad>>,
+    roots: Query<Entity, Or<(With<OverlayRoot>, With<ScreenRoot>)>>,
+    children: Query<&Children>,
+    buttons: Query<(), With<Button>>,
+) {
+    // 枠線はゲームパッドをつないでいるときだけ出す
+    visible.0 = !gamepads.is_empty();
+    let first = roots.iter().find_map(|root| {
+        children
+            .iter_descendants(root)
+            .find(|&entity| buttons.contains(entity))
+    });
+    match first {
+        Some(button) => focus.set(button, FocusCause::Navigated),
+        None => focus.clear(),
+    }
+}
+
+/// フォーカス中のボタンを枠線で示す
+pub(super) fn highlight_focused_button(
+    mut commands: Commands,
+    focus: Res<InputFocus>,
+    visible: Res<InputFocusVisible>,
+    buttons: Query<(Entity, Has<Outline>), With<Button>>,
+) {
+    if !focus.is_changed() && !visible.is_changed() {
+        return;
+    }
+    for (button, outlined) in &buttons {
+        let focused = visible.0 && focus.get() == Some(button);
+        if focused && !outlined {
+            commands
+                .entity(button)
+                .insert(Outline::new(Val::Px(3.0), Val::Px(2.0), FOCUS_OUTLINE));
+        } else if !focused && outlined {
+            commands.entity(button).remove::<Outline>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::gamepad::GamepadConnection;
+
+    use super::*;
+
+    fn connect(app: &mut App, gamepad: Entity) {
+        let connection = GamepadConnection::Connected {
+            name: String::new(),
+            vendor_id: None,
+            product_id: None,
+        };
+        app.world_mut()
+            .write_message(GamepadConnectionEvent::new(gamepad, connection));
+        app.update();
+    }
+
+    #[test]
+    fn players_follow_connection_order() {
+        let mut app = App::new();
+        app.add_message::<GamepadConnectionEvent>()
+            .init_resource::<GamepadOrder>()
+            .add_systems(Update, track_gamepad_order);
+        let first = app.world_mut().spawn_empty().id();
+        let second = app.world_mut().spawn_empty().id();
+
+        // 後から作られたエンティティでも、先につないだほうが 1P になる
+        connect(&mut app, second);
+        connect(&mut app, first);
+        let order = app.world().resource::<GamepadOrder>();
+        assert_eq!(order.player_index(second), Some(0));
+        assert_eq!(order.player_index(first), Some(1));
+
+        app.world_mut().write_message(GamepadConnectionEvent::new(
+            second,
+            GamepadConnection::Disconnected,
+        ));
+        app.update();
+        let order = app.world().resource::<GamepadOrder>();
+        assert_eq!(order.player_index(first), Some(0));
+        assert_eq!(order.player_index(second), None);
+    }
+}
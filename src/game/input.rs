@@ -1,53 +1,131 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 
-use super::board::Direction;
+use super::board::{Board, Direction};
+use super::gamepad::action_button;
+use super::keybindings::{Action, KeyBindings};
+use super::render::direction_vector;
+use super::topology::Topology;
 
 #[derive(Message)]
-pub(super) struct Slide(pub(super) Direction);
+pub(super) struct Slide {
+    /// 動かす盤面（`PlayField` エンティティ）
+    pub(super) field: Entity,
+    pub(super) direction: Direction,
+}
 
-pub(super) fn on_drag_end(drag_end: On<Pointer<DragEnd>>, mut move_message: MessageWriter<Slide>) {
-    if drag_end.button == PointerButton::Primary {
-        if drag_end.distance.length() < 50.0 {
-            return;
-        }
+/// キーボードとゲームパッドのどちらからでも操作を受け付ける
+#[derive(SystemParam)]
+pub(super) struct ActionInput<'w, 's> {
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    bindings: Res<'w, KeyBindings>,
+    gamepads: Query<'w, 's, &'static Gamepad>,
+}
+
+impl ActionInput<'_, '_> {
+    pub(super) fn just_pressed(&self, action: Action) -> bool {
+        self.bindings.just_pressed(action, &self.keys)
+            || action_button(action).is_some_and(|button| {
+                self.gamepads
+                    .iter()
+                    .any(|gamepad| gamepad.just_pressed(button))
+            })
+    }
+}
+
+type VersusKeys = [(Direction, &'static [KeyCode])];
+
+const GRID_KEYS_PLAYER_ONE: [(Direction, &[KeyCode]); 4] = [
+    (Direction::Up, &[KeyCode::KeyW]),
+    (Direction::Down, &[KeyCode::KeyS]),
+    (Direction::Left, &[KeyCode::KeyA]),
+    (Direction::Right, &[KeyCode::KeyD]),
+];
+
+const GRID_KEYS_PLAYER_TWO: [(Direction, &[KeyCode]); 4] = [
+    (Direction::Up, &[KeyCode::ArrowUp]),
+    (Direction::Down, &[KeyCode::ArrowDown]),
+    (Direction::Left, &[KeyCode::ArrowLeft]),
+    (Direction::Right, &[KeyCode::ArrowRight]),
+];
 
-        if drag_end.distance.x.abs() > drag_end.distance.y.abs() {
-            if drag_end.distance.x > 0.0 {
-                move_message.write(Slide(Direction::Right));
-            } else {
-                move_message.write(Slide(Direction::Left));
+/// 六角形盤面の 1P は QWE / ASD の 6 キーをそれぞれの方向に割り当てる
+const HEX_KEYS_PLAYER_ONE: [(Direction, &[KeyCode]); 6] = [
+    (Direction::UpLeft, &[KeyCode::KeyQ]),
+    (Direction::Up, &[KeyCode::KeyW]),
+    (Direction::UpRight, &[KeyCode::KeyE]),
+    (Direction::DownLeft, &[KeyCode::KeyA]),
+    (Direction::Down, &[KeyCode::KeyS]),
+    (Direction::DownRight, &[KeyCode::KeyD]),
+];
+
+/// 対戦時の 2P は右手側の UIO / JKL を QWE / ASD と同じ配置で使う
+const HEX_KEYS_PLAYER_TWO: [(Direction, &[KeyCode]); 6] = [
+    (Direction::UpLeft, &[KeyCode::KeyU]),
+    (Direction::Up, &[KeyCode::KeyI, KeyCode::ArrowUp]),
+    (Direction::UpRight, &[KeyCode::KeyO]),
+    (Direction::DownLeft, &[KeyCode::KeyJ]),
+    (Direction::Down, &[KeyCode::KeyK, KeyCode::ArrowDown]),
+    (Direction::DownRight, &[KeyCode::KeyL]),
+];
+
+/// 盤面ごとのキー割り当て
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum Keymap {
+    /// 1 人プレイ。キー設定（`KeyBindings`）に従う
+    Solo,
+    /// 対戦の 1P（左手側のキー）
+    PlayerOne,
+    /// 対戦の 2P（矢印キー / 右手側のキー）
+    PlayerTwo,
+}
+
+impl Keymap {
+    /// 押された方向。対戦時は左右で固定の配置を使う
+    fn pressed(
+        self,
+        topology: Topology,
+        bindings: &KeyBindings,
+        keys: &ButtonInput<KeyCode>,
+    ) -> Option<Direction> {
+        let versus: &VersusKeys = match (topology, self) {
+            (_, Self::Solo) => {
+                return topology
+                    .directions()
+                    .iter()
+                    .copied()
+                    .find(|&direction| bindings.just_pressed(Action::Move(direction), keys));
             }
-        } else if drag_end.distance.y > 0.0 {
-            move_message.write(Slide(Direction::Down));
-        } else {
-            move_message.write(Slide(Direction::Up));
-        }
+            (Topology::Grid { .. }, Self::PlayerOne) => &GRID_KEYS_PLAYER_ONE,
+            (Topology::Grid { .. }, Self::PlayerTwo) => &GRID_KEYS_PLAYER_TWO,
+            (Topology::Hex { .. }, Self::PlayerOne) => &HEX_KEYS_PLAYER_ONE,
+            (Topology::Hex { .. }, Self::PlayerTwo) => &HEX_KEYS_PLAYER_TWO,
+        };
+        versus
+            .iter()
+            .find(|(_, codes)| keys.any_just_pressed(codes.iter().copied()))
+            .map(|(direction, _)| *direction)
     }
 }
 
+/// ドラッグ量（またはスティックの傾き）からトポロジー上で最も近いスライド方向を選ぶ。
+/// 正方格子では 4 象限、六角形盤面では 60° ずつの 6 セクターに分かれる。
+pub(super) fn drag_direction(topology: Topology, drag: Vec2) -> Option<Direction> {
+    topology.directions().iter().copied().max_by(|a, b| {
+        drag.dot(direction_vector(*a))
+            .total_cmp(&drag.dot(direction_vector(*b)))
+    })
+}
+
 pub(super) fn handle_input(
     keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    fields: Query<(Entity, &Board, &Keymap)>,
     mut move_message: MessageWriter<Slide>,
 ) {
-    if keys.just_pressed(KeyCode::KeyW)
-        || keys.just_pressed(KeyCode::ArrowUp)
-        || keys.just_pressed(KeyCode::KeyK)
-    {
-        move_message.write(Slide(Direction::Up));
-    } else if keys.just_pressed(KeyCode::KeyS)
-        || keys.just_pressed(KeyCode::ArrowDown)
-        || keys.just_pressed(KeyCode::KeyJ)
-    {
-        move_message.write(Slide(Direction::Down));
-    } else if keys.just_pressed(KeyCode::KeyA)
-        || keys.just_pressed(KeyCode::ArrowLeft)
-        || keys.just_pressed(KeyCode::KeyH)
-    {
-        move_message.write(Slide(Direction::Left));
-    } else if keys.just_pressed(KeyCode::KeyD)
-        || keys.just_pressed(KeyCode::ArrowRight)
-        || keys.just_pressed(KeyCode::KeyL)
-    {
-        move_message.write(Slide(Direction::Right));
+    for (field, board, keymap) in &fields {
+        if let Some(direction) = keymap.pressed(board.topology(), &bindings, &keys) {
+            move_message.write(Slide { field, direction });
+        }
     }
 }
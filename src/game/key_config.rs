@@ -0,0 +1,269 @@
+//! キー設定画面。操作をクリックしてから押したキーを割り当てる。
+//! 変更はその場でユーザー設定ファイルに保存する。
+
+use bevy::prelude::*;
+
+use super::GameFont;
+use super::keybindings::{Action, KeyBindings, is_supported_key, key_label, save_key_bindings};
+use super::menu::ScreenRoot;
+use super::state::Screen;
+use super::theme::UiColor;
+use super::ui::{Press, spawn_overlay_button};
+
+const IDLE_MESSAGE: &str = "Click an action, then press a key";
+
+/// 操作ごとの割り当て済みキーを表示するテキスト
+#[derive(Component)]
+pub(super) struct KeyListText(Action);
+
+#[derive(Component)]
+pub(super) struct KeyConfigStatus;
+
+/// キー入力を待っている操作
+#[derive(Resource, Default)]
+pub(super) struct KeyCapture(Option<Action>);
+
+pub(super) fn open_key_config(_press: On<Press>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::KeyConfig);
+}
+
+fn text_font(font: &Handle<Font>, size: f32) -> TextFont {
+    TextFont {
+        font: font.clone().into(),
+        font_size: size.into(),
+        ..default()
+    }
+}
+
+pub(super) fn spawn_key_config(mut commands: Commands, font: Res<GameFont>) {
+    commands.insert_resource(KeyCapture::default());
+    let font = &font.0;
+
+    commands
+        .spawn((
+            ScreenRoot,
+            DespawnOnExit(Screen::KeyConfig),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            UiColor::Background,
+            ZIndex(10),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Stretch,
+                        row_gap: Val::Px(4.0),
+                        padding: UiRect::all(Val::Px(20.0)),
+                        border_radius: BorderRadius::all(Val::Px(8.0)),
+                        ..default()
+                    },
+                    UiColor::Panel,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new("Keys"),
+                        text_font(font, 32.0),
+                        UiColor::OverlayText,
+                        Node {
+                            align_self: AlignSelf::Center,
+                            margin: UiRect::bottom(Val::Px(8.0)),
+                            ..default()
+                        },
+                    ));
+
+                    for action in Action::ALL {
+                        spawn_binding_row(parent, action, font);
+                    }
+
+                    parent.spawn((
+                        KeyConfigStatus,
+                        Text::new(IDLE_MESSAGE),
+                        text_font(font, 16.0),
+                        UiColor::OverlaySubtext,
+                        Node {
+                            align_self: AlignSelf::Center,
+                            margin: UiRect::top(Val::Px(8.0)),
+                            ..default()
+                        },
+                    ));
+
+                    parent
+                        .spawn(Node {
+                            flex_direction: FlexDirection::Row,
+                            justify_content: JustifyContent::Center,
+                            column_gap: Val::Px(12.0),
+                            margin: UiRect::top(Val::Px(8.0)),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            spawn_overlay_button(parent, "Reset", font).observe(on_reset_click);
+                            spawn_overlay_button(parent, "Done", font).observe(on_done_click);
+                        });
+                });
+        });
+}
+
+/// 1 つの操作の行。行をクリックするとキー入力待ちになり、Clear で割り当てを外す
+fn spawn_binding_row(parent: &mut ChildSpawnerCommands, action: Action, font: &Handle<Font>) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                flex_direction: FlexDirection::Row,
+                justify_content: JustifyContent::SpaceBetween,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(16.0),
+                padding: UiRect::axes(Val::Px(12.0), Val::Px(2.0)),
+                border_radius: BorderRadius::all(Val::Px(4.0)),
+                ..default()
+            },
+            UiColor::Button,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(action.label()),
+                text_font(font, 18.0),
+                UiColor::ButtonText,
+                Node {
+                    width: Val::Px(170.0),
+                    ..default()
+                },
+            ));
+            parent.spawn((
+                KeyListText(action),
+                Text::default(),
+                text_font(font, 18.0),
+                UiColor::ButtonText,
+                Node {
+                    width: Val::Px(220.0),
+                    ..default()
+                },
+            ));
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                        ..default()
+                    },
+                    UiColor::Button,
+                    children![(
+                        Text::new("Clear"),
+                        text_font(font, 14.0),
+                        UiColor::ButtonText,
+                    )],
+                ))
+                .observe(
+                    move |_press: On<Press>,
+                          mut bindings: ResMut<KeyBindings>,
+                          mut capture: ResMut<KeyCapture>| {
+                        bindings.clear(action);
+                        capture.0 = None;
+                        save_key_bindings(&bindings);
+                    },
+                );
+        })
+        .observe(
+            move |_press: On<Press>,
+                  mut capture: ResMut<KeyCapture>,
+                  mut status: Query<&mut Text, With<KeyConfigStatus>>| {
+                capture.0 = Some(action);
+                for mut text in &mut status {
+                    text.0 = format!("Press a key for {} (Esc to cancel)", action.label());
+                }
+            },
+        );
+}
+
+fn on_reset_click(
+    _press: On<Press>,
+    mut bindings: ResMut<KeyBindings>,
+    mut capture: ResMut<KeyCapture>,
+    mut status: Query<&mut Text, With<KeyConfigStatus>>,
+) {
+    *bindings = KeyBindings::default();
+    capture.0 = None;
+    save_key_bindings(&bindings);
+    for mut text in &mut status {
+        text.0 = "Restored the default keys".to_string();
+    }
+}
+
+fn on_done_click(_press: On<Press>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Settings);
+}
+
+/// 入力待ちの操作に押されたキーを割り当てる。ほかの操作と衝突するキーは割り当てない。
+/// 入力待ちでないときの Esc は画面を閉じて設定画面に戻る
+pub(super) fn capture_key(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut capture: ResMut<KeyCapture>,
+    mut bindings: ResMut<KeyBindings>,
+    mut status: Query<&mut Text, With<KeyConfigStatus>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let Some(action) = capture.0 else {
+        if keys.just_pressed(KeyCode::Escape) {
+            next_screen.set(Screen::Settings);
+        }
+        return;
+    };
+    let Some(&key) = keys
+        .get_just_pressed()
+        .find(|&&key| key == KeyCode::Escape || is_supported_key(key))
+    else {
+        return;
+    };
+
+    let message = if key == KeyCode::Escape {
+        IDLE_MESSAGE.to_string()
+    } else {
+        match bindings.bind(action, key) {
+            Ok(()) => {
+                save_key_bindings(&bindings);
+                format!("{} → {}", key_label(key), action.label())
+            }
+            Err(other) => format!("{} is already used by {}", key_label(key), other.label()),
+        }
+    };
+    capture.0 = None;
+    for mut text in &mut status {
+        text.0.clone_from(&message);
+    }
+}
+
+/// 割り当て済みのキーの表示を更新する
+pub(super) fn sync_key_list(
+    bindings: Res<KeyBindings>,
+    capture: Res<KeyCapture>,
+    mut texts: Query<(&KeyListText, &mut Text)>,
+) {
+    if !bindings.is_changed() && !capture.is_changed() {
+        return;
+    }
+    for (list, mut text) in &mut texts {
+        text.0 = if capture.0 == Some(list.0) {
+            "...".to_string()
+        } else {
+            let keys: Vec<String> = bindings
+                .keys(list.0)
+                .iter()
+                .copied()
+                .map(key_label)
+                .collect();
+            if keys.is_empty() {
+                "-".to_string()
+            } else {
+                keys.join(", ")
+            }
+        };
+    }
+}
@@ -0,0 +1,458 @@
+//! 操作とキーの対応表。ユーザー設定ファイルから読み込み、キー設定画面で変更できる。
+//!
+//! 設定ファイルは 1 行に 1 つの操作を `操作 = キー, キー` の形で書く。
+//! キー名は Bevy の `KeyCode` の名前（`KeyW`・`ArrowUp`・`Numpad8` など）を使う。
+//!
+//! ```text
+//! # Colemak の例
+//! move_up = KeyW, ArrowUp
+//! move_left = KeyA, ArrowLeft
+//! move_down = KeyR, ArrowDown
+//! move_right = KeyS, ArrowRight
+//! ```
+
+use std::fmt::Write as _;
+
+use bevy::prelude::*;
+
+use super::board::Direction;
+use super::topology::Topology;
+
+/// 1 つの操作に割り当てられるキーの数
+const MAX_KEYS_PER_ACTION: usize = 3;
+
+/// キーで行える操作
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(super) enum Action {
+    Move(Direction),
+    Undo,
+    NewGame,
+    Hint,
+    Pause,
+}
+
+impl Action {
+    pub(super) const ALL: [Self; 12] = [
+        Self::Move(Direction::Up),
+        Self::Move(Direction::Down),
+        Self::Move(Direction::Left),
+        Self::Move(Direction::Right),
+        Self::Move(Direction::UpLeft),
+        Self::Move(Direction::UpRight),
+        Self::Move(Direction::DownLeft),
+        Self::Move(Direction::DownRight),
+        Self::Undo,
+        Self::NewGame,
+        Self::Hint,
+        Self::Pause,
+    ];
+
+    /// 設定ファイルでの名前
+    fn config_name(self) -> &'static str {
+        match self {
+            Self::Move(Direction::Up) => "move_up",
+            Self::Move(Direction::Down) => "move_down",
+            Self::Move(Direction::Left) => "move_left",
+            Self::Move(Direction::Right) => "move_right",
+            Self::Move(Direction::UpLeft) => "move_up_left",
+            Self::Move(Direction::UpRight) => "move_up_right",
+            Self::Move(Direction::DownLeft) => "move_down_left",
+            Self::Move(Direction::DownRight) => "move_down_right",
+            Self::Undo => "undo",
+            Self::NewGame => "new_game",
+            Self::Hint => "hint",
+            Self::Pause => "pause",
+        }
+    }
+
+    pub(super) fn label(self) -> &'static str {
+        match self {
+            Self::Move(Direction::Up) => "Up",
+            Self::Move(Direction::Down) => "Down",
+            Self::Move(Direction::Left) => "Left",
+            Self::Move(Direction::Right) => "Right",
+            Self::Move(Direction::UpLeft) => "Up-Left (Hex)",
+            Self::Move(Direction::UpRight) => "Up-Right (Hex)",
+            Self::Move(Direction::DownLeft) => "Down-Left (Hex)",
+            Self::Move(Direction::DownRight) => "Down-Right (Hex)",
+            Self::Undo => "Undo",
+            Self::NewGame => "New Game",
+            Self::Hint => "Hint",
+            Self::Pause => "Pause",
+        }
+    }
+
+    /// 同じキーを割り当てると衝突するか。
+    /// 移動どうしは同じ盤面で同時に使う方向のときだけ衝突する（正方格子の左と六角形の左下など）
+    fn conflicts_with(self, other: Self) -> bool {
+        match (self, other) {
+            _ if self == other => false,
+            (Self::Move(a), Self::Move(b)) => {
+                [Topology::CLASSIC, Topology::HEX].iter().any(|topology| {
+                    let directions = topology.directions();
+                    directions.contains(&a) && directions.contains(&b)
+                })
+            }
+            _ => true,
+        }
+    }
+}
+
+/// 操作ごとのキー割り当て。`Action::ALL` と同じ順に並べて持つ
+#[derive(Resource, Clone, PartialEq, Eq, Debug)]
+pub(super) struct KeyBindings {
+    keys: Vec<(Action, Vec<KeyCode>)>,
+}
+
+impl Default for KeyBindings {
+    /// WASD・矢印・HJKL で移動、六角形盤面の斜めは QE / AD
+    fn default() -> Self {
+        let keys = Action::ALL.map(|action| {
+            let keys: &[KeyCode] = match action {
+                Action::Move(Direction::Up) => &[KeyCode::KeyW, KeyCode::ArrowUp, KeyCode::KeyK],
+                Action::Move(Direction::Down) => {
+                    &[KeyCode::KeyS, KeyCode::ArrowDown, KeyCode::KeyJ]
+                }
+                Action::Move(Direction::Left) => {
+                    &[KeyCode::KeyA, KeyCode::ArrowLeft, KeyCode::KeyH]
+                }
+                Action::Move(Direction::Right) => {
+                    &[KeyCode::KeyD, KeyCode::ArrowRight, KeyCode::KeyL]
+                }
+                Action::Move(Direction::UpLeft) => &[KeyCode::KeyQ],
+                Action::Move(Direction::UpRight) => &[KeyCode::KeyE],
+                Action::Move(Direction::DownLeft) => &[KeyCode::KeyA],
+                Action::Move(Direction::DownRight) => &[KeyCode::KeyD],
+                Action::Undo => &[KeyCode::KeyZ, KeyCode::Backspace],
+                Action::NewGame => &[KeyCode::KeyN],
+                Action::Hint => &[KeyCode::Space],
+                Action::Pause => &[KeyCode::Escape, KeyCode::KeyP],
+            };
+            (action, keys.to_vec())
+        });
+        Self {
+            keys: keys.to_vec(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub(super) fn keys(&self, action: Action) -> &[KeyCode] {
+        self.keys
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map_or(&[], |(_, keys)| keys.as_slice())
+    }
+
+    pub(super) fn just_pressed(&self, action: Action, input: &ButtonInput<KeyCode>) -> bool {
+        input.any_just_pressed(self.keys(action).iter().copied())
+    }
+
+    /// `key` を `action` に割り当てたときに衝突する操作
+    pub(super) fn conflict(&self, action: Action, key: KeyCode) -> Option<Action> {
+        self.keys
+            .iter()
+            .find(|(other, keys)| action.conflicts_with(*other) && keys.contains(&key))
+            .map(|(other, _)| *other)
+    }
+
+    /// `action` にキーを追加する。上限を超えたら古いキーから外す。
+    /// 衝突する操作があれば割り当てずにその操作を返す
+    pub(super) fn bind(&mut self, action: Action, key: KeyCode) -> Result<(), Action> {
+        if let Some(other) = self.conflict(action, key) {
+            return Err(other);
+        }
+        self.push(action, key);
+        Ok(())
+    }
+
+    /// 衝突を確かめずに `action` へキーを追加する。上限を超えたら古いキーから外す
+    fn push(&mut self, action: Action, key: KeyCode) {
+        let Some((_, keys)) = self.keys.iter_mut().find(|(bound, _)| *bound == action) else {
+            return;
+        };
+        if !keys.contains(&key) {
+            if keys.len() == MAX_KEYS_PER_ACTION {
+                keys.remove(0);
+            }
+            keys.push(key);
+        }
+    }
+
+    pub(super) fn clear(&mut self, action: Action) {
+        if let Some((_, keys)) = self.keys.iter_mut().find(|(bound, _)| *bound == action) {
+            keys.clear();
+        }
+    }
+
+    /// 設定ファイルを読み込む。書かれていない操作は既定のキーのまま。
+    /// 操作の間でキーを入れ替えた設定も読めるよう、衝突は全行を反映してから確かめる
+    pub(super) fn parse(text: &str) -> Result<Self, String> {
+        let mut bindings = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {message}", number + 1);
+
+            let (name, keys) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected `action = key, ...`".to_string()))?;
+            let action = Action::ALL
+                .into_iter()
+                .find(|action| action.config_name() == name.trim())
+                .ok_or_else(|| error(format!("unknown action `{}`", name.trim())))?;
+
+            bindings.clear(action);
+            for key in keys.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+                let key = parse_key(key).ok_or_else(|| error(format!("unknown key `{key}`")))?;
+                bindings.push(action, key);
+            }
+        }
+
+        for (action, keys) in &bindings.keys {
+            if let Some((key, other)) = keys
+                .iter()
+                .find_map(|&key| Some((key, bindings.conflict(*action, key)?)))
+            {
+                return Err(format!(
+                    "{key:?} is bound to both {} and {}",
+                    action.config_name(),
+                    other.config_name()
+                ));
+            }
+        }
+        Ok(bindings)
+    }
+
+    pub(super) fn to_config_string(&self) -> String {
+        let mut text = String::new();
+        for (action, keys) in &self.keys {
+            let keys: Vec<String> = keys.iter().map(|key| format!("{key:?}")).collect();
+            let _ = writeln!(text, "{} = {}", action.config_name(), keys.join(", "));
+        }
+        text
+    }
+}
+
+/// 割り当てられるキー。設定ファイルではこの名前（`KeyCode` の Debug 表記）で書く
+const SUPPORTED_KEYS: [KeyCode; 86] = [
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyD,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyQ,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyX,
+    KeyCode::KeyY,
+    KeyCode::KeyZ,
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::Numpad0,
+    KeyCode::Numpad1,
+    KeyCode::Numpad2,
+    KeyCode::Numpad3,
+    KeyCode::Numpad4,
+    KeyCode::Numpad5,
+    KeyCode::Numpad6,
+    KeyCode::Numpad7,
+    KeyCode::Numpad8,
+    KeyCode::Numpad9,
+    KeyCode::NumpadAdd,
+    KeyCode::NumpadSubtract,
+    KeyCode::NumpadMultiply,
+    KeyCode::NumpadDivide,
+    KeyCode::NumpadDecimal,
+    KeyCode::NumpadEnter,
+    KeyCode::ArrowUp,
+    KeyCode::ArrowDown,
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowRight,
+    KeyCode::Space,
+    KeyCode::Enter,
+    KeyCode::Backspace,
+    KeyCode::Tab,
+    KeyCode::Escape,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Quote,
+    KeyCode::BracketLeft,
+    KeyCode::BracketRight,
+    KeyCode::Backslash,
+    KeyCode::Minus,
+    KeyCode::Equal,
+    KeyCode::Backquote,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+];
+
+pub(super) fn is_supported_key(key: KeyCode) -> bool {
+    SUPPORTED_KEYS.contains(&key)
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    SUPPORTED_KEYS
+        .into_iter()
+        .find(|key| format!("{key:?}") == name)
+}
+
+/// 画面表示用のキー名（`KeyW` → `W`、`Digit1` → `1`）
+pub(super) fn key_label(key: KeyCode) -> String {
+    let name = format!("{key:?}");
+    name.strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+/// ユーザー設定ファイルの場所（Web 版では保存しない）
+#[cfg(not(target_arch = "wasm32"))]
+fn config_path() -> Option<std::path::PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(std::path::PathBuf::from))
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".config"))
+        })?;
+    Some(base.join("bevy_2048").join("keybindings.cfg"))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn config_path() -> Option<std::path::PathBuf> {
+    None
+}
+
+/// 設定ファイルがあれば読み込む。読めない・壊れている場合は既定のキーを使う
+pub(super) fn load_key_bindings(mut commands: Commands) {
+    let bindings = config_path()
+        .and_then(|path| {
+            let text = std::fs::read_to_string(&path).ok()?;
+            KeyBindings::parse(&text)
+                .inspect_err(|error| warn!("ignoring {}: {error}", path.display()))
+                .ok()
+        })
+        .unwrap_or_default();
+    commands.insert_resource(bindings);
+}
+
+pub(super) fn save_key_bindings(bindings: &KeyBindings) {
+    let Some(path) = config_path() else {
+        return;
+    };
+    let saved = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&path, bindings.to_config_string()));
+    if let Err(error) = saved {
+        warn!("failed to save {}: {error}", path.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_round_trips_and_keeps_unlisted_defaults() {
+        let mut bindings = KeyBindings::default();
+        bindings.clear(Action::Move(Direction::Up));
+        bindings
+            .bind(Action::Move(Direction::Up), KeyCode::Numpad8)
+            .unwrap();
+
+        let parsed = KeyBindings::parse(&bindings.to_config_string()).unwrap();
+        assert_eq!(parsed, bindings);
+
+        let partial = KeyBindings::parse("# comment\nhint = KeyI\n").unwrap();
+        assert_eq!(partial.keys(Action::Hint), &[KeyCode::KeyI]);
+        assert_eq!(
+            partial.keys(Action::Undo),
+            KeyBindings::default().keys(Action::Undo)
+        );
+
+        assert!(KeyBindings::parse("jump = Space").is_err());
+        assert!(KeyBindings::parse("hint = NotAKey").is_err());
+        assert!(KeyBindings::parse("hint = KeyW").is_err());
+    }
+
+    #[test]
+    fn config_round_trips_keys_swapped_between_actions() {
+        // 上の行には下の既定キーが書かれるが、下の行まで読めば衝突しない
+        let up = Action::Move(Direction::Up);
+        let down = Action::Move(Direction::Down);
+        let mut bindings = KeyBindings::default();
+        bindings.clear(up);
+        bindings.clear(down);
+        bindings.bind(up, KeyCode::KeyS).unwrap();
+        bindings.bind(down, KeyCode::KeyW).unwrap();
+
+        let parsed = KeyBindings::parse(&bindings.to_config_string()).unwrap();
+        assert_eq!(parsed, bindings);
+    }
+
+    #[test]
+    fn conflicts_only_within_the_same_board() {
+        let mut bindings = KeyBindings::default();
+        // 正方格子の「左」と六角形の「左下」は同時に使わないので同じキーでよい
+        assert_eq!(
+            bindings.conflict(Action::Move(Direction::DownLeft), KeyCode::KeyA),
+            None
+        );
+        assert_eq!(
+            bindings.conflict(Action::Move(Direction::Up), KeyCode::KeyA),
+            Some(Action::Move(Direction::Left))
+        );
+        assert_eq!(
+            bindings.bind(Action::Hint, KeyCode::KeyQ),
+            Err(Action::Move(Direction::UpLeft))
+        );
+
+        for key in [KeyCode::Numpad1, KeyCode::Numpad2, KeyCode::Numpad3] {
+            bindings.bind(Action::Hint, key).unwrap();
+        }
+        assert_eq!(
+            bindings.keys(Action::Hint),
+            &[KeyCode::Numpad1, KeyCode::Numpad2, KeyCode::Numpad3]
+        );
+    }
+}
@@ -0,0 +1,640 @@
+//! タイトル・ゲームの選択・設定・成績・記録の画面。
+//! どの画面も盤面を隠す背景の上にパネルを出し、`DespawnOnExit` でその画面を抜けると消える。
+
+use std::num::NonZero;
+
+use bevy::prelude::*;
+
+use super::GameFont;
+use super::accessibility::{TileAccessibility, TilePalette};
+use super::board::{Board, Score, exp_to_value};
+use super::input::ActionInput;
+use super::key_config::open_key_config;
+use super::keybindings::Action;
+use super::session::{GameMode, GameReset, MatchConfig, PlayField};
+use super::skin::{TileSkin, cycle_skin};
+use super::spawner::Spawner;
+use super::state::{MatchOutcome, Screen};
+use super::theme::{ColorScheme, Theme, UiColor, cycle_color_scheme, cycle_theme};
+use super::topology::Topology;
+use super::ui::{Press, spawn_labeled_button, spawn_overlay_button};
+
+/// 記録の画面に並べる数
+#[cfg(not(target_arch = "wasm32"))]
+const REPLAY_LIST_LIMIT: usize = 8;
+
+/// ウィンドウいっぱいに盤面を隠して出す画面。ゲームパッドではこの画面のボタンだけを選べる
+#[derive(Component)]
+pub(super) struct ScreenRoot;
+
+#[derive(Component)]
+pub(super) struct ModeText;
+
+#[derive(Component)]
+pub(super) struct VariantText;
+
+#[derive(Component)]
+pub(super) struct SpawnerText;
+
+#[derive(Component)]
+pub(super) struct ThemeText;
+
+#[derive(Component)]
+pub(super) struct ColorSchemeText;
+
+#[derive(Component)]
+pub(super) struct PaletteText;
+
+#[derive(Component)]
+pub(super) struct CuesText;
+
+#[derive(Component)]
+pub(super) struct SkinText;
+
+/// 記録の画面で、書き出した結果を出すテキスト
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Component)]
+pub(super) struct ReplayStatus;
+
+/// 設定画面の Back で戻る画面（タイトルか一時停止）
+#[derive(Resource, Default)]
+pub(super) struct SettingsReturn(Screen);
+
+/// ゲームの選択画面で選んでいる設定。Start を押すまで今のゲームには影響しない
+#[derive(Resource, Default)]
+pub(super) struct ModeChoice(MatchConfig);
+
+/// 起動してからの成績
+#[derive(Resource, Default, Clone)]
+pub(super) struct SessionStats {
+    /// 最後まで遊んだゲームの数
+    games: u32,
+    /// 2048 に到達した回数
+    wins: u32,
+    best_score: u32,
+    best_tile: Option<NonZero<u8>>,
+}
+
+impl SessionStats {
+    fn record<'a>(&mut self, fields: impl IntoIterator<Item = (&'a Board, &'a Score)>) {
+        for (board, score) in fields {
+            self.best_score = self.best_score.max(**score);
+            self.best_tile = self.best_tile.max(board.iter().flatten().copied().max());
+        }
+    }
+}
+
+/// ボタンを押すと `screen` に移る
+pub(super) fn open_screen(screen: Screen) -> impl Fn(On<Press>, ResMut<NextState<Screen>>) {
+    move |_press, mut next_screen| next_screen.set(screen)
+}
+
+/// 設定画面を開く。Back で今の画面に戻る
+pub(super) fn open_settings(
+    _press: On<Press>,
+    screen: Res<State<Screen>>,
+    mut settings_return: ResMut<SettingsReturn>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    settings_return.0 = *screen.get();
+    next_screen.set(Screen::Settings);
+}
+
+fn close_settings(
+    _press: On<Press>,
+    settings_return: Res<SettingsReturn>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    next_screen.set(settings_return.0);
+}
+
+/// 一時停止キー（Esc）でメニューの画面を 1 つ戻る。
+/// 一時停止とプレイ中の切り替えは `toggle_pause`、キー設定画面は `capture_key` が受け持つ
+pub(super) fn back_on_pause_key(
+    input: ActionInput,
+    screen: Res<State<Screen>>,
+    settings_return: Res<SettingsReturn>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    if !input.just_pressed(Action::Pause) {
+        return;
+    }
+    match screen.get() {
+        Screen::ModeSelect | Screen::Stats | Screen::Replays => next_screen.set(Screen::Title),
+        Screen::Settings => next_screen.set(settings_return.0),
+        _ => {}
+    }
+}
+
+fn text_font(font: &Handle<Font>, size: f32) -> TextFont {
+    TextFont {
+        font: font.clone().into(),
+        font_size: size.into(),
+        ..default()
+    }
+}
+
+/// 画面の土台。盤面を隠す背景の上にタイトル付きのパネルを出し、`content` でパネルの中身を足す
+fn spawn_screen(
+    commands: &mut Commands,
+    screen: Screen,
+    title: &str,
+    font: &Handle<Font>,
+    content: impl FnOnce(&mut ChildSpawnerCommands),
+) {
+    commands
+        .spawn((
+            ScreenRoot,
+            DespawnOnExit(screen),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            UiColor::Background,
+            ZIndex(10),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(12.0),
+                        padding: UiRect::all(Val::Px(32.0)),
+                        border_radius: BorderRadius::all(Val::Px(8.0)),
+                        ..default()
+                    },
+                    UiColor::Panel,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new(title),
+                        text_font(font, 48.0),
+                        UiColor::OverlayText,
+                        Node {
+                            margin: UiRect::bottom(Val::Px(8.0)),
+                            ..default()
+                        },
+                    ));
+                    content(parent);
+                });
+        });
+}
+
+/// 左に見出し、右に `value`（ボタンや値）を置く行
+fn spawn_row(
+    parent: &mut ChildSpawnerCommands,
+    caption: &str,
+    font: &Handle<Font>,
+    value: impl FnOnce(&mut ChildSpawnerCommands),
+) {
+    parent
+        .spawn(Node {
+            min_width: Val::Px(360.0),
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::SpaceBetween,
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(24.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(caption),
+                text_font(font, 20.0),
+                UiColor::OverlaySubtext,
+            ));
+            value(parent);
+        });
+}
+
+/// 画面の下に並べるボタンの行
+fn spawn_button_row(
+    parent: &mut ChildSpawnerCommands,
+    buttons: impl FnOnce(&mut ChildSpawnerCommands),
+) {
+    parent
+        .spawn(Node {
+            flex_direction: FlexDirection::Row,
+            column_gap: Val::Px(12.0),
+            margin: UiRect::top(Val::Px(8.0)),
+            ..default()
+        })
+        .with_children(buttons);
+}
+
+pub(super) fn spawn_title(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    fields: Query<&Board, With<PlayField>>,
+    outcome: Res<MatchOutcome>,
+) {
+    // 勝負がついておらず動かせる盤面が残っていれば、遊んでいたゲームに戻れる。
+    // 盤面の状態は画面を離れると `Playing` に戻るので、決着した対戦を再開させない
+    let resumable = outcome.winner.is_none() && fields.iter().any(Board::can_move);
+    let font = &font.0;
+    spawn_screen(&mut commands, Screen::Title, "2048", font, |parent| {
+        if resumable {
+            spawn_overlay_button(parent, "Continue", font).observe(open_screen(Screen::Playing));
+        }
+        spawn_overlay_button(parent, "New Game", font).observe(open_screen(Screen::ModeSelect));
+        spawn_overlay_button(parent, "Settings", font).observe(open_settings);
+        spawn_overlay_button(parent, "Stats", font).observe(open_screen(Screen::Stats));
+        spawn_overlay_button(parent, "Replays", font).observe(open_screen(Screen::Replays));
+    });
+}
+
+fn mode_label(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Solo => "1P",
+        GameMode::Versus => "2P",
+    }
+}
+
+fn variant_label(topology: Topology) -> &'static str {
+    match topology {
+        Topology::Grid {
+            width: 4,
+            height: 4,
+        } => "Classic",
+        Topology::Grid { .. } => "Mini",
+        Topology::Hex { .. } => "Hex",
+    }
+}
+
+fn spawner_label(spawner: Spawner) -> String {
+    match spawner {
+        Spawner::Random => "Normal".to_string(),
+        Spawner::Evil { depth } => format!("Evil {depth}"),
+        Spawner::Human => "Hot-seat".to_string(),
+    }
+}
+
+fn cues_label(cues: bool) -> &'static str {
+    if cues { "On" } else { "Off" }
+}
+
+/// 今のゲームの設定から選び始める
+pub(super) fn spawn_mode_select(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    config: Res<MatchConfig>,
+    mut choice: ResMut<ModeChoice>,
+) {
+    choice.0 = *config;
+    let font = &font.0;
+    spawn_screen(
+        &mut commands,
+        Screen::ModeSelect,
+        "New Game",
+        font,
+        |parent| {
+            spawn_row(parent, "Players", font, |parent| {
+                spawn_labeled_button(parent, ModeText, mode_label(config.mode), font)
+                    .observe(on_mode_click);
+            });
+            spawn_row(parent, "Board", font, |parent| {
+                spawn_labeled_button(parent, VariantText, variant_label(config.topology), font)
+                    .observe(on_variant_click);
+            });
+            spawn_row(parent, "Difficulty", font, |parent| {
+                spawn_labeled_button(parent, SpawnerText, &spawner_label(config.spawner), font)
+                    .observe(on_spawner_click);
+            });
+            spawn_button_row(parent, |parent| {
+                spawn_overlay_button(parent, "Back", font).observe(open_screen(Screen::Title));
+                spawn_overlay_button(parent, "Start", font).observe(on_start_click);
+            });
+        },
+    );
+}
+
+/// 1 人プレイと 2 人対戦を切り替える
+fn on_mode_click(
+    _press: On<Press>,
+    mut choice: ResMut<ModeChoice>,
+    mut labels: Query<&mut Text, With<ModeText>>,
+) {
+    choice.0.mode = match choice.0.mode {
+        GameMode::Solo => GameMode::Versus,
+        GameMode::Versus => GameMode::Solo,
+    };
+    for mut text in &mut labels {
+        text.0 = mode_label(choice.0.mode).to_string();
+    }
+}
+
+/// 正方格子 → 3×3 → 六角形盤面の順に切り替える
+fn on_variant_click(
+    _press: On<Press>,
+    mut choice: ResMut<ModeChoice>,
+    mut labels: Query<&mut Text, With<VariantText>>,
+) {
+    choice.0.topology = match choice.0.topology {
+        topology if topology == Topology::CLASSIC => Topology::MINI,
+        Topology::Grid { .. } => Topology::HEX,
+        Topology::Hex { .. } => Topology::CLASSIC,
+    };
+    for mut text in &mut labels {
+        text.0 = variant_label(choice.0.topology).to_string();
+    }
+}
+
+/// 通常 → Evil 1 → Evil 2 → Evil 3 → ホットシートの順に難易度を切り替える
+fn on_spawner_click(
+    _press: On<Press>,
+    mut choice: ResMut<ModeChoice>,
+    mut labels: Query<&mut Text, With<SpawnerText>>,
+) {
+    choice.0.spawner = match choice.0.spawner {
+        Spawner::Random => Spawner::Evil { depth: 1 },
+        Spawner::Evil { depth } if depth < 3 => Spawner::Evil { depth: depth + 1 },
+        Spawner::Evil { .. } => Spawner::Human,
+        Spawner::Human => Spawner::Random,
+    };
+    for mut text in &mut labels {
+        text.0 = spawner_label(choice.0.spawner);
+    }
+}
+
+fn on_start_click(_press: On<Press>, choice: Res<ModeChoice>, mut reset: GameReset) {
+    reset.restart(choice.0);
+}
+
+pub(super) fn spawn_settings(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    theme: Res<Theme>,
+    scheme: Res<ColorScheme>,
+    accessibility: Res<TileAccessibility>,
+    skin: Res<TileSkin>,
+) {
+    let font = &font.0;
+    spawn_screen(
+        &mut commands,
+        Screen::Settings,
+        "Settings",
+        font,
+        |parent| {
+            spawn_row(parent, "Theme", font, |parent| {
+                spawn_labeled_button(parent, ThemeText, &theme.name, font).observe(cycle_theme);
+            });
+            spawn_row(parent, "Colors", font, |parent| {
+                spawn_labeled_button(parent, ColorSchemeText, scheme.label(), font)
+                    .observe(cycle_color_scheme);
+            });
+            spawn_row(parent, "Palette", font, |parent| {
+                spawn_labeled_button(parent, PaletteText, accessibility.palette.label(), font)
+                    .observe(on_palette_click);
+            });
+            spawn_row(parent, "Cues", font, |parent| {
+                spawn_labeled_button(parent, CuesText, cues_label(accessibility.cues), font)
+                    .observe(on_cues_click);
+            });
+            spawn_row(parent, "Tiles", font, |parent| {
+                spawn_labeled_button(parent, SkinText, skin.label(), font).observe(cycle_skin);
+            });
+            spawn_row(parent, "Keys", font, |parent| {
+                spawn_overlay_button(parent, "Edit", font).observe(open_key_config);
+            });
+            spawn_button_row(parent, |parent| {
+                spawn_overlay_button(parent, "Back", font).observe(close_settings);
+            });
+        },
+    );
+}
+
+/// テーマの配色 → 1 型 → 2 型 → 3 型色覚向けの順にタイルの配色を切り替える
+fn on_palette_click(
+    _press: On<Press>,
+    mut accessibility: ResMut<TileAccessibility>,
+    mut labels: Query<&mut Text, With<PaletteText>>,
+) {
+    accessibility.palette = match accessibility.palette {
+        TilePalette::Theme => TilePalette::Protanopia,
+        TilePalette::Protanopia => TilePalette::Deuteranopia,
+        TilePalette::Deuteranopia => TilePalette::Tritanopia,
+        TilePalette::Tritanopia => TilePalette::Theme,
+    };
+    for mut text in &mut labels {
+        text.0 = accessibility.palette.label().to_string();
+    }
+}
+
+/// タイルの目印の表示を切り替える
+fn on_cues_click(
+    _press: On<Press>,
+    mut accessibility: ResMut<TileAccessibility>,
+    mut labels: Query<&mut Text, With<CuesText>>,
+) {
+    accessibility.cues = !accessibility.cues;
+    for mut text in &mut labels {
+        text.0 = cues_label(accessibility.cues).to_string();
+    }
+}
+
+/// テーマや配色の設定、スキンが変わったらボタンの表示を書き換える
+pub(super) fn sync_theme_labels(
+    theme: Res<Theme>,
+    scheme: Res<ColorScheme>,
+    skin: Res<TileSkin>,
+    mut themes: Query<&mut Text, (With<ThemeText>, Without<ColorSchemeText>, Without<SkinText>)>,
+    mut schemes: Query<&mut Text, (With<ColorSchemeText>, Without<SkinText>)>,
+    mut skins: Query<&mut Text, With<SkinText>>,
+) {
+    if theme.is_changed() {
+        for mut text in &mut themes {
+            text.0.clone_from(&theme.name);
+        }
+    }
+    if scheme.is_changed() {
+        for mut text in &mut schemes {
+            text.0 = scheme.label().to_string();
+        }
+    }
+    if skin.is_changed() {
+        for mut text in &mut skins {
+            text.0 = skin.label().to_string();
+        }
+    }
+}
+
+/// 終わったゲームを成績に足す
+pub(super) fn record_game_over(
+    mut stats: ResMut<SessionStats>,
+    fields: Query<(&Board, &Score), With<PlayField>>,
+) {
+    stats.games += 1;
+    stats.record(fields);
+}
+
+/// 2048 に到達したら成績に足す
+pub(super) fn record_win(
+    mut stats: ResMut<SessionStats>,
+    fields: Query<(&Board, &Score), With<PlayField>>,
+) {
+    stats.wins += 1;
+    stats.record(fields);
+}
+
+/// 成績。最高記録には遊んでいる途中のゲームも含める
+pub(super) fn spawn_stats(
+    mut commands: Commands,
+    font: Res<GameFont>,
+    stats: Res<SessionStats>,
+    fields: Query<(&Board, &Score), With<PlayField>>,
+) {
+    let mut stats = stats.clone();
+    stats.record(fields);
+    let best_tile = stats
+        .best_tile
+        .map_or("-".to_string(), |exp| exp_to_value(exp.get()).to_string());
+    let lines = [
+        ("Games", stats.games.to_string()),
+        ("Wins", stats.wins.to_string()),
+        ("Best score", stats.best_score.to_string()),
+        ("Best tile", best_tile),
+    ];
+
+    let font = &font.0;
+    spawn_screen(&mut commands, Screen::Stats, "Stats", font, |parent| {
+        for (caption, value) in lines {
+            spawn_row(parent, caption, font, |parent| {
+                parent.spawn((
+                    Text::new(value),
+                    text_font(font, 24.0),
+                    UiColor::OverlayText,
+                ));
+            });
+        }
+        spawn_button_row(parent, |parent| {
+            spawn_overlay_button(parent, "Back", font).observe(open_screen(Screen::Title));
+        });
+    });
+}
+
+/// 保存した記録の一覧。GIF を押すと今のテーマと設定で書き出し直す
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) fn spawn_replays(mut commands: Commands, font: Res<GameFont>) {
+    use super::export::{GifSettings, export_saved_replay, saved_replays};
+    use super::render::LabelSettings;
+
+    let replays = saved_replays();
+    let font = &font.0;
+    spawn_screen(&mut commands, Screen::Replays, "Replays", font, |parent| {
+        if replays.is_empty() {
+            parent.spawn((
+                Text::new("No saved replays yet. Export a GIF while playing to save one."),
+                text_font(font, 18.0),
+                UiColor::OverlaySubtext,
+            ));
+        }
+        for (name, replay) in replays.into_iter().take(REPLAY_LIST_LIMIT) {
+            let score = replay.final_score().unwrap_or(replay.initial_score);
+            let caption = format!("{name}  ({} moves, {score})", replay.moves.len());
+            spawn_row(parent, &caption, font, |parent| {
+                spawn_overlay_button(parent, "GIF", font).observe(
+                    move |_press: On<Press>,
+                          mut commands: Commands,
+                          settings: Res<GifSettings>,
+                          theme: Res<Theme>,
+                          labels: Res<LabelSettings>,
+                          font: Res<GameFont>,
+                          fonts: Res<Assets<Font>>,
+                          mut status: Query<(Entity, &mut Text), With<ReplayStatus>>| {
+                        let Ok((status, mut text)) = status.single_mut() else {
+                            return;
+                        };
+                        let Some(font) = fonts.get(&font.0) else {
+                            text.0 = "The font is still loading".to_string();
+                            return;
+                        };
+                        text.0 = format!("Exporting {name}.gif...");
+                        commands.spawn(export_saved_replay(
+                            &name, &replay, &settings, &theme, &labels, font, status,
+                        ));
+                    },
+                );
+            });
+        }
+        parent.spawn((
+            ReplayStatus,
+            Text::default(),
+            text_font(font, 16.0),
+            UiColor::OverlaySubtext,
+        ));
+        spawn_button_row(parent, |parent| {
+            spawn_overlay_button(parent, "Back", font).observe(open_screen(Screen::Title));
+        });
+    });
+}
+
+/// Web 版では記録をファイルに残さない
+#[cfg(target_arch = "wasm32")]
+pub(super) fn spawn_replays(mut commands: Commands, font: Res<GameFont>) {
+    let font = &font.0;
+    spawn_screen(&mut commands, Screen::Replays, "Replays", font, |parent| {
+        parent.spawn((
+            Text::new("Replays are saved by the desktop version."),
+            text_font(font, 18.0),
+            UiColor::OverlaySubtext,
+        ));
+        spawn_button_row(parent, |parent| {
+            spawn_overlay_button(parent, "Back", font).observe(open_screen(Screen::Title));
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::state::app::StatesPlugin;
+
+    use super::*;
+    use crate::game::session::Player;
+    use crate::game::state::GamePhase;
+
+    fn set_screen(app: &mut App, screen: Screen) {
+        app.world_mut()
+            .resource_mut::<NextState<Screen>>()
+            .set(screen);
+        app.update();
+    }
+
+    fn continue_offered(app: &mut App) -> bool {
+        app.world_mut()
+            .query::<&Text>()
+            .iter(app.world())
+            .any(|text| text.0 == "Continue")
+    }
+
+    #[test]
+    fn decided_match_cannot_be_continued_from_title() {
+        let mut app = App::new();
+        app.add_plugins(StatesPlugin)
+            .init_state::<Screen>()
+            .add_sub_state::<GamePhase>()
+            .init_resource::<MatchOutcome>()
+            .insert_resource(GameFont(Handle::default()))
+            .add_systems(OnEnter(Screen::Title), spawn_title);
+        app.world_mut()
+            .spawn((PlayField, Board::new(Topology::CLASSIC)));
+        app.update();
+
+        set_screen(&mut app, Screen::Playing);
+        set_screen(&mut app, Screen::Title);
+        assert!(continue_offered(&mut app));
+
+        // 対戦で勝者が決まった後にタイトルへ戻っても、続きからは選べない
+        set_screen(&mut app, Screen::Playing);
+        app.world_mut().resource_mut::<MatchOutcome>().winner = Some(Player(0));
+        app.world_mut()
+            .resource_mut::<NextState<GamePhase>>()
+            .set(GamePhase::Won);
+        app.update();
+        set_screen(&mut app, Screen::Title);
+        assert!(!continue_offered(&mut app));
+    }
+}
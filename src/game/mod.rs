@@ -1,18 +1,48 @@
+mod accessibility;
+mod ai;
 mod animation;
-mod board;
+pub mod board;
+pub mod env;
+mod export;
+mod gamepad;
 mod input;
+mod key_config;
+mod keybindings;
+mod menu;
+pub mod ntuple;
+mod particles;
+#[cfg(feature = "bot")]
+pub mod protocol;
 mod render;
+pub mod replay;
+mod score_effects;
+mod session;
+mod skin;
+pub mod spawner;
 mod state;
+mod swipe;
+pub mod tablebase;
+#[cfg(test)]
+mod testing;
+mod theme;
+pub mod topology;
 mod ui;
 mod update_mode;
 
+use bevy::input_focus::InputDispatchPlugin;
+use bevy::input_focus::directional_navigation::DirectionalNavigationPlugin;
 use bevy::prelude::*;
-use rand::rng;
 
-use animation::{AnimationPhase, PendingSlide};
+pub use accessibility::{TileAccessibility, TilePalette};
+pub use animation::{AnimationSettings, InputBuffer};
 use board::{Board, Score};
-use input::{Slide, handle_input, on_drag_end};
-use state::{GamePhase, HasWon, check_game_state};
+pub use export::{GifSettings, replay_gif};
+use input::{Slide, handle_input};
+pub use particles::ParticleSettings;
+pub use render::{LabelFormat, LabelSettings, text_color, tile_color};
+use session::MatchConfig;
+use state::{GamePhase, MatchOutcome, Screen, check_game_state, toggle_pause};
+pub use theme::ColorScheme;
 use update_mode::{
     capture_idle_update_mode, request_redraw_during_animation, sync_focused_update_mode,
 };
@@ -20,24 +50,66 @@ use update_mode::{
 #[derive(Resource)]
 pub(super) struct GameFont(pub(super) Handle<Font>);
 
-pub(super) struct GamePlugin;
+pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Score>()
-            .register_type::<Score>()
-            .insert_resource(Board::with_two_tiles(&mut rng()))
+        // ボタンのフォーカス移動（ゲームパッド操作）に使う
+        if !app.is_plugin_added::<InputDispatchPlugin>() {
+            app.add_plugins(InputDispatchPlugin);
+        }
+        if !app.is_plugin_added::<DirectionalNavigationPlugin>() {
+            app.add_plugins(DirectionalNavigationPlugin);
+        }
+
+        app.register_type::<Score>()
             .register_type::<Board>()
-            .init_resource::<AnimationPhase>()
-            .init_resource::<PendingSlide>()
-            .init_resource::<HasWon>()
-            .init_state::<GamePhase>()
+            .init_resource::<MatchConfig>()
+            .init_resource::<InputBuffer>()
+            .init_resource::<AnimationSettings>()
+            .init_resource::<ParticleSettings>()
+            .init_resource::<animation::SystemReducedMotion>()
+            .init_resource::<MatchOutcome>()
+            .init_resource::<key_config::KeyCapture>()
+            .init_resource::<swipe::Swipe>()
+            .init_resource::<theme::Theme>()
+            .init_resource::<ColorScheme>()
+            .init_resource::<theme::SystemDarkMode>()
+            .init_resource::<TileAccessibility>()
+            .init_resource::<LabelSettings>()
+            .init_resource::<GifSettings>()
+            .init_resource::<skin::TileSkin>()
+            .init_resource::<menu::SettingsReturn>()
+            .init_resource::<menu::ModeChoice>()
+            .init_resource::<menu::SessionStats>()
+            .init_resource::<gamepad::GamepadOrder>()
+            .init_state::<Screen>()
+            .add_sub_state::<GamePhase>()
+            .init_asset::<ai::NTupleWeights>()
+            .init_asset_loader::<ai::NTupleWeightsLoader>()
+            .init_asset::<ai::TablebaseAsset>()
+            .init_asset_loader::<ai::TablebaseLoader>()
+            .init_asset::<theme::ThemeAsset>()
+            .init_asset_loader::<theme::ThemeLoader>()
+            .init_asset::<skin::SkinAsset>()
+            .init_asset_loader::<skin::SkinLoader>()
             .add_message::<Slide>()
-            .add_observer(on_drag_end)
+            .add_message::<animation::SlideScored>()
+            .add_message::<gamepad::GamepadPush>()
+            .add_observer(swipe::on_swipe_start)
+            .add_observer(swipe::on_swipe_drag)
+            .add_observer(swipe::on_swipe_end)
+            .add_observer(animation::on_cell_click)
+            .add_observer(ui::press_clicked_button)
             .add_systems(
                 Startup,
                 (
                     load_font,
+                    theme::load_themes,
+                    skin::load_skins,
+                    keybindings::load_key_bindings,
+                    ai::load_hint_weights,
+                    animation::query_system_reduced_motion,
                     capture_idle_update_mode,
                     render::setup_board,
                     ui::setup_ui,
@@ -48,13 +120,17 @@ impl Plugin for GamePlugin {
                 Update,
                 (
                     handle_input,
+                    gamepad::slide_with_gamepad,
+                    ai::load_tablebase_for_mini,
+                    ai::show_hint,
                     animation::prepare_slide,
                     animation::animate_slide,
                     animation::resolve_slide,
                     animation::animate_effects,
                     check_game_state,
-                    request_redraw_during_animation,
-                    sync_focused_update_mode,
+                    ai::clear_hint_on_move,
+                    render::highlight_placement_targets,
+                    ui::sync_placement_hint,
                 )
                     .chain()
                     .run_if(in_state(GamePhase::Playing)),
@@ -62,15 +138,105 @@ impl Plugin for GamePlugin {
             .add_systems(
                 Update,
                 (
+                    score_effects::spawn_score_popups,
+                    score_effects::count_up_score,
+                    score_effects::animate_score_popups,
+                    score_effects::animate_score_flourish,
+                    particles::spawn_merge_bursts,
+                    particles::update_particles,
                     ui::sync_ui_score,
+                    export::finish_gif_exports,
+                    request_redraw_during_animation,
+                    sync_focused_update_mode,
+                )
+                    .chain()
+                    .after(animation::animate_effects),
+            )
+            .add_systems(
+                Update,
+                (
+                    (
+                        theme::track_system_dark_mode,
+                        theme::sync_theme,
+                        skin::sync_skin,
+                        menu::sync_theme_labels,
+                    )
+                        .chain(),
                     ui::button_hover,
                     ui::adapt_header_to_window,
+                    animation::receive_system_reduced_motion
+                        .run_if(resource_exists::<animation::ReducedMotionQuery>),
+                    render::fit_tile_labels,
+                    toggle_pause,
+                    menu::back_on_pause_key,
+                    swipe::follow_swipe.before(animation::prepare_slide),
+                    session::new_game_on_key
+                        .run_if(in_state(Screen::Playing).or_else(in_state(Screen::Paused))),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    gamepad::track_gamepad_order,
+                    gamepad::detect_gamepad_pushes,
+                    (
+                        gamepad::navigate_with_gamepad,
+                        gamepad::confirm_with_gamepad,
+                    )
+                        .run_if(not(in_state(GamePhase::Playing))),
+                    gamepad::sync_navigable_buttons,
+                    gamepad::focus_first_button
+                        .run_if(state_changed::<Screen>.or_else(state_changed::<GamePhase>)),
+                    gamepad::highlight_focused_button,
+                )
+                    .chain()
+                    .before(handle_input),
+            )
+            .add_systems(
+                Update,
+                session::undo_move
+                    .before(animation::prepare_slide)
+                    .run_if(in_state(GamePhase::Playing).or_else(in_state(GamePhase::GameOver))),
+            )
+            .add_systems(
+                Update,
+                (key_config::capture_key, key_config::sync_key_list)
+                    .chain()
+                    .run_if(in_state(Screen::KeyConfig)),
+            )
+            // Update で出した UI やタイルも、描画の前にテーマの色で塗る
+            .add_systems(
+                PostUpdate,
+                (
+                    theme::paint_ui,
+                    render::paint_board,
+                    accessibility::sync_tile_cues,
+                    render::label_tiles.before(bevy::text::Text2dUpdateSystems),
+                    skin::skin_tiles,
+                    theme::apply_theme_font.run_if(resource_changed::<GameFont>),
                 ),
             )
-            .add_systems(OnEnter(GamePhase::GameOver), ui::spawn_game_over_overlay)
-            .add_systems(OnEnter(GamePhase::Won), ui::spawn_won_overlay)
-            .add_systems(OnExit(GamePhase::GameOver), ui::despawn_overlay)
-            .add_systems(OnExit(GamePhase::Won), ui::despawn_overlay);
+            // 画面と盤面の状態ごとの UI は `DespawnOnExit` でその状態を抜けると消える
+            .add_systems(OnEnter(Screen::Title), menu::spawn_title)
+            .add_systems(OnEnter(Screen::ModeSelect), menu::spawn_mode_select)
+            .add_systems(OnEnter(Screen::Paused), ui::spawn_paused_overlay)
+            .add_systems(OnEnter(Screen::Settings), menu::spawn_settings)
+            .add_systems(OnEnter(Screen::KeyConfig), key_config::spawn_key_config)
+            .add_systems(OnEnter(Screen::Stats), menu::spawn_stats)
+            .add_systems(OnEnter(Screen::Replays), menu::spawn_replays)
+            .add_systems(OnExit(GamePhase::Playing), animation::clear_slide_queues)
+            .add_systems(
+                OnEnter(GamePhase::GameOver),
+                (ui::spawn_game_over_overlay, menu::record_game_over),
+            )
+            .add_systems(
+                OnEnter(GamePhase::Won),
+                (
+                    ui::spawn_won_overlay,
+                    particles::spawn_confetti,
+                    menu::record_win,
+                ),
+            );
     }
 }
 
@@ -0,0 +1,376 @@
+//! n-tuple ネットワークによる盤面の価値関数と、その TD 学習。
+//! 決まった形（タプル）に並ぶセルの指数を添字にして重みを引き、全タプル・全対称配置の和を
+//! afterstate（スライド直後、新しいタイルが出る前の盤面）の価値とする。
+//! 重みは `train` ツールで自己対戦により学習し、バイナリファイルに保存する。
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+
+use bevy::math::IVec2;
+use rand::prelude::*;
+
+use super::board::{Board, Direction};
+use super::topology::Topology;
+
+const MAGIC: &[u8; 8] = b"2048NTUP";
+const VERSION: u32 = 1;
+/// 1 セルが取りうる指数の種類（0 = 空き）。それ以上の指数は最後の値に丸める
+const CELL_STATES: usize = 16;
+
+/// 4 マスのタプル（横一列 2 種と 2×2 の正方形 3 種）。重みは約 1.3 MB で、ゲームに同梱しやすい
+pub const SMALL_PATTERNS: &[&[(i32, i32)]] = &[
+    &[(0, 0), (1, 0), (2, 0), (3, 0)],
+    &[(0, 1), (1, 1), (2, 1), (3, 1)],
+    &[(0, 0), (1, 0), (0, 1), (1, 1)],
+    &[(1, 0), (2, 0), (1, 1), (2, 1)],
+    &[(1, 1), (2, 1), (1, 2), (2, 2)],
+];
+
+/// 6 マスのタプル 4 種。強いが重みは約 270 MB になる
+pub const LARGE_PATTERNS: &[&[(i32, i32)]] = &[
+    &[(0, 0), (1, 0), (2, 0), (3, 0), (0, 1), (1, 1)],
+    &[(0, 1), (1, 1), (2, 1), (3, 1), (0, 2), (1, 2)],
+    &[(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)],
+    &[(0, 1), (1, 1), (2, 1), (0, 2), (1, 2), (2, 2)],
+];
+
+struct Tuple {
+    cells: Vec<IVec2>,
+    /// 回転・反転した各配置のセルインデックス
+    placements: Vec<Vec<usize>>,
+    weights: Vec<f32>,
+}
+
+impl Tuple {
+    fn new(topology: Topology, cells: Vec<IVec2>) -> Self {
+        let Topology::Grid { width, height } = topology else {
+            unreachable!("n-tuple networks are built for grid boards only");
+        };
+        // 正方形の盤面では転置した 4 配置も同じ意味を持つ
+        let symmetries = if width == height { 8 } else { 4 };
+        let max = IVec2::new(width as i32 - 1, height as i32 - 1);
+        let placements = (0..symmetries)
+            .filter_map(|symmetry| {
+                cells
+                    .iter()
+                    .map(|&cell| topology.index_of(transform(cell, symmetry, max)))
+                    .collect::<Option<Vec<usize>>>()
+            })
+            .collect();
+        let weights = vec![0.0; CELL_STATES.pow(cells.len() as u32)];
+        Self {
+            cells,
+            placements,
+            weights,
+        }
+    }
+
+    fn key(board: &Board, placement: &[usize]) -> usize {
+        placement.iter().fold(0, |key, &index| {
+            let exp = board[index].map_or(0, |exp| usize::from(exp.get()));
+            key * CELL_STATES + exp.min(CELL_STATES - 1)
+        })
+    }
+}
+
+/// 盤面の回転・反転。0..4 は左右・上下の反転、4..8 はそれに転置を加えたもの
+fn transform(cell: IVec2, symmetry: usize, max: IVec2) -> IVec2 {
+    let cell = if symmetry >= 4 {
+        IVec2::new(cell.y, cell.x)
+    } else {
+        cell
+    };
+    let x = if symmetry & 1 != 0 { max.x - cell.x } else { cell.x };
+    let y = if symmetry & 2 != 0 { max.y - cell.y } else { cell.y };
+    IVec2::new(x, y)
+}
+
+/// afterstate の価値を返す n-tuple ネットワーク。正方格子の盤面だけを扱う
+pub struct NTupleNetwork {
+    topology: Topology,
+    tuples: Vec<Tuple>,
+}
+
+impl NTupleNetwork {
+    /// 重みがすべて 0 のネットワークを作る。`patterns` は左下を原点とした (x, y) のリスト
+    pub fn new(width: usize, height: usize, patterns: &[&[(i32, i32)]]) -> Self {
+        let topology = Topology::Grid { width, height };
+        let tuples = patterns
+            .iter()
+            .map(|pattern| {
+                let cells = pattern.iter().map(|&(x, y)| IVec2::new(x, y)).collect();
+                Tuple::new(topology, cells)
+            })
+            .collect();
+        Self { topology, tuples }
+    }
+
+    pub fn supports(&self, topology: Topology) -> bool {
+        self.topology == topology
+    }
+
+    /// 1 つの盤面で参照する重みの数（学習率の正規化に使う）
+    pub fn feature_count(&self) -> usize {
+        self.tuples.iter().map(|tuple| tuple.placements.len()).sum()
+    }
+
+    pub fn value(&self, board: &Board) -> f32 {
+        self.tuples
+            .iter()
+            .map(|tuple| {
+                tuple
+                    .placements
+                    .iter()
+                    .map(|placement| tuple.weights[Tuple::key(board, placement)])
+                    .sum::<f32>()
+            })
+            .sum()
+    }
+
+    /// 盤面が参照するすべての重みに `delta` を足す
+    pub fn update(&mut self, board: &Board, delta: f32) {
+        for tuple in &mut self.tuples {
+            for placement in &tuple.placements {
+                tuple.weights[Tuple::key(board, placement)] += delta;
+            }
+        }
+    }
+
+    /// 得点 + afterstate の価値が最大になる手。動かせなければ `None`
+    pub fn best_move(&self, board: &Board) -> Option<(Direction, f32)> {
+        board
+            .topology()
+            .directions()
+            .iter()
+            .filter_map(|&direction| {
+                let result = board.compute_slide(direction);
+                result.changed.then(|| {
+                    let value = result.score_gained as f32 + self.value(&result.new_board);
+                    (direction, value)
+                })
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let Topology::Grid { width, height } = self.topology else {
+            unreachable!("n-tuple networks are built for grid boards only");
+        };
+        writer.write_all(MAGIC)?;
+        for value in [
+            VERSION,
+            width as u32,
+            height as u32,
+            self.tuples.len() as u32,
+        ] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        for tuple in &self.tuples {
+            writer.write_all(&(tuple.cells.len() as u32).to_le_bytes())?;
+            for cell in &tuple.cells {
+                writer.write_all(&cell.x.to_le_bytes())?;
+                writer.write_all(&cell.y.to_le_bytes())?;
+            }
+            for weight in &tuple.weights {
+                writer.write_all(&weight.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        fn invalid(message: &str) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+        }
+        fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+            let mut bytes = [0; 4];
+            reader.read_exact(&mut bytes)?;
+            Ok(u32::from_le_bytes(bytes))
+        }
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not an n-tuple weights file"));
+        }
+        if read_u32(reader)? != VERSION {
+            return Err(invalid("unsupported n-tuple weights version"));
+        }
+        let width = read_u32(reader)? as usize;
+        let height = read_u32(reader)? as usize;
+        if width == 0 || height == 0 || width * height > 64 {
+            return Err(invalid("invalid board size"));
+        }
+        let topology = Topology::Grid { width, height };
+
+        let tuple_count = read_u32(reader)?;
+        let mut tuples = Vec::new();
+        for _ in 0..tuple_count {
+            let len = read_u32(reader)? as usize;
+            if len == 0 || len > 6 {
+                return Err(invalid("invalid tuple length"));
+            }
+            let cells = (0..len)
+                .map(|_| {
+                    let x = read_u32(reader)? as i32;
+                    let y = read_u32(reader)? as i32;
+                    Ok(IVec2::new(x, y))
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+            let mut tuple = Tuple::new(topology, cells);
+            let mut bytes = vec![0; tuple.weights.len() * 4];
+            reader.read_exact(&mut bytes)?;
+            for (weight, chunk) in tuple.weights.iter_mut().zip(bytes.chunks_exact(4)) {
+                *weight = f32::from_le_bytes(chunk.try_into().expect("chunk is 4 bytes"));
+            }
+            tuples.push(tuple);
+        }
+        Ok(Self { topology, tuples })
+    }
+}
+
+/// 1 ゲーム分の結果
+#[derive(Clone, Copy, Default, Debug)]
+pub struct EpisodeStats {
+    pub score: u32,
+    pub moves: u32,
+    pub max_exp: u8,
+}
+
+/// afterstate に対する TD(λ) 学習。
+/// λ > 0 のときは直近 `horizon` 個の afterstate に λ^k で減衰させた誤差を配る（打ち切り付きの後ろ向き更新）。
+pub struct TdLearner {
+    /// 学習率（特徴数で割ってから各重みに適用する）
+    pub alpha: f32,
+    pub lambda: f32,
+    pub horizon: usize,
+}
+
+impl TdLearner {
+    /// 貪欲に 1 ゲーム自己対戦し、`learn` が真なら重みを更新する
+    pub fn play<R: Rng + ?Sized>(
+        &self,
+        network: &mut NTupleNetwork,
+        rng: &mut R,
+        learn: bool,
+    ) -> EpisodeStats {
+        let rate = self.alpha / network.feature_count().max(1) as f32;
+        let horizon = if self.lambda > 0.0 {
+            self.horizon.max(1)
+        } else {
+            1
+        };
+        let mut history: VecDeque<Board> = VecDeque::with_capacity(horizon);
+        let mut stats = EpisodeStats::default();
+        let mut board = Board::with_two_tiles(network.topology, rng);
+
+        while let Some((direction, value)) = network.best_move(&board) {
+            let result = board.compute_slide(direction);
+            if learn && let Some(previous) = history.front() {
+                let error = value - network.value(previous);
+                self.propagate(network, &history, rate * error);
+            }
+            stats.score += result.score_gained;
+            stats.moves += 1;
+
+            if history.len() == horizon {
+                history.pop_back();
+            }
+            history.push_front(result.new_board.clone());
+            board = result.new_board;
+            board.place_random_tile(rng);
+        }
+
+        // 終局後の afterstate の価値は 0
+        if learn && let Some(previous) = history.front() {
+            let error = -network.value(previous);
+            self.propagate(network, &history, rate * error);
+        }
+        stats.max_exp = board
+            .iter()
+            .flatten()
+            .map(|exp| exp.get())
+            .max()
+            .unwrap_or(0);
+        stats
+    }
+
+    fn propagate(&self, network: &mut NTupleNetwork, history: &VecDeque<Board>, delta: f32) {
+        let mut scale = 1.0;
+        for board in history {
+            network.update(board, delta * scale);
+            scale *= self.lambda;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::board::non_zero_exp;
+
+    #[test]
+    fn symmetric_boards_have_equal_value() {
+        let mut network = NTupleNetwork::new(4, 4, SMALL_PATTERNS);
+        let mut board = Board::new(Topology::CLASSIC);
+        board[0] = Some(non_zero_exp(3));
+        board[1] = Some(non_zero_exp(1));
+        network.update(&board, 1.0);
+
+        // 左右反転した盤面も同じ重みを参照する
+        let mut mirrored = Board::new(Topology::CLASSIC);
+        mirrored[3] = Some(non_zero_exp(3));
+        mirrored[2] = Some(non_zero_exp(1));
+
+        assert!(network.value(&board) > 0.0);
+        assert_eq!(network.value(&board), network.value(&mirrored));
+    }
+
+    #[test]
+    fn weights_round_trip_through_binary_file() {
+        let mut network = NTupleNetwork::new(4, 4, SMALL_PATTERNS);
+        let board = Board::with_two_tiles(Topology::CLASSIC, &mut StdRng::seed_from_u64(3));
+        network.update(&board, 0.5);
+
+        let mut bytes = Vec::new();
+        network.write_to(&mut bytes).unwrap();
+        let loaded = NTupleNetwork::read_from(&mut bytes.as_slice()).unwrap();
+
+        assert!(loaded.supports(Topology::CLASSIC));
+        assert_eq!(loaded.value(&board), network.value(&board));
+        assert!(NTupleNetwork::read_from(&mut &bytes[..20]).is_err());
+        assert!(NTupleNetwork::read_from(&mut &b"not weights at all"[..]).is_err());
+    }
+
+    #[test]
+    fn td_learning_improves_greedy_play() {
+        let topology = Topology::Grid {
+            width: 3,
+            height: 3,
+        };
+        let patterns: &[&[(i32, i32)]] = &[&[(0, 0), (1, 0), (2, 0)], &[(0, 1), (1, 1), (2, 1)]];
+        let mut network = NTupleNetwork::new(3, 3, patterns);
+        let learner = TdLearner {
+            alpha: 0.1,
+            lambda: 0.5,
+            horizon: 3,
+        };
+        let average = |network: &mut NTupleNetwork, seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let total: u32 = (0..100)
+                .map(|_| learner.play(network, &mut rng, false).score)
+                .sum();
+            total / 100
+        };
+
+        let before = average(&mut network, 1);
+        let mut rng = StdRng::seed_from_u64(2);
+        for _ in 0..300 {
+            learner.play(&mut network, &mut rng, true);
+        }
+        let after = average(&mut network, 1);
+
+        assert!(network.supports(topology));
+        assert!(after > before, "average score {before} -> {after}");
+    }
+}
@@ -0,0 +1,204 @@
+//! 小さなスプライトを CPU で動かすだけの軽いパーティクル。
+//! 大きな値のマージではタイルの色の粒が弾け、2048 に到達すると紙吹雪が降る。
+//! 動きを減らす設定では出さない。
+
+use bevy::prelude::*;
+use rand::prelude::*;
+use rand::rng;
+
+use super::animation::{Motion, SlideScored};
+use super::board::{Board, exp_to_value, non_zero_exp};
+use super::render::board_index_to_position;
+use super::theme::Theme;
+use super::update_mode::LiveEffect;
+
+const GRAVITY: f32 = 900.0;
+/// 1 秒あたりに失う速さの割合（空気抵抗）
+const BURST_DRAG: f32 = 2.0;
+const BURST_COUNT: usize = 18;
+const BURST_SPEED: std::ops::Range<f32> = 250.0..520.0;
+const BURST_LIFETIME: std::ops::Range<f32> = 0.4..0.8;
+const BURST_SIZE: std::ops::Range<f32> = 6.0..12.0;
+/// タイル（z = 2）より手前、スコアのポップアップより奥
+const BURST_Z: f32 = 4.0;
+const CONFETTI_COUNT: usize = 150;
+const CONFETTI_LIFETIME: std::ops::Range<f32> = 2.5..4.0;
+const CONFETTI_SIZE: Vec2 = Vec2::new(8.0, 14.0);
+/// 紙吹雪はひらひらとゆっくり落ちる
+const CONFETTI_GRAVITY_SCALE: f32 = 0.3;
+const CONFETTI_DRAG: f32 = 0.8;
+const CONFETTI_Z: f32 = 20.0;
+/// 消えはじめるまでの寿命の割合
+const FADE_START: f32 = 0.7;
+
+/// パーティクルの設定。`GamePlugin` を追加する前にリソースとして挿入すれば既定値を変えられる
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ParticleSettings {
+    /// この値以上のタイルができたマージで粒を飛ばす
+    pub burst_min_value: u32,
+    /// 2048 に到達したときに紙吹雪を降らせる
+    pub confetti: bool,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        Self {
+            burst_min_value: 128,
+            confetti: true,
+        }
+    }
+}
+
+#[derive(Component)]
+#[require(LiveEffect)]
+pub(super) struct Particle {
+    velocity: Vec2,
+    /// 回転の速さ（ラジアン / 秒）
+    spin: f32,
+    gravity_scale: f32,
+    drag: f32,
+    life: Timer,
+}
+
+fn spawn_particle(
+    commands: &mut Commands,
+    rng: &mut impl Rng,
+    translation: Vec3,
+    size: Vec2,
+    color: Color,
+    particle: Particle,
+) -> Entity {
+    commands
+        .spawn((
+            particle,
+            Sprite {
+                color,
+                custom_size: Some(size),
+                ..default()
+            },
+            Transform::from_translation(translation).with_rotation(Quat::from_rotation_z(
+                rng.random_range(0.0..std::f32::consts::TAU),
+            )),
+        ))
+        .id()
+}
+
+/// 大きな値のマージ先から、タイルの色の粒を飛ばす
+pub(super) fn spawn_merge_bursts(
+    mut commands: Commands,
+    mut scored: MessageReader<SlideScored>,
+    settings: Res<ParticleSettings>,
+    motion: Motion,
+    theme: Res<Theme>,
+    fields: Query<&Board>,
+) {
+    let mut rng = rng();
+    for SlideScored { field, merges, .. } in scored.read() {
+        let Ok(board) = fields.get(*field) else {
+            continue;
+        };
+        if motion.reduced() {
+            continue;
+        }
+
+        for &(index, exp) in merges {
+            if exp_to_value(exp.get()) < settings.burst_min_value {
+                continue;
+            }
+            let origin = board_index_to_position(board.topology(), index).extend(BURST_Z);
+            let color = theme.tile(exp);
+            for _ in 0..BURST_COUNT {
+                let angle = rng.random_range(0.0..std::f32::consts::TAU);
+                let particle = Particle {
+                    velocity: Vec2::from_angle(angle) * rng.random_range(BURST_SPEED),
+                    spin: rng.random_range(-10.0..10.0),
+                    gravity_scale: 1.0,
+                    drag: BURST_DRAG,
+                    life: Timer::from_seconds(rng.random_range(BURST_LIFETIME), TimerMode::Once),
+                };
+                let size = Vec2::splat(rng.random_range(BURST_SIZE));
+                let entity = spawn_particle(&mut commands, &mut rng, origin, size, color, particle);
+                commands.entity(entity).insert(ChildOf(*field));
+            }
+        }
+    }
+}
+
+/// 画面の上端から紙吹雪を降らせる
+pub(super) fn spawn_confetti(
+    mut commands: Commands,
+    settings: Res<ParticleSettings>,
+    motion: Motion,
+    theme: Res<Theme>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+) {
+    if !settings.confetti || motion.reduced() {
+        return;
+    }
+    let Some((top_left, top_right)) = cameras.iter().find_map(|(camera, transform)| {
+        let size = camera.logical_viewport_size()?;
+        let left = camera.viewport_to_world_2d(transform, Vec2::ZERO).ok()?;
+        let right = camera
+            .viewport_to_world_2d(transform, Vec2::new(size.x, 0.0))
+            .ok()?;
+        Some((left, right))
+    }) else {
+        return;
+    };
+
+    let mut rng = rng();
+    for _ in 0..CONFETTI_COUNT {
+        let x = rng.random_range(top_left.x..=top_right.x);
+        // 一度に現れないように、画面の少し上から高さをばらして落とす
+        let y = top_left.y + rng.random_range(0.0..300.0);
+        let color = theme.tile(non_zero_exp(rng.random_range(3..=11)));
+        let particle = Particle {
+            velocity: Vec2::new(rng.random_range(-80.0..80.0), rng.random_range(-60.0..0.0)),
+            spin: rng.random_range(-6.0..6.0),
+            gravity_scale: CONFETTI_GRAVITY_SCALE,
+            drag: CONFETTI_DRAG,
+            life: Timer::from_seconds(rng.random_range(CONFETTI_LIFETIME), TimerMode::Once),
+        };
+        spawn_particle(
+            &mut commands,
+            &mut rng,
+            Vec3::new(x, y, CONFETTI_Z),
+            CONFETTI_SIZE,
+            color,
+            particle,
+        );
+    }
+}
+
+/// 粒を動かし、寿命の終わりに向けて薄くして消す
+pub(super) fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    motion: Motion,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    let delta = motion.delta(&time);
+    let dt = delta.as_secs_f32();
+    for (entity, mut particle, mut transform, mut sprite) in &mut particles {
+        particle.life.tick(delta);
+        if particle.life.is_finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let gravity = GRAVITY * particle.gravity_scale;
+        particle.velocity.y -= gravity * dt;
+        let drag = (particle.drag * dt).min(1.0);
+        particle.velocity *= 1.0 - drag;
+        transform.translation += (particle.velocity * dt).extend(0.0);
+        transform.rotate_z(particle.spin * dt);
+
+        let t = particle.life.fraction();
+        let alpha = if t < FADE_START {
+            1.0
+        } else {
+            1.0 - (t - FADE_START) / (1.0 - FADE_START)
+        };
+        sprite.color.set_alpha(alpha);
+    }
+}
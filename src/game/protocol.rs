@@ -0,0 +1,296 @@
+//! 外部エージェント向けの JSON Lines プロトコル。
+//! 毎手番、盤面・スコア・合法手・直前の出現タイルを 1 行の JSON で送り、
+//! エージェントは方向を 1 行で返す（`{"move":"up"}` または `up`）。
+//! 局面には通し番号 `id` が付き、返答に `{"move":"up","id":3}` のように添えると、
+//! 時間切れの後に届いた前の局面への返答を取り違えずに捨てられる。
+
+use std::time::Duration;
+
+use rand::prelude::*;
+use rand::rngs::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use super::board::{Board, Direction, WIN_EXP, exp_to_value};
+use super::topology::Topology;
+
+const DIRECTION_NAMES: [(Direction, &str); 8] = [
+    (Direction::Up, "up"),
+    (Direction::Down, "down"),
+    (Direction::Left, "left"),
+    (Direction::Right, "right"),
+    (Direction::UpLeft, "up_left"),
+    (Direction::UpRight, "up_right"),
+    (Direction::DownLeft, "down_left"),
+    (Direction::DownRight, "down_right"),
+];
+
+pub fn direction_name(direction: Direction) -> &'static str {
+    DIRECTION_NAMES
+        .iter()
+        .find(|(candidate, _)| *candidate == direction)
+        .map(|(_, name)| *name)
+        .expect("every direction has a name")
+}
+
+/// エージェントの返答
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Reply {
+    pub direction: Direction,
+    /// 答えた局面の `id`。方向だけの返答では `None`
+    pub id: Option<u64>,
+}
+
+/// エージェントの返答 1 行を読む
+pub fn parse_reply(line: &str) -> Result<Reply, String> {
+    #[derive(Deserialize)]
+    struct JsonReply {
+        #[serde(rename = "move")]
+        direction: String,
+        id: Option<u64>,
+    }
+
+    let line = line.trim();
+    let (name, id) = if line.starts_with('{') {
+        let reply = serde_json::from_str::<JsonReply>(line)
+            .map_err(|error| format!("invalid reply: {error}"))?;
+        (reply.direction, reply.id)
+    } else {
+        (line.trim_matches('"').to_string(), None)
+    };
+    DIRECTION_NAMES
+        .iter()
+        .find(|(_, candidate)| candidate.eq_ignore_ascii_case(&name))
+        .map(|(direction, _)| Reply {
+            direction: *direction,
+            id,
+        })
+        .ok_or_else(|| format!("unknown direction: {name:?}"))
+}
+
+/// 出現したタイルの位置。`row` は上から、`col` は行内の左から数える
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Spawn {
+    pub row: usize,
+    pub col: usize,
+    pub value: u32,
+}
+
+/// ゲームが終わった理由
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum EndReason {
+    /// 動かせる方向がなくなった
+    GameOver,
+    /// 制限時間内に返答がなかった
+    Timeout,
+    /// 盤面が変化しない方向を選んだ
+    IllegalMove,
+    /// 返答を方向として解釈できなかった
+    InvalidInput,
+    /// エージェントの出力が閉じられた
+    Disconnected,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    State {
+        /// 局面の通し番号。ゲームをまたいで増えていく
+        id: u64,
+        game: usize,
+        turn: u32,
+        /// 各セルの値（空きは 0）。行は上から下、行内は左から右
+        board: Vec<Vec<u32>>,
+        score: u32,
+        legal_moves: Vec<&'static str>,
+        last_spawn: Option<Spawn>,
+    },
+    Error {
+        game: usize,
+        message: String,
+    },
+    Summary {
+        game: usize,
+        seed: u64,
+        score: u32,
+        moves: u32,
+        max_tile: u32,
+        won: bool,
+        reason: EndReason,
+        elapsed_ms: u128,
+    },
+}
+
+impl ServerMessage {
+    pub fn to_line(&self) -> String {
+        serde_json::to_string(self).expect("server messages are always serializable")
+    }
+}
+
+/// ウィンドウを持たない 1 ゲーム分の進行。乱数はシードから決まるので、
+/// 同じ手順なら同じビルドでは同じ盤面になる
+pub struct HeadlessGame {
+    game: usize,
+    seed: u64,
+    board: Board,
+    score: u32,
+    moves: u32,
+    last_spawn: Option<usize>,
+    rng: ChaCha8Rng,
+}
+
+impl HeadlessGame {
+    pub fn new(game: usize, topology: Topology, seed: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        Self {
+            game,
+            seed,
+            board: Board::with_two_tiles(topology, &mut rng),
+            score: 0,
+            moves: 0,
+            last_spawn: None,
+            rng,
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<Direction> {
+        self.board
+            .topology()
+            .directions()
+            .iter()
+            .copied()
+            .filter(|&direction| self.board.compute_slide(direction).changed)
+            .collect()
+    }
+
+    pub fn is_over(&self) -> bool {
+        !self.board.can_move()
+    }
+
+    /// 1 手進めて新しいタイルを置く。盤面が変化しない手は `Err` で、状態は変わらない
+    pub fn play(&mut self, direction: Direction) -> Result<(), String> {
+        let result = self.board.compute_slide(direction);
+        if !result.changed {
+            return Err(format!("illegal move: {}", direction_name(direction)));
+        }
+        self.board = result.new_board;
+        self.score += result.score_gained;
+        self.moves += 1;
+        self.last_spawn = self.board.place_random_tile(&mut self.rng);
+        Ok(())
+    }
+
+    /// `id` を付けた今の局面
+    pub fn state(&self, id: u64) -> ServerMessage {
+        let topology = self.board.topology();
+        let rows: Vec<Vec<usize>> = topology.rows().into_iter().rev().collect();
+        let last_spawn = self.last_spawn.and_then(|spawned| {
+            let exp = self.board[spawned]?;
+            rows.iter().enumerate().find_map(|(row, cells)| {
+                let col = cells.iter().position(|&index| index == spawned)?;
+                Some(Spawn {
+                    row,
+                    col,
+                    value: exp_to_value(exp.get()),
+                })
+            })
+        });
+
+        ServerMessage::State {
+            id,
+            game: self.game,
+            turn: self.moves,
+            board: rows
+                .iter()
+                .map(|cells| {
+                    cells
+                        .iter()
+                        .map(|&index| self.board[index].map_or(0, |exp| exp_to_value(exp.get())))
+                        .collect()
+                })
+                .collect(),
+            score: self.score,
+            legal_moves: self.legal_moves().into_iter().map(direction_name).collect(),
+            last_spawn,
+        }
+    }
+
+    pub fn error(&self, message: String) -> ServerMessage {
+        ServerMessage::Error {
+            game: self.game,
+            message,
+        }
+    }
+
+    pub fn summary(&self, reason: EndReason, elapsed: Duration) -> ServerMessage {
+        let max_exp = self.board.iter().flatten().map(|exp| exp.get()).max();
+        ServerMessage::Summary {
+            game: self.game,
+            seed: self.seed,
+            score: self.score,
+            moves: self.moves,
+            max_tile: max_exp.map_or(0, exp_to_value),
+            won: max_exp.is_some_and(|exp| exp >= WIN_EXP),
+            reason,
+            elapsed_ms: elapsed.as_millis(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reply_accepts_json_and_bare_words() {
+        let direction = |line| parse_reply(line).map(|reply| reply.direction);
+        assert_eq!(direction(r#"{"move":"left"}"#), Ok(Direction::Left));
+        assert_eq!(direction("  UP \n"), Ok(Direction::Up));
+        assert_eq!(direction("\"down_right\""), Ok(Direction::DownRight));
+        assert!(direction("sideways").is_err());
+        assert!(direction(r#"{"dir":"up"}"#).is_err());
+
+        assert_eq!(
+            parse_reply(r#"{"move":"up","id":3}"#),
+            Ok(Reply {
+                direction: Direction::Up,
+                id: Some(3)
+            })
+        );
+        assert_eq!(parse_reply("up").map(|reply| reply.id), Ok(None));
+    }
+
+    #[test]
+    fn same_seed_and_moves_give_same_game() {
+        let play = || {
+            let mut game = HeadlessGame::new(0, Topology::CLASSIC, 7);
+            for _ in 0..20 {
+                let Some(&direction) = game.legal_moves().first() else {
+                    break;
+                };
+                game.play(direction).expect("legal move must be accepted");
+            }
+            game.state(0).to_line()
+        };
+
+        assert_eq!(play(), play());
+    }
+
+    #[test]
+    fn illegal_move_is_rejected_without_changing_state() {
+        let mut game = HeadlessGame::new(0, Topology::CLASSIC, 1);
+        let illegal = Topology::CLASSIC
+            .directions()
+            .iter()
+            .copied()
+            .find(|direction| !game.legal_moves().contains(direction));
+        let before = game.state(0).to_line();
+
+        if let Some(direction) = illegal {
+            assert!(game.play(direction).is_err());
+            assert_eq!(game.state(0).to_line(), before);
+        }
+        assert!(game.play(Direction::UpLeft).is_err());
+        assert_eq!(game.state(0).to_line(), before);
+    }
+}
@@ -1,53 +1,302 @@
 use std::num::NonZero;
 
+use bevy::asset::RenderAssetUsages;
 use bevy::camera::ScalingMode;
 use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::text::TextLayoutInfo;
 
 use super::GameFont;
-use super::board::{BOARD_SIZE, Board, exp_to_value};
+use super::animation::AnimationPhase;
+use super::board::{Board, Direction, exp_to_value};
+use super::session::MatchConfig;
+use super::skin::{TileSkin, tile_image};
+use super::theme::Theme;
+use super::topology::Topology;
 
 pub(super) const TILE_SIZE: f32 = 100.0;
 pub(super) const TILE_GAP: f32 = 10.0;
 const BOARD_PADDING: f32 = 10.0;
-pub(super) const BOARD_PX: f32 =
-    TILE_SIZE * BOARD_SIZE as f32 + TILE_GAP * (BOARD_SIZE as f32 + 1.0);
 pub(super) const BOARD_OFFSET_Y: f32 = -30.0;
+/// 対戦モードで並べる盤面どうしの間隔
+const BOARD_SPACING: f32 = 40.0;
 const MARGIN: f32 = 40.0;
 const HEADER_HEIGHT: f32 = 80.0;
 
+/// フラットトップ六角形の高さ / 幅
+const HEX_ASPECT: f32 = 0.866_025_4;
+/// 六角形マスク画像の横幅（ピクセル）
+const HEX_IMAGE_WIDTH: u32 = 128;
+
 /// Text2d を高解像度でラスタライズするためのスケール倍率。
 /// font_size にこの値を掛け、Transform を 1/この値 に縮小することで、
 /// カメラ拡大時でもテキストがクリアに表示される。
-const TEXT_RENDER_SCALE: f32 = 3.0;
+pub(super) const TEXT_RENDER_SCALE: f32 = 3.0;
 
-const COLOR_BG: Color = Color::srgb(0.98, 0.97, 0.94);
-const COLOR_BOARD: Color = Color::srgb(0.733, 0.678, 0.627);
 const COLOR_EMPTY_CELL: Color = Color::srgb(0.804, 0.757, 0.706);
 const COLOR_TEXT_DARK: Color = Color::srgb(0.467, 0.431, 0.396);
+/// 2048 より大きなタイルの色の起点（4096）
+pub(super) const COLOR_BEYOND: Color = Color::srgb(0.239, 0.227, 0.196);
+/// 2048 より大きなタイルの明るさ（Oklab の L）の範囲。明るい文字が読める暗さに収める
+const BEYOND_LIGHTNESS: std::ops::RangeInclusive<f32> = 0.28..=0.52;
+/// 2048 より大きなタイルの最低限の彩度。灰色に近い起点でも色相の違いが出るようにする
+const BEYOND_MIN_CHROMA: f32 = 0.09;
+/// 値が 1 段上がるごとに回す色相（黄金角）。何段重ねても近い色が続かない
+const BEYOND_HUE_STEP: f32 = 137.508;
+
+/// タイルの横幅のうち数字に使える割合。六角形は角が斜めなので狭くとる
+const LABEL_FILL_GRID: f32 = 0.84;
+const LABEL_FILL_HEX: f32 = 0.7;
+/// 絵のタイルの隅に添える数字（小札）の大きさと、タイルの大きさに対する位置。右下の角に寄せる
+const BADGE_FONT_SIZE: f32 = 14.0;
+const BADGE_OFFSET: Vec2 = Vec2::new(0.32, -0.34);
 
+/// タイルの数字の書き方
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LabelFormat {
+    /// すべての桁を書く（はみ出すときは縮める）
+    #[default]
+    Full,
+    /// 16K・1M のように K / M を付けて縮める
+    Suffix,
+    /// 2^14 のように指数で書く
+    Exponent,
+}
+
+/// タイルの数字の設定。`GamePlugin` を追加する前にリソースとして挿入すれば既定値を変えられる
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct LabelSettings {
+    pub format: LabelFormat,
+    /// この値以上のタイルだけを `format` で書き、それより小さいタイルはすべての桁を書く
+    pub compact_from: u32,
+}
+
+impl Default for LabelSettings {
+    fn default() -> Self {
+        Self {
+            format: LabelFormat::Full,
+            compact_from: 10_000,
+        }
+    }
+}
+
+#[derive(Component)]
+pub(super) struct BoardBackground;
+
+/// 盤面のセル背景。ホットシートではクリックしてタイルを置く対象になる
 #[derive(Component)]
-struct CellBackground;
+pub(super) struct CellBackground {
+    pub(super) board_index: usize,
+}
 
 #[derive(Component)]
 pub(super) struct VisualTile {
     pub(super) board_index: usize,
+    pub(super) exp: NonZero<u8>,
 }
 
 #[derive(Component)]
 pub(super) struct TileText;
 
-/// ボードインデックスからワールド座標を計算する
-pub(super) fn board_index_to_position(index: usize) -> Vec2 {
-    let x = index % BOARD_SIZE;
-    let y = index / BOARD_SIZE;
-    let offset = -BOARD_PX / 2.0 + BOARD_PADDING + TILE_SIZE / 2.0;
-    Vec2::new(
-        offset + x as f32 * (TILE_SIZE + TILE_GAP),
-        offset + y as f32 * (TILE_SIZE + TILE_GAP) + BOARD_OFFSET_Y,
+/// 六角形セル用の白いマスク画像。Sprite の color で塗り色を乗算する
+#[derive(Resource)]
+pub(super) struct HexagonImage(Handle<Image>);
+
+/// 横幅 `tile` のセルのスプライトサイズ
+fn cell_size(topology: Topology, tile: f32) -> Vec2 {
+    match topology {
+        Topology::Grid { .. } => Vec2::splat(tile),
+        Topology::Hex { .. } => Vec2::new(tile, tile * HEX_ASPECT),
+    }
+}
+
+/// テーマのタイルと隙間の比を、固定のセル間隔に当てはめたときの隙間
+fn themed_gap(theme: &Theme) -> f32 {
+    (TILE_SIZE + TILE_GAP) * theme.tile_gap / (theme.tile_size + theme.tile_gap)
+}
+
+/// テーマに合わせたセルのスプライトサイズ。セルの中心の位置は変えずに、隙間の分だけ縮める
+pub(super) fn themed_cell_size(topology: Topology, theme: &Theme) -> Vec2 {
+    cell_size(topology, TILE_SIZE + TILE_GAP - themed_gap(theme))
+}
+
+/// テーマに合わせたボード背景の大きさ。外周の余白もテーマの隙間にそろえる
+pub(super) fn themed_board_extent(topology: Topology, theme: &Theme) -> Vec2 {
+    board_extent(topology) + Vec2::splat(themed_gap(theme) - TILE_GAP)
+}
+
+/// 大きさ `size` のセルの中心からのずれ `offset` が、セルの形の内側にあるか
+pub(super) fn cell_contains(topology: Topology, size: Vec2, offset: Vec2) -> bool {
+    let half = size / 2.0;
+    match topology {
+        Topology::Grid { .. } => offset.abs().cmple(half).all(),
+        // フラットトップの六角形
+        Topology::Hex { .. } => {
+            offset.y.abs() <= half.y && HEX_ASPECT * offset.x.abs() + offset.y.abs() / 2.0 <= half.y
+        }
+    }
+}
+
+/// タイルの横幅のうち数字に使える割合
+pub(super) fn label_fill(topology: Topology) -> f32 {
+    match topology {
+        Topology::Grid { .. } => LABEL_FILL_GRID,
+        Topology::Hex { .. } => LABEL_FILL_HEX,
+    }
+}
+
+/// 隣接する六角形セルの外接円半径（隙間込み）
+fn hex_spacing() -> f32 {
+    (TILE_SIZE + TILE_GAP) / 2.0
+}
+
+/// ボード背景の大きさ
+fn board_extent(topology: Topology) -> Vec2 {
+    match topology {
+        Topology::Grid { width, height } => Vec2::new(
+            TILE_SIZE * width as f32 + TILE_GAP * (width as f32 + 1.0),
+            TILE_SIZE * height as f32 + TILE_GAP * (height as f32 + 1.0),
+        ),
+        Topology::Hex { radius } => {
+            let span = 2.0 * radius as f32 * hex_spacing();
+            cell_size(topology, TILE_SIZE)
+                + Vec2::new(1.5 * span, 2.0 * HEX_ASPECT * span)
+                + Vec2::splat(BOARD_PADDING * 2.0)
+        }
+    }
+}
+
+/// ボードインデックスから、盤面中心を原点とするローカル座標を計算する
+pub(super) fn board_index_to_position(topology: Topology, index: usize) -> Vec2 {
+    let coords = topology.coords(index).as_vec2();
+    match topology {
+        Topology::Grid { .. } => {
+            let offset = -board_extent(topology) / 2.0 + BOARD_PADDING + TILE_SIZE / 2.0;
+            offset + coords * (TILE_SIZE + TILE_GAP)
+        }
+        Topology::Hex { .. } => {
+            let spacing = hex_spacing();
+            Vec2::new(
+                1.5 * spacing * coords.x,
+                2.0 * HEX_ASPECT * spacing * (coords.y + coords.x / 2.0),
+            )
+        }
+    }
+}
+
+/// 隣り合うセルの中心どうしの距離
+pub(super) fn cell_step(topology: Topology) -> f32 {
+    match topology {
+        Topology::Grid { .. } => TILE_SIZE + TILE_GAP,
+        Topology::Hex { .. } => 3.0_f32.sqrt() * hex_spacing(),
+    }
+}
+
+/// `count` 枚並べたうちの `index` 番目の盤面の中心位置
+pub(super) fn play_field_offset(topology: Topology, index: usize, count: usize) -> Vec3 {
+    let stride = board_extent(topology).x + BOARD_SPACING;
+    let x = (index as f32 - (count as f32 - 1.0) / 2.0) * stride;
+    Vec3::new(x, BOARD_OFFSET_Y, 0.0)
+}
+
+/// スライド方向の画面上の単位ベクトル（y は上向き）
+pub(super) fn direction_vector(direction: Direction) -> Vec2 {
+    match direction {
+        Direction::Up => Vec2::Y,
+        Direction::Down => Vec2::NEG_Y,
+        Direction::Left => Vec2::NEG_X,
+        Direction::Right => Vec2::X,
+        Direction::UpLeft => Vec2::new(-HEX_ASPECT, 0.5),
+        Direction::UpRight => Vec2::new(HEX_ASPECT, 0.5),
+        Direction::DownLeft => Vec2::new(-HEX_ASPECT, -0.5),
+        Direction::DownRight => Vec2::new(HEX_ASPECT, -0.5),
+    }
+}
+
+/// トポロジーに合わせたセル形状のスプライトを作る。色と大きさは `paint_board` がテーマに合わせる
+fn cell_sprite(topology: Topology, hexagon: &HexagonImage) -> Sprite {
+    let image = match topology {
+        Topology::Grid { .. } => Handle::default(),
+        Topology::Hex { .. } => hexagon.0.clone(),
+    };
+    Sprite {
+        image,
+        custom_size: Some(cell_size(topology, TILE_SIZE)),
+        ..default()
+    }
+}
+
+/// 白いフラットトップ六角形のマスク画像を生成する（4x4 スーパーサンプリングで縁を滑らかにする）
+fn hexagon_image() -> Image {
+    const SAMPLES: u32 = 4;
+    let width = HEX_IMAGE_WIDTH;
+    let height = (width as f32 * HEX_ASPECT).round() as u32;
+    let size = Vec2::new(width as f32, height as f32);
+
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let mut covered = 0;
+            for sy in 0..SAMPLES {
+                for sx in 0..SAMPLES {
+                    let offset = Vec2::new(
+                        x as f32 + (sx as f32 + 0.5) / SAMPLES as f32,
+                        y as f32 + (sy as f32 + 0.5) / SAMPLES as f32,
+                    ) - size / 2.0;
+                    if cell_contains(Topology::HEX, size, offset) {
+                        covered += 1;
+                    }
+                }
+            }
+            let alpha = (covered * 255 / (SAMPLES * SAMPLES)) as u8;
+            data.extend_from_slice(&[255, 255, 255, alpha]);
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+pub(super) fn camera_scaling(topology: Topology, count: usize) -> ScalingMode {
+    let extent = board_extent(topology);
+    let width = extent.x * count as f32 + BOARD_SPACING * (count as f32 - 1.0);
+    ScalingMode::AutoMin {
+        min_width: width + MARGIN * 2.0,
+        min_height: extent.y + HEADER_HEIGHT + MARGIN * 2.0,
+    }
+}
+
+/// 2048 より大きなタイルの色。`base` を起点に、値が上がるごとに明るさと彩度をそろえたまま色相を回す
+pub(super) fn beyond_color(base: Color, step: u32) -> Color {
+    let base = Oklcha::from(base);
+    let (low, high) = (*BEYOND_LIGHTNESS.start(), *BEYOND_LIGHTNESS.end());
+    // 色相だけでなく明るさも 3 段で揺らして、隣り合う値をさらに見分けやすくする
+    let lightness = base.lightness.clamp(low, high) + 0.05 * ((step % 3) as f32 - 1.0);
+    let color: Color = Oklcha::lch(
+        lightness.clamp(low, high),
+        base.chroma.max(BEYOND_MIN_CHROMA),
+        (base.hue + BEYOND_HUE_STEP * step as f32).rem_euclid(360.0),
+    )
+    .into();
+    let srgba = color.to_srgba();
+    Color::srgb(
+        srgba.red.clamp(0.0, 1.0),
+        srgba.green.clamp(0.0, 1.0),
+        srgba.blue.clamp(0.0, 1.0),
     )
 }
 
-pub(super) fn tile_color(exp: Option<NonZero<u8>>) -> Color {
+/// 組み込みの Classic テーマのタイルの色。端末版でも使う
+pub fn tile_color(exp: Option<NonZero<u8>>) -> Color {
     match exp.map(|e| e.get()) {
         None => COLOR_EMPTY_CELL,
         Some(1) => Color::srgb(0.933, 0.894, 0.855), // 2
@@ -61,17 +310,33 @@ pub(super) fn tile_color(exp: Option<NonZero<u8>>) -> Color {
         Some(9) => Color::srgb(0.929, 0.788, 0.314), // 512
         Some(10) => Color::srgb(0.929, 0.773, 0.247), // 1024
         Some(11) => Color::srgb(0.929, 0.761, 0.180), // 2048
-        _ => Color::srgb(0.239, 0.227, 0.196),       // > 2048
+        Some(e) => beyond_color(COLOR_BEYOND, u32::from(e) - 12),
     }
 }
 
-pub(super) fn text_color(exp: Option<NonZero<u8>>) -> Color {
+/// 組み込みの Classic テーマの文字の色
+pub fn text_color(exp: Option<NonZero<u8>>) -> Color {
     match exp.map(|e| e.get()) {
         Some(1 | 2) => COLOR_TEXT_DARK,
         _ => Color::srgb(0.976, 0.965, 0.949),
     }
 }
 
+/// タイルに書く数字
+pub(super) fn tile_label(exp: NonZero<u8>, settings: &LabelSettings) -> String {
+    let value = exp_to_value(exp.get());
+    if value < settings.compact_from {
+        return value.to_string();
+    }
+    match settings.format {
+        LabelFormat::Full => value.to_string(),
+        LabelFormat::Suffix if value >= 1_000_000 => format!("{}M", value / 1_000_000),
+        LabelFormat::Suffix if value >= 1_000 => format!("{}K", value / 1_000),
+        LabelFormat::Suffix => value.to_string(),
+        LabelFormat::Exponent => format!("2^{}", exp.get()),
+    }
+}
+
 pub(super) fn font_size_for_tile(exp: Option<NonZero<u8>>) -> f32 {
     match exp.map(|e| e.get()) {
         Some(e) if e >= 10 => 26.0, // 1024+
@@ -84,84 +349,319 @@ pub(super) fn font_size_for_tile(exp: Option<NonZero<u8>>) -> f32 {
 /// VisualTile エンティティをスポーンする
 pub(super) fn spawn_visual_tile(
     commands: &mut Commands,
+    field: Entity,
+    topology: Topology,
     board_index: usize,
     exp: NonZero<u8>,
     scale: Vec3,
     font: &GameFont,
+    hexagon: &HexagonImage,
+    skin: &TileSkin,
 ) -> Entity {
-    let pos = board_index_to_position(board_index);
-    let tile = Some(exp);
+    let pos = board_index_to_position(topology, board_index);
 
+    // スキンにその値の絵があれば絵のタイル、なければ数字のタイルにする
     commands
         .spawn((
-            VisualTile { board_index },
-            Sprite {
-                color: tile_color(tile),
-                custom_size: Some(Vec2::splat(TILE_SIZE)),
-                ..default()
-            },
+            VisualTile { board_index, exp },
+            ChildOf(field),
+            cell_sprite(topology, hexagon),
             Transform::from_translation(pos.extend(2.0)).with_scale(scale),
         ))
         .with_children(|parent| {
+            // 数字と大きさは `label_tiles` が値に合わせて書く
             let inv_scale = 1.0 / TEXT_RENDER_SCALE;
             parent.spawn((
                 TileText,
-                Text2d::new(exp_to_value(exp.get()).to_string()),
+                Text2d::default(),
                 TextFont {
                     font: font.0.clone().into(),
-                    font_size: (font_size_for_tile(tile) * TEXT_RENDER_SCALE).into(),
                     ..default()
                 },
-                TextColor(text_color(tile)),
                 TextLayout::justify(Justify::Center),
                 Transform::from_translation(Vec3::Z).with_scale(Vec3::splat(inv_scale)),
             ));
+            if let Some(image) = skin.image(exp) {
+                parent.spawn(tile_image(image));
+            }
         })
         .id()
 }
 
-pub(super) fn setup_board(mut commands: Commands, board: Res<Board>, font: Res<GameFont>) {
-    commands.insert_resource(ClearColor(COLOR_BG));
-    commands.spawn((
-        Camera2d,
-        Msaa::Off,
-        Projection::Orthographic(OrthographicProjection {
-            scaling_mode: ScalingMode::AutoMin {
-                min_width: BOARD_PX + MARGIN * 2.0,
-                min_height: BOARD_PX + HEADER_HEIGHT + MARGIN * 2.0,
-            },
-            ..OrthographicProjection::default_2d()
-        }),
-    ));
+/// タイルの数字を書く。絵のタイルでは隅に小さく添えるか、スキンが望まなければ隠す。
+/// 新しいタイルと値が変わったタイルはその場で、設定・スキン・テーマが変わったらすべて書き直す。
+/// 描画の前に文字の配置を計算し直せるように、`PostUpdate` の文字の配置より前に動かす
+pub(super) fn label_tiles(
+    settings: Res<LabelSettings>,
+    skin: Res<TileSkin>,
+    theme: Res<Theme>,
+    fields: Query<&Board>,
+    tiles: Query<(Ref<VisualTile>, &ChildOf, &Children)>,
+    mut texts: Query<(&mut Text2d, &mut TextFont, &mut Transform, &mut Visibility), With<TileText>>,
+) {
+    let all = settings.is_changed() || skin.is_changed() || theme.is_changed();
+    for (tile, child_of, children) in &tiles {
+        if !all && !tile.is_changed() {
+            continue;
+        }
+        let Ok(board) = fields.get(child_of.parent()) else {
+            continue;
+        };
+        let label = tile_label(tile.exp, &settings);
+        let (size, offset, visible) = if skin.image(tile.exp).is_some() {
+            let cell = themed_cell_size(board.topology(), &theme);
+            (BADGE_FONT_SIZE, cell * BADGE_OFFSET, skin.badge())
+        } else {
+            (font_size_for_tile(Some(tile.exp)), Vec2::ZERO, true)
+        };
+
+        let mut labels = texts.iter_many_mut(children);
+        while let Some((mut text, mut font, mut transform, mut visibility)) = labels.fetch_next() {
+            if text.0 != label {
+                text.0.clone_from(&label);
+            }
+            font.font_size = (size * TEXT_RENDER_SCALE).into();
+            transform.translation = offset.extend(1.0);
+            visibility.set_if_neq(if visible {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            });
+        }
+    }
+}
+
+/// 数字の実際の幅を測り、タイルに収まらなければ縮める。
+/// 文字の配置は `PostUpdate` で決まるので、その結果を次のフレームで使う
+pub(super) fn fit_tile_labels(
+    theme: Res<Theme>,
+    fields: Query<&Board>,
+    tiles: Query<&ChildOf, With<VisualTile>>,
+    mut labels: Query<(Ref<TextLayoutInfo>, &ChildOf, &mut Transform), With<TileText>>,
+) {
+    for (layout, child_of, mut transform) in &mut labels {
+        if !theme.is_changed() && !layout.is_changed() {
+            continue;
+        }
+        let Some(board) = tiles
+            .get(child_of.parent())
+            .ok()
+            .and_then(|tile| fields.get(tile.parent()).ok())
+        else {
+            continue;
+        };
 
-    // ボード背景
+        let topology = board.topology();
+        let available = themed_cell_size(topology, &theme).x * label_fill(topology);
+        let width = layout.size.x / layout.scale_factor.max(f32::EPSILON) / TEXT_RENDER_SCALE;
+        let fit = if width > available {
+            available / width
+        } else {
+            1.0
+        };
+        let scale = Vec3::splat(fit / TEXT_RENDER_SCALE);
+        if transform.scale != scale {
+            transform.scale = scale;
+        }
+    }
+}
+
+/// 盤面上のすべてのタイルを等倍でスポーンする
+pub(super) fn spawn_board_tiles(
+    commands: &mut Commands,
+    field: Entity,
+    board: &Board,
+    font: &GameFont,
+    hexagon: &HexagonImage,
+    skin: &TileSkin,
+) {
+    for (index, cell) in board.iter().enumerate() {
+        if let Some(exp) = cell {
+            spawn_visual_tile(
+                commands,
+                field,
+                board.topology(),
+                index,
+                *exp,
+                Vec3::ONE,
+                font,
+                hexagon,
+                skin,
+            );
+        }
+    }
+}
+
+/// ボード背景とセル背景を盤面エンティティの子としてスポーンする
+pub(super) fn spawn_board_layout(
+    commands: &mut Commands,
+    field: Entity,
+    topology: Topology,
+    hexagon: &HexagonImage,
+) {
     commands.spawn((
+        BoardBackground,
+        ChildOf(field),
         Sprite {
-            color: COLOR_BOARD,
-            custom_size: Some(Vec2::splat(BOARD_PX)),
+            custom_size: Some(board_extent(topology)),
             ..default()
         },
-        Transform::from_translation(Vec3::new(0.0, BOARD_OFFSET_Y, 0.0)),
+        Transform::default(),
     ));
 
-    // 16 個のセル背景
-    for index in 0..(BOARD_SIZE * BOARD_SIZE) {
-        let pos = board_index_to_position(index);
+    for index in 0..topology.cell_count() {
+        let pos = board_index_to_position(topology, index);
         commands.spawn((
-            CellBackground,
-            Sprite {
-                color: COLOR_EMPTY_CELL,
-                custom_size: Some(Vec2::splat(TILE_SIZE)),
-                ..default()
-            },
+            CellBackground { board_index: index },
+            ChildOf(field),
+            cell_sprite(topology, hexagon),
             Transform::from_translation(pos.extend(1.0)),
+            Pickable::default(),
         ));
     }
+}
 
-    // 初期タイルのスポーン
-    for (index, cell) in board.iter().enumerate() {
-        if let Some(exp) = cell {
-            spawn_visual_tile(&mut commands, index, *exp, Vec3::ONE, &font);
+/// 配置待ちの盤面では空きセルを強調し、それ以外は通常の色にする
+fn cell_color(theme: &Theme, board: &Board, phase: &AnimationPhase, index: usize) -> Color {
+    if *phase == AnimationPhase::AwaitingPlacement && board[index].is_none() {
+        theme.placement_target
+    } else {
+        theme.empty_cell
+    }
+}
+
+pub(super) fn highlight_placement_targets(
+    theme: Res<Theme>,
+    fields: Query<(&Board, &AnimationPhase)>,
+    mut cells: Query<(&CellBackground, &ChildOf, &mut Sprite)>,
+) {
+    for (cell, child_of, mut sprite) in &mut cells {
+        let Ok((board, phase)) = fields.get(child_of.parent()) else {
+            continue;
+        };
+        let color = cell_color(&theme, board, phase, cell.board_index);
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+/// 盤面の色と大きさをテーマに合わせる。新しく出したタイル・値が変わったタイルはその場で、
+/// テーマが変わったら（テーマファイルの書き換えも含む）盤面全体を塗り直す
+pub(super) fn paint_board(
+    theme: Res<Theme>,
+    mut clear_color: ResMut<ClearColor>,
+    fields: Query<(&Board, &AnimationPhase)>,
+    mut tiles: Query<
+        (Ref<VisualTile>, &ChildOf, &mut Sprite, &Children),
+        (Without<CellBackground>, Without<BoardBackground>),
+    >,
+    mut labels: Query<&mut TextColor, With<TileText>>,
+    mut cells: Query<
+        (Ref<CellBackground>, &ChildOf, &mut Sprite),
+        (Without<VisualTile>, Without<BoardBackground>),
+    >,
+    mut backgrounds: Query<
+        (Ref<BoardBackground>, &ChildOf, &mut Sprite),
+        (Without<VisualTile>, Without<CellBackground>),
+    >,
+) {
+    let all = theme.is_changed();
+    if all {
+        clear_color.0 = theme.background;
+    }
+
+    for (tile, child_of, mut sprite, children) in &mut tiles {
+        if !all && !tile.is_changed() {
+            continue;
+        }
+        let Ok((board, _)) = fields.get(child_of.parent()) else {
+            continue;
+        };
+        sprite.color = theme.tile(tile.exp);
+        sprite.custom_size = Some(themed_cell_size(board.topology(), &theme));
+        let mut texts = labels.iter_many_mut(children);
+        while let Some(mut color) = texts.fetch_next() {
+            color.0 = theme.text(tile.exp);
+        }
+    }
+
+    for (cell, child_of, mut sprite) in &mut cells {
+        if !all && !cell.is_added() {
+            continue;
+        }
+        let Ok((board, phase)) = fields.get(child_of.parent()) else {
+            continue;
+        };
+        sprite.color = cell_color(&theme, board, phase, cell.board_index);
+        sprite.custom_size = Some(themed_cell_size(board.topology(), &theme));
+    }
+
+    for (background, child_of, mut sprite) in &mut backgrounds {
+        if !all && !background.is_added() {
+            continue;
+        }
+        let Ok((board, _)) = fields.get(child_of.parent()) else {
+            continue;
+        };
+        sprite.color = theme.board;
+        sprite.custom_size = Some(themed_board_extent(board.topology(), &theme));
+    }
+}
+
+pub(super) fn setup_board(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    config: Res<MatchConfig>,
+    theme: Res<Theme>,
+) {
+    commands.insert_resource(HexagonImage(images.add(hexagon_image())));
+    commands.insert_resource(ClearColor(theme.background));
+    commands.spawn((
+        Camera2d,
+        Msaa::Off,
+        Projection::Orthographic(OrthographicProjection {
+            scaling_mode: camera_scaling(config.topology, config.mode.player_count()),
+            ..OrthographicProjection::default_2d()
+        }),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(exp: u8, format: LabelFormat) -> String {
+        let settings = LabelSettings {
+            format,
+            compact_from: 10_000,
+        };
+        tile_label(NonZero::new(exp).unwrap(), &settings)
+    }
+
+    #[test]
+    fn compact_labels_start_at_the_threshold() {
+        assert_eq!(label(13, LabelFormat::Suffix), "8192");
+        assert_eq!(label(14, LabelFormat::Suffix), "16K");
+        assert_eq!(label(20, LabelFormat::Suffix), "1M");
+        assert_eq!(label(14, LabelFormat::Exponent), "2^14");
+        assert_eq!(label(14, LabelFormat::Full), "16384");
+    }
+
+    #[test]
+    fn colors_past_2048_stay_distinct_and_dark() {
+        let colors: Vec<Oklaba> = (12..=24)
+            .map(|exp| tile_color(NonZero::new(exp)).into())
+            .collect();
+        for (i, a) in colors.iter().enumerate() {
+            assert!(
+                a.lightness <= *BEYOND_LIGHTNESS.end() + 0.01,
+                "{a:?} too light"
+            );
+            for b in &colors[i + 1..] {
+                let d = Vec3::new(a.lightness - b.lightness, a.a - b.a, a.b - b.b).length();
+                assert!(d > 0.03, "{a:?} and {b:?} too close ({d})");
+            }
         }
     }
 }
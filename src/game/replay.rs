@@ -0,0 +1,278 @@
+//! 対局の記録（リプレイ）。最初の盤面と、各手の方向とその後に出たタイルを順に持つ。
+//! 記録から途中の盤面をすべて再現でき、`*.replay.ron` として保存・読み込みできる。
+
+use std::num::NonZero;
+
+use serde::{Deserialize, Serialize};
+
+use super::board::{Board, Direction, SlideResult};
+use super::topology::Topology;
+
+/// リプレイファイルの拡張子
+pub const REPLAY_EXTENSION: &str = "replay.ron";
+/// 記録に書けるタイルの指数の上限（2^20）。壊れたファイルや手で書き換えたファイルの値で、
+/// 合体を重ねても `exp_to_value` が `u32` からあふれないようにする
+const MAX_RECORDED_EXP: u8 = 20;
+
+/// 1 手分の記録
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ReplayMove {
+    pub direction: Direction,
+    /// 手の後に出たタイルの (セル, 指数)。盤面が埋まっていれば出ない
+    pub spawn: Option<(usize, u8)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Replay {
+    pub topology: Topology,
+    /// 最初の盤面の各セルの指数（空きは 0）
+    pub initial: Vec<u8>,
+    /// 最初の盤面でのスコア。対局の途中から切り出した記録で 0 でなくなる
+    #[serde(default)]
+    pub initial_score: u32,
+    pub moves: Vec<ReplayMove>,
+}
+
+/// 再生した 1 手分。手の前の盤面とスコア、スライドの結果、出たタイル
+pub struct ReplayStep {
+    pub before: Board,
+    pub score: u32,
+    pub slide: SlideResult,
+    pub spawn: Option<(usize, NonZero<u8>)>,
+}
+
+impl Replay {
+    /// `board` から始まる空の記録
+    pub fn new(board: &Board) -> Self {
+        Self {
+            topology: board.topology(),
+            initial: board
+                .iter()
+                .map(|cell| cell.map_or(0, NonZero::get))
+                .collect(),
+            initial_score: 0,
+            moves: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, direction: Direction, spawn: Option<(usize, NonZero<u8>)>) {
+        self.moves.push(ReplayMove {
+            direction,
+            spawn: spawn.map(|(index, exp)| (index, exp.get())),
+        });
+    }
+
+    /// 最後の手の後に出たタイルを記録する。ホットシートでは手の後でもう一人が置く
+    pub fn set_last_spawn(&mut self, index: usize, exp: NonZero<u8>) {
+        if let Some(last) = self.moves.last_mut() {
+            last.spawn = Some((index, exp.get()));
+        }
+    }
+
+    /// 最後の手を取り消す
+    pub fn pop(&mut self) {
+        self.moves.pop();
+    }
+
+    pub fn initial_board(&self) -> Result<Board, String> {
+        let empty = match self.topology {
+            Topology::Grid { width, height } => width == 0 || height == 0,
+            Topology::Hex { radius } => radius == 0,
+        };
+        if empty {
+            return Err(format!("invalid board size {:?}", self.topology));
+        }
+        if self.initial.len() != self.topology.cell_count() {
+            return Err(format!(
+                "the initial board has {} cells, expected {}",
+                self.initial.len(),
+                self.topology.cell_count()
+            ));
+        }
+        if let Some(&exp) = self.initial.iter().find(|&&exp| exp > MAX_RECORDED_EXP) {
+            return Err(format!(
+                "initial tile exponent {exp} exceeds {MAX_RECORDED_EXP}"
+            ));
+        }
+        let mut board = Board::new(self.topology);
+        for (cell, &exp) in board.iter_mut().zip(&self.initial) {
+            *cell = NonZero::new(exp);
+        }
+        Ok(board)
+    }
+
+    /// 最初から順に再生する。盤面が動かない手や、埋まっているセルへの出現、大きすぎるタイルがあれば `Err`
+    pub fn steps(&self) -> Result<Vec<ReplayStep>, String> {
+        let mut board = self.initial_board()?;
+        let mut score = self.initial_score;
+        let mut steps = Vec::with_capacity(self.moves.len());
+        for (turn, step) in self.moves.iter().enumerate() {
+            let slide = board.compute_slide(step.direction);
+            if !slide.changed {
+                return Err(format!("move {} does not change the board", turn + 1));
+            }
+            let mut next = slide.new_board.clone();
+            let spawn = match step.spawn {
+                Some((_, exp)) if exp > MAX_RECORDED_EXP => {
+                    return Err(format!(
+                        "move {} spawns exponent {exp}, which exceeds {MAX_RECORDED_EXP}",
+                        turn + 1
+                    ));
+                }
+                Some((index, exp)) => {
+                    let exp = NonZero::new(exp)
+                        .filter(|_| next.get(index).is_some_and(Option::is_none))
+                        .ok_or_else(|| format!("move {} spawns on a taken cell", turn + 1))?;
+                    next[index] = Some(exp);
+                    Some((index, exp))
+                }
+                None => None,
+            };
+            let gained = slide.score_gained;
+            steps.push(ReplayStep {
+                before: std::mem::replace(&mut board, next),
+                score,
+                slide,
+                spawn,
+            });
+            score += gained;
+        }
+        Ok(steps)
+    }
+
+    /// 最後の `count` 手だけの記録。手前の手を再生した盤面から始める
+    pub fn last_moves(&self, count: usize) -> Result<Self, String> {
+        let skip = self.moves.len().saturating_sub(count);
+        let steps = self.steps()?;
+        let (start, score) = match steps.into_iter().nth(skip) {
+            Some(step) => (step.before, step.score),
+            None => (self.final_board()?, self.final_score()?),
+        };
+        Ok(Self {
+            initial_score: score,
+            moves: self.moves[skip..].to_vec(),
+            ..Self::new(&start)
+        })
+    }
+
+    /// すべての手を再生した後の盤面
+    pub fn final_board(&self) -> Result<Board, String> {
+        let mut board = self.initial_board()?;
+        for step in self.steps()? {
+            board = step.slide.new_board;
+            if let Some((index, exp)) = step.spawn {
+                board[index] = Some(exp);
+            }
+        }
+        Ok(board)
+    }
+
+    /// すべての手を再生した後のスコア
+    pub fn final_score(&self) -> Result<u32, String> {
+        Ok(self.steps()?.last().map_or(self.initial_score, |step| {
+            step.score + step.slide.score_gained
+        }))
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        ron::from_str(text).map_err(|err| err.to_string())
+    }
+
+    pub fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .expect("replays are always serializable")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+
+    use super::*;
+
+    /// シードから決まる対局を `moves` 手まで記録する
+    fn record(topology: Topology, moves: usize) -> (Replay, Board) {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut board = Board::with_two_tiles(topology, &mut rng);
+        let mut replay = Replay::new(&board);
+        for _ in 0..moves {
+            let Some(slide) = topology
+                .directions()
+                .iter()
+                .map(|&direction| (direction, board.compute_slide(direction)))
+                .find(|(_, slide)| slide.changed)
+            else {
+                break;
+            };
+            board = slide.1.new_board;
+            let spawn = board.place_random_tile(&mut rng);
+            replay.push(
+                slide.0,
+                spawn.and_then(|index| Some((index, board[index]?))),
+            );
+        }
+        (replay, board)
+    }
+
+    #[test]
+    fn replay_reproduces_the_recorded_board() {
+        for topology in [Topology::CLASSIC, Topology::HEX] {
+            let (replay, board) = record(topology, 40);
+            let parsed = Replay::from_ron(&replay.to_ron()).unwrap();
+            assert_eq!(parsed, replay);
+            assert_eq!(parsed.final_board().unwrap(), board);
+        }
+    }
+
+    #[test]
+    fn last_moves_start_from_the_replayed_board() {
+        let (replay, board) = record(Topology::CLASSIC, 30);
+        let tail = replay.last_moves(10).unwrap();
+        assert_eq!(tail.moves.len(), 10);
+        assert_eq!(tail.final_board().unwrap(), board);
+        assert_eq!(tail.final_score(), replay.final_score());
+        assert_eq!(replay.last_moves(100).unwrap(), replay);
+    }
+
+    #[test]
+    fn spawn_on_a_taken_cell_is_rejected() {
+        let (mut replay, _) = record(Topology::CLASSIC, 3);
+        let steps = replay.steps().unwrap();
+        let taken = steps[2].slide.new_board.iter().position(Option::is_some);
+        replay.moves[2].spawn = taken.map(|index| (index, 1));
+        assert!(replay.steps().is_err());
+    }
+
+    #[test]
+    fn corrupt_files_are_rejected_instead_of_overflowing() {
+        let (replay, _) = record(Topology::CLASSIC, 3);
+
+        let mut huge_initial = replay.clone();
+        huge_initial.initial[0] = 40;
+        assert!(huge_initial.initial_board().is_err());
+
+        let mut huge_spawn = replay.clone();
+        let free = replay.steps().unwrap()[0]
+            .slide
+            .new_board
+            .iter()
+            .position(Option::is_none);
+        huge_spawn.moves[0].spawn = free.map(|index| (index, 32));
+        assert!(huge_spawn.steps().is_err());
+
+        for topology in [
+            Topology::Grid {
+                width: 0,
+                height: 4,
+            },
+            Topology::Hex { radius: 0 },
+        ] {
+            let empty = Replay {
+                topology,
+                initial: vec![0; topology.cell_count()],
+                ..replay.clone()
+            };
+            assert!(empty.initial_board().is_err());
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! スコアの演出。マージ先から「+N」が浮かび上がり、ヘッダーのスコアはカウントアップする。
+//! 1 手で大きく稼いだときはヘッダーのスコアが光って弾む。
+//! どれもアニメーション設定（速さ・アニメーションなし・動きを減らす）に従う。
+
+use bevy::prelude::*;
+
+use super::GameFont;
+use super::animation::{Motion, SlideScored};
+use super::board::{Board, Score, exp_to_value};
+use super::render::{TEXT_RENDER_SCALE, board_index_to_position};
+use super::theme::Theme;
+use super::ui::UIScoreText;
+use super::update_mode::LiveEffect;
+
+/// ヘッダーのスコアが追いつくまでの時間
+const COUNT_DURATION: f32 = 0.3;
+const POPUP_DURATION: f32 = 0.6;
+/// ポップアップが浮かび上がる高さ
+const POPUP_RISE: f32 = 60.0;
+/// タイルより手前に出す
+const POPUP_Z: f32 = 5.0;
+const POPUP_FONT_SIZE: f32 = 28.0;
+/// 1 手でこれ以上稼いだら派手に見せる
+const BIG_GAIN: u32 = 512;
+const BIG_POPUP_FONT_SIZE: f32 = 40.0;
+const FLOURISH_DURATION: f32 = 0.6;
+const FLOURISH_SCALE_PEAK: f32 = 1.3;
+
+/// ヘッダーに表示しているスコア。実際のスコアに向かってカウントアップする
+#[derive(Component, Default)]
+pub(super) struct ScoreCounter {
+    from: u32,
+    to: u32,
+    shown: u32,
+    elapsed: f32,
+}
+
+impl ScoreCounter {
+    pub(super) fn value(&self) -> u32 {
+        self.shown
+    }
+}
+
+#[derive(Component)]
+#[require(LiveEffect)]
+pub(super) struct ScorePopup {
+    origin: Vec2,
+    timer: Timer,
+}
+
+/// 大きく稼いだときのヘッダーのスコアの演出
+#[derive(Component)]
+#[require(LiveEffect)]
+pub(super) struct ScoreFlourish(Timer);
+
+/// 表示中のスコアを実際のスコアに近づける。取り消しや新しいゲームで減ったときはすぐに合わせる
+pub(super) fn count_up_score(
+    time: Res<Time>,
+    motion: Motion,
+    mut fields: Query<(&Score, &mut ScoreCounter)>,
+) {
+    for (score, mut counter) in &mut fields {
+        let target = **score;
+        if counter.shown == target {
+            continue;
+        }
+        if target < counter.shown || motion.instant() {
+            counter.shown = target;
+            continue;
+        }
+
+        if counter.to != target {
+            counter.from = counter.shown;
+            counter.to = target;
+            counter.elapsed = 0.0;
+        }
+        counter.elapsed += motion.delta(&time).as_secs_f32();
+        let t = motion.ease(counter.elapsed / COUNT_DURATION);
+        let gained = (counter.to - counter.from) as f32 * t;
+        counter.shown = (counter.from + gained.round() as u32).min(target);
+    }
+}
+
+/// マージ先に「+N」を出す。大きく稼いだ手ではヘッダーのスコアも光らせる
+pub(super) fn spawn_score_popups(
+    mut commands: Commands,
+    mut scored: MessageReader<SlideScored>,
+    motion: Motion,
+    font: Res<GameFont>,
+    theme: Res<Theme>,
+    fields: Query<&Board>,
+    headers: Query<Entity, With<UIScoreText>>,
+) {
+    for SlideScored {
+        field,
+        merges,
+        gained,
+    } in scored.read()
+    {
+        let Ok(board) = fields.get(*field) else {
+            continue;
+        };
+        if motion.instant() {
+            continue;
+        }
+
+        let big = *gained >= BIG_GAIN;
+        let (size, color) = if big {
+            (BIG_POPUP_FONT_SIZE, theme.highlight)
+        } else {
+            (POPUP_FONT_SIZE, theme.score)
+        };
+        for &(index, exp) in merges {
+            let origin = board_index_to_position(board.topology(), index);
+            commands.spawn((
+                ScorePopup {
+                    origin,
+                    timer: Timer::from_seconds(POPUP_DURATION, TimerMode::Once),
+                },
+                ChildOf(*field),
+                Text2d::new(format!("+{}", exp_to_value(exp.get()))),
+                TextFont {
+                    font: font.0.clone().into(),
+                    font_size: (size * TEXT_RENDER_SCALE).into(),
+                    ..default()
+                },
+                TextColor(color),
+                Transform::from_translation(origin.extend(POPUP_Z))
+                    .with_scale(Vec3::splat(1.0 / TEXT_RENDER_SCALE)),
+            ));
+        }
+
+        if big {
+            for header in &headers {
+                commands
+                    .entity(header)
+                    .insert(ScoreFlourish(Timer::from_seconds(
+                        FLOURISH_DURATION,
+                        TimerMode::Once,
+                    )));
+            }
+        }
+    }
+}
+
+/// ポップアップを浮かび上がらせながら消していく。動きを減らす設定ではその場で消える
+pub(super) fn animate_score_popups(
+    mut commands: Commands,
+    time: Res<Time>,
+    motion: Motion,
+    mut popups: Query<(Entity, &mut ScorePopup, &mut Transform, &mut TextColor)>,
+) {
+    let delta = motion.delta(&time);
+    for (entity, mut popup, mut transform, mut color) in &mut popups {
+        popup.timer.tick(delta);
+        if popup.timer.is_finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let t = popup.timer.fraction();
+        let rise = if motion.reduced() {
+            0.0
+        } else {
+            POPUP_RISE * motion.ease(t)
+        };
+        transform.translation.y = popup.origin.y + rise;
+        color.0.set_alpha(1.0 - t * t);
+    }
+}
+
+/// ヘッダーのスコアをテーマの強調色に光らせて弾ませ、元に戻す
+pub(super) fn animate_score_flourish(
+    mut commands: Commands,
+    time: Res<Time>,
+    motion: Motion,
+    theme: Res<Theme>,
+    mut headers: Query<(Entity, &mut ScoreFlourish, &mut TextColor, &mut UiTransform)>,
+) {
+    let delta = motion.delta(&time);
+    for (entity, mut flourish, mut color, mut transform) in &mut headers {
+        flourish.0.tick(delta);
+        if flourish.0.is_finished() {
+            color.0 = theme.score;
+            *transform = UiTransform::IDENTITY;
+            commands
+                .entity(entity)
+                .remove::<(ScoreFlourish, LiveEffect)>();
+            continue;
+        }
+
+        let t = flourish.0.fraction();
+        color.0 = theme.highlight.mix(&theme.score, t);
+        if !motion.reduced() {
+            let scale = 1.0 + (FLOURISH_SCALE_PEAK - 1.0) * (t * std::f32::consts::PI).sin();
+            transform.scale = Vec2::splat(scale);
+        }
+    }
+}
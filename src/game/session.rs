@@ -0,0 +1,220 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use rand::rng;
+
+use super::GameFont;
+use super::animation::{AnimationPhase, PendingSlide, SlideQueue};
+use super::board::{Board, Score};
+use super::input::{ActionInput, Keymap};
+use super::keybindings::Action;
+use super::render::{
+    HexagonImage, VisualTile, camera_scaling, play_field_offset, spawn_board_layout,
+    spawn_board_tiles,
+};
+use super::replay::Replay;
+use super::score_effects::ScoreCounter;
+use super::skin::TileSkin;
+use super::spawner::Spawner;
+use super::state::{GamePhase, HasWon, MatchOutcome, Screen};
+use super::topology::Topology;
+
+/// 取り消せる手数の上限
+const UNDO_LIMIT: usize = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub(super) enum GameMode {
+    #[default]
+    Solo,
+    /// 2 つの盤面を左右に並べるローカル対戦
+    Versus,
+}
+
+impl GameMode {
+    pub(super) fn player_count(self) -> usize {
+        match self {
+            Self::Solo => 1,
+            Self::Versus => 2,
+        }
+    }
+}
+
+/// 次に始めるゲームの設定
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub(super) struct MatchConfig {
+    pub(super) mode: GameMode,
+    pub(super) topology: Topology,
+    pub(super) spawner: Spawner,
+}
+
+/// 盤面 1 つ分のプレイ状態を持つエンティティ。
+/// ボード背景・セル背景・タイルはこのエンティティの子としてスポーンされる。
+#[derive(Component)]
+#[require(
+    Score,
+    AnimationPhase,
+    PendingSlide,
+    SlideQueue,
+    ScoreCounter,
+    HasWon,
+    UndoHistory,
+    Spawner,
+    Transform,
+    Visibility
+)]
+pub(super) struct PlayField;
+
+/// 取り消し用に、各手の直前の盤面とスコアを覚えておく
+#[derive(Component, Default)]
+pub(super) struct UndoHistory(Vec<(Board, Score)>);
+
+impl UndoHistory {
+    pub(super) fn push(&mut self, board: &Board, score: Score) {
+        if self.0.len() == UNDO_LIMIT {
+            self.0.remove(0);
+        }
+        self.0.push((board.clone(), score));
+    }
+}
+
+/// 対局の記録。画像の書き出しやリプレイの保存に使う
+#[derive(Component)]
+pub(super) struct MoveLog(pub(super) Replay);
+
+/// 盤面の持ち主（0 始まり）
+#[derive(Component, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(super) struct Player(pub(super) usize);
+
+impl Player {
+    pub(super) fn label(self) -> String {
+        format!("P{}", self.0 + 1)
+    }
+}
+
+/// すべての盤面を作り直して新しいゲームを始めるためのパラメータ
+#[derive(SystemParam)]
+pub(super) struct GameReset<'w, 's> {
+    commands: Commands<'w, 's>,
+    config: ResMut<'w, MatchConfig>,
+    outcome: ResMut<'w, MatchOutcome>,
+    next_state: ResMut<'w, NextState<GamePhase>>,
+    next_screen: ResMut<'w, NextState<Screen>>,
+    font: Res<'w, GameFont>,
+    hexagon: Res<'w, HexagonImage>,
+    skin: Res<'w, TileSkin>,
+    fields: Query<'w, 's, (Entity, &'static mut SlideQueue), With<PlayField>>,
+    projections: Query<'w, 's, &'static mut Projection, With<Camera2d>>,
+}
+
+impl GameReset<'_, '_> {
+    pub(super) fn config(&self) -> MatchConfig {
+        *self.config
+    }
+
+    /// 指定した設定で新しいゲームを始め、盤面の画面に移る
+    pub(super) fn restart(&mut self, config: MatchConfig) {
+        // 消す盤面に溜まっていた入力が、このフレームのうちに新しいゲームへ流れ込まないようにする
+        for (entity, mut queue) in &mut self.fields {
+            queue.clear();
+            self.commands.entity(entity).despawn();
+        }
+
+        *self.config = config;
+        *self.outcome = MatchOutcome::default();
+        self.next_state.set(GamePhase::Playing);
+        self.next_screen.set(Screen::Playing);
+
+        let count = config.mode.player_count();
+        for index in 0..count {
+            let board = Board::with_two_tiles(config.topology, &mut rng());
+            let keymap = match config.mode {
+                GameMode::Solo => Keymap::Solo,
+                GameMode::Versus if index == 0 => Keymap::PlayerOne,
+                GameMode::Versus => Keymap::PlayerTwo,
+            };
+            let field = self
+                .commands
+                .spawn((
+                    PlayField,
+                    Player(index),
+                    keymap,
+                    config.spawner,
+                    MoveLog(Replay::new(&board)),
+                    board.clone(),
+                    Transform::from_translation(play_field_offset(config.topology, index, count)),
+                ))
+                .id();
+            spawn_board_layout(&mut self.commands, field, config.topology, &self.hexagon);
+            spawn_board_tiles(
+                &mut self.commands,
+                field,
+                &board,
+                &self.font,
+                &self.hexagon,
+                &self.skin,
+            );
+        }
+
+        for mut projection in &mut self.projections {
+            if let Projection::Orthographic(orthographic) = &mut *projection {
+                orthographic.scaling_mode = camera_scaling(config.topology, count);
+            }
+        }
+    }
+}
+
+/// 新しいゲームのキーで、今の設定のままゲームを始め直す
+pub(super) fn new_game_on_key(input: ActionInput, mut reset: GameReset) {
+    if input.just_pressed(Action::NewGame) {
+        let config = reset.config();
+        reset.restart(config);
+    }
+}
+
+/// 取り消しキーで 1 手前の盤面に戻す。対戦では使えない。
+/// ゲームオーバー画面からでも戻せる
+pub(super) fn undo_move(
+    mut commands: Commands,
+    input: ActionInput,
+    config: Res<MatchConfig>,
+    font: Res<GameFont>,
+    hexagon: Res<HexagonImage>,
+    skin: Res<TileSkin>,
+    mut next_state: ResMut<NextState<GamePhase>>,
+    mut fields: Query<(
+        Entity,
+        &mut Board,
+        &mut Score,
+        &mut AnimationPhase,
+        &mut UndoHistory,
+        &mut MoveLog,
+        &mut SlideQueue,
+    )>,
+    tiles: Query<(Entity, &ChildOf), With<VisualTile>>,
+) {
+    if config.mode != GameMode::Solo || !input.just_pressed(Action::Undo) {
+        return;
+    }
+
+    for (field, mut board, mut score, mut phase, mut history, mut log, mut queue) in &mut fields {
+        if phase.is_animating() {
+            continue;
+        }
+        let Some((previous, previous_score)) = history.0.pop() else {
+            continue;
+        };
+        log.0.pop();
+        // 取り消す前の盤面に向けて溜めた入力を、戻した盤面で再生しない
+        queue.clear();
+
+        for (entity, child_of) in &tiles {
+            if child_of.parent() == field {
+                commands.entity(entity).despawn();
+            }
+        }
+        spawn_board_tiles(&mut commands, field, &previous, &font, &hexagon, &skin);
+        *board = previous;
+        *score = previous_score;
+        *phase = AnimationPhase::Idle;
+        next_state.set(GamePhase::Playing);
+    }
+}
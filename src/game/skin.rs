@@ -0,0 +1,239 @@
+//! 画像のタイルスキン。値ごとの絵を `assets/skins/*.skin.ron` の対応表から読み込み、数字の代わりにタイルに描く。
+//! 対応表にない値には代わりの絵を描き、それもなければいつもの数字のタイルにする。
+
+use std::collections::BTreeMap;
+use std::num::NonZero;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::board::Board;
+use super::render::{VisualTile, themed_cell_size};
+use super::theme::Theme;
+use super::topology::Topology;
+use super::ui::Press;
+
+/// ヘッダーのボタンで数字のタイルの次から順に切り替えるスキン
+const SKIN_PATHS: &[&str] = &["skins/shapes.skin.ron"];
+
+/// タイルの大きさのうち絵に使う割合。六角形は角が斜めなので小さくする
+const IMAGE_FILL_GRID: f32 = 0.8;
+const IMAGE_FILL_HEX: f32 = 0.62;
+/// 絵はタイルの背景より上、目印と数字より下に描く
+const IMAGE_Z: f32 = 0.25;
+
+/// スキンの対応表の中身。値は指数（2 なら 1、2048 なら 11）で書く
+#[derive(Deserialize)]
+struct SkinFile {
+    name: String,
+    /// 指数ごとの絵。アセットフォルダからの相対パス
+    images: BTreeMap<u8, String>,
+    /// 対応表にない値の絵。省略するとその値は数字のタイルにする
+    #[serde(default)]
+    fallback: Option<String>,
+    /// 絵の隅に小さく数字を添える
+    #[serde(default)]
+    badge: bool,
+}
+
+fn parse_skin(bytes: &[u8]) -> Result<SkinFile, String> {
+    let file: SkinFile = ron::de::from_bytes(bytes).map_err(|err| err.to_string())?;
+    if file.images.contains_key(&0) {
+        return Err("exponents start at 1 (the 2 tile)".to_string());
+    }
+    if file.images.is_empty() && file.fallback.is_none() {
+        return Err("a skin needs at least one image".to_string());
+    }
+    Ok(file)
+}
+
+/// スキン 1 つ分
+#[derive(Asset, TypePath, Clone)]
+pub(super) struct SkinAsset {
+    name: String,
+    images: BTreeMap<NonZero<u8>, Handle<Image>>,
+    fallback: Option<Handle<Image>>,
+    badge: bool,
+}
+
+#[derive(Default, TypePath)]
+pub(super) struct SkinLoader;
+
+impl AssetLoader for SkinLoader {
+    type Asset = SkinAsset;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file = parse_skin(&bytes)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        let images = file
+            .images
+            .into_iter()
+            .filter_map(|(exp, path)| Some((NonZero::new(exp)?, load_context.load(path))))
+            .collect();
+        Ok(SkinAsset {
+            name: file.name,
+            images,
+            fallback: file.fallback.map(|path| load_context.load(path)),
+            badge: file.badge,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["skin.ron"]
+    }
+}
+
+/// 選べるスキンと、選んでいるスキン。`None` なら数字のタイル
+#[derive(Resource)]
+pub(super) struct Skins {
+    handles: Vec<Handle<SkinAsset>>,
+    current: Option<usize>,
+}
+
+/// 今タイルに使っているスキン。読み込みが終わるまでと、スキンを選んでいないときは `None`
+#[derive(Resource, Default)]
+pub(super) struct TileSkin(Option<SkinAsset>);
+
+impl TileSkin {
+    /// その値のタイルに描く絵。なければ数字のタイルにする
+    pub(super) fn image(&self, exp: NonZero<u8>) -> Option<Handle<Image>> {
+        let skin = self.0.as_ref()?;
+        skin.images.get(&exp).or(skin.fallback.as_ref()).cloned()
+    }
+
+    /// 絵の隅に数字を添えるか
+    pub(super) fn badge(&self) -> bool {
+        self.0.as_ref().is_some_and(|skin| skin.badge)
+    }
+
+    pub(super) fn label(&self) -> &str {
+        self.0.as_ref().map_or("Numbers", |skin| &skin.name)
+    }
+}
+
+pub(super) fn load_skins(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Skins {
+        handles: SKIN_PATHS
+            .iter()
+            .map(|path| asset_server.load(*path))
+            .collect(),
+        current: None,
+    });
+}
+
+/// 数字 → 各スキン → 数字 の順に切り替える
+pub(super) fn cycle_skin(_press: On<Press>, mut skins: ResMut<Skins>) {
+    skins.current = match skins.current {
+        None if !skins.handles.is_empty() => Some(0),
+        Some(index) if index + 1 < skins.handles.len() => Some(index + 1),
+        _ => None,
+    };
+}
+
+/// 選んでいるスキンの読み込みが終わったときや書き換えられたとき、選び直したときに、今のスキンを差し替える
+pub(super) fn sync_skin(
+    mut events: MessageReader<AssetEvent<SkinAsset>>,
+    skins: Res<Skins>,
+    assets: Res<Assets<SkinAsset>>,
+    mut skin: ResMut<TileSkin>,
+) {
+    let handle = skins.current.map(|index| &skins.handles[index]);
+    let updated = events.read().any(|event| {
+        handle.is_some_and(|handle| {
+            event.is_loaded_with_dependencies(handle) || event.is_modified(handle)
+        })
+    });
+    if !updated && !skins.is_changed() {
+        return;
+    }
+    skin.0 = handle.and_then(|handle| assets.get(handle)).cloned();
+}
+
+/// タイルの絵
+#[derive(Component)]
+pub(super) struct TileImage;
+
+/// 絵のスプライト。大きさは `skin_tiles` がテーマに合わせて決める
+pub(super) fn tile_image(image: Handle<Image>) -> (TileImage, Sprite, Transform) {
+    (
+        TileImage,
+        Sprite::from_image(image),
+        Transform::from_xyz(0.0, 0.0, IMAGE_Z),
+    )
+}
+
+/// タイルの絵を描き直す。値が変わったタイルと新しいタイルはその場で、
+/// スキンやテーマが変わったらすべてのタイルを描き直す。数字と小札は `label_tiles` が書く
+pub(super) fn skin_tiles(
+    mut commands: Commands,
+    skin: Res<TileSkin>,
+    theme: Res<Theme>,
+    fields: Query<&Board>,
+    tiles: Query<(Entity, Ref<VisualTile>, &ChildOf, &Children)>,
+    mut images: Query<&mut Sprite, With<TileImage>>,
+) {
+    let all = skin.is_changed() || theme.is_changed();
+    for (entity, tile, child_of, children) in &tiles {
+        if !all && !tile.is_changed() {
+            continue;
+        }
+        let Ok(board) = fields.get(child_of.parent()) else {
+            continue;
+        };
+        let existing = children.iter().find(|child| images.contains(*child));
+
+        let Some(image) = skin.image(tile.exp) else {
+            if let Some(child) = existing {
+                commands.entity(child).despawn();
+            }
+            continue;
+        };
+        let fill = match board.topology() {
+            Topology::Grid { .. } => IMAGE_FILL_GRID,
+            Topology::Hex { .. } => IMAGE_FILL_HEX,
+        };
+        let size = Vec2::splat(themed_cell_size(board.topology(), &theme).min_element() * fill);
+        match existing.and_then(|child| images.get_mut(child).ok()) {
+            Some(mut sprite) => {
+                sprite.image = image;
+                sprite.custom_size = Some(size);
+            }
+            None => {
+                let mut bundle = tile_image(image);
+                bundle.1.custom_size = Some(size);
+                commands.spawn((ChildOf(entity), bundle));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_skin_covers_every_value_up_to_2048() {
+        let bytes = include_bytes!("../../assets/skins/shapes.skin.ron");
+        let skin = parse_skin(bytes).unwrap();
+        assert!((1..=11).all(|exp| skin.images.contains_key(&exp)));
+        assert!(skin.fallback.is_some());
+    }
+
+    #[test]
+    fn exponent_zero_and_empty_skins_are_rejected() {
+        assert!(parse_skin(br#"(name: "Zero", images: { 0: "a.png" })"#).is_err());
+        assert!(parse_skin(br#"(name: "Empty", images: {})"#).is_err());
+        assert!(parse_skin(br#"(name: "Fallback", images: {}, fallback: Some("a.png"))"#).is_ok());
+    }
+}
@@ -0,0 +1,356 @@
+use std::num::NonZero;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use super::board::{Board, non_zero_exp};
+
+/// 1 回の出現位置決定で作って評価する局面数の上限。
+/// 探索は `resolve_slide` の中で同期的に進むので、深さを上げても 1 フレームを大きく超えて止まらないようにする。
+/// 上限に達したら、その深さの読みは捨てて 1 つ浅い深さで読み切った答えを使う（`Search::worst_spawn`）
+const SEARCH_BOARD_BUDGET: usize = 2_000;
+/// 動かせなくなった局面の評価値
+const LOSS_VALUE: f32 = -1.0e6;
+
+/// 新しいタイルの出現位置と値（2 か 4）を決める方式
+#[derive(Component, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Spawner {
+    /// 空きセルから一様に選び、10% の確率で 4 を置く
+    #[default]
+    Random,
+    /// プレイヤーにとって最も不利なセルと値をミニマックス探索で選ぶ。
+    /// `depth` はプレイヤーの手を何手先まで読むか（強さ）。局面数の上限内で読み切れる深さまでしか読まない
+    Evil { depth: u8 },
+    /// ホットシート。もう一人のプレイヤーが空きセルをクリックして位置と値を選ぶ
+    Human,
+}
+
+impl Spawner {
+    /// 盤面にタイルを 1 枚置き、置いたインデックスを返す。空きセルがなければ `None`。
+    /// `Human` は人が選ぶまで何も置かないため、常に `None` を返す。
+    pub fn place_tile<R: Rng + ?Sized>(&self, board: &mut Board, rng: &mut R) -> Option<usize> {
+        match *self {
+            Self::Random => board.place_random_tile(rng),
+            Self::Evil { depth } => {
+                let (index, exp) = Search::new(SEARCH_BOARD_BUDGET).worst_spawn(board, depth)?;
+                board[index] = Some(exp);
+                Some(index)
+            }
+            Self::Human => None,
+        }
+    }
+}
+
+fn with_tile(board: &Board, index: usize, exp: NonZero<u8>) -> Board {
+    let mut board = board.clone();
+    board[index] = Some(exp);
+    board
+}
+
+/// 出現側の候補。置いた後の盤面とその静的評価を持つ
+struct Candidate {
+    index: usize,
+    exp: NonZero<u8>,
+    board: Board,
+    value: f32,
+}
+
+struct Search {
+    /// 作って評価してよい局面の数
+    budget: usize,
+    /// これまでに作って評価した局面の数
+    evaluated: usize,
+    /// 局面数の上限に達して、読み切れなかった枝がある
+    exhausted: bool,
+}
+
+impl Search {
+    fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            evaluated: 0,
+            exhausted: false,
+        }
+    }
+
+    /// `boards` 局面を作る分を上限から差し引く。足りなければ何も引かずに `false` を返す
+    fn charge(&mut self, boards: usize) -> bool {
+        if self.evaluated + boards > self.budget {
+            self.exhausted = true;
+            return false;
+        }
+        self.evaluated += boards;
+        true
+    }
+
+    /// プレイヤーの最善応手を考慮して、評価値が最小になる出現を選ぶ。
+    /// 1 手読みから `depth` まで 1 手ずつ深くし、上限までに作れる局面を使い切ったら打ち切る。
+    /// 打ち切った深さの結果は候補の並び順に偏るので捨て、最後に読み切った深さの答えを返す
+    fn worst_spawn(&mut self, board: &Board, depth: u8) -> Option<(usize, NonZero<u8>)> {
+        // 出現候補そのものは上限に関係なく作る（空きセル数の 2 倍なので小さい）
+        self.evaluated += 2 * empty_count(board);
+        let mut candidates = spawn_candidates(board);
+        let mut best = None;
+        for depth in 1..=depth.max(1) {
+            let mut found = None;
+            let mut beta = f32::INFINITY;
+            for candidate in &candidates {
+                let value = self.player_value(
+                    &candidate.board,
+                    candidate.value,
+                    depth,
+                    f32::NEG_INFINITY,
+                    beta,
+                );
+                if value < beta {
+                    beta = value;
+                    found = Some((candidate.index, candidate.exp));
+                }
+            }
+            // 1 手読みは上限に届いても使う
+            if self.exhausted && best.is_some() {
+                break;
+            }
+            best = found;
+            if self.exhausted {
+                break;
+            }
+            // 次の深さは今の最善から読むと枝刈りが効く
+            if let Some(first) = candidates
+                .iter()
+                .position(|candidate| Some((candidate.index, candidate.exp)) == found)
+            {
+                candidates[..=first].rotate_right(1);
+            }
+        }
+        best
+    }
+
+    /// プレイヤー手番の局面の値（プレイヤーは最大化する）。
+    /// 値はこの先で得るスコアと、読み切った局面の評価値の和。`value` はこの局面の静的評価
+    fn player_value(
+        &mut self,
+        board: &Board,
+        value: f32,
+        depth: u8,
+        mut alpha: f32,
+        beta: f32,
+    ) -> f32 {
+        let directions = board.topology().directions();
+        if !self.charge(directions.len()) {
+            return value;
+        }
+
+        // 得点と静的評価が高い手から読む
+        let mut moves: Vec<_> = directions
+            .iter()
+            .map(|&direction| board.compute_slide(direction))
+            .filter(|result| result.changed)
+            .map(|result| {
+                let value = evaluate(&result.new_board);
+                (result.score_gained as f32 + value, value, result)
+            })
+            .collect();
+        if moves.is_empty() {
+            return LOSS_VALUE;
+        }
+        moves.sort_by(|(a, ..), (b, ..)| b.total_cmp(a));
+
+        let mut best = f32::NEG_INFINITY;
+        for (_, value, result) in moves {
+            let gained = result.score_gained as f32;
+            let value = if depth == 1 {
+                gained + value
+            } else {
+                gained
+                    + self.spawner_value(
+                        &result.new_board,
+                        value,
+                        depth - 1,
+                        alpha - gained,
+                        beta - gained,
+                    )
+            };
+            best = best.max(value);
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    /// 出現側手番の局面の値（出現側は最小化する）。`value` はこの局面の静的評価
+    fn spawner_value(
+        &mut self,
+        board: &Board,
+        value: f32,
+        depth: u8,
+        alpha: f32,
+        mut beta: f32,
+    ) -> f32 {
+        let empty = empty_count(board);
+        if empty == 0 || !self.charge(2 * empty) {
+            return value;
+        }
+
+        let mut best = f32::INFINITY;
+        for candidate in spawn_candidates(board) {
+            let value = self.player_value(&candidate.board, candidate.value, depth, alpha, beta);
+            best = best.min(value);
+            beta = beta.min(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+}
+
+fn empty_count(board: &Board) -> usize {
+    board.iter().filter(|cell| cell.is_none()).count()
+}
+
+/// 空きセルと値のすべての組み合わせ。枝刈りが効くよう、静的評価でプレイヤーに不利なものから並べる
+fn spawn_candidates(board: &Board) -> Vec<Candidate> {
+    let mut candidates: Vec<_> = board
+        .iter()
+        .enumerate()
+        .filter(|(_, cell)| cell.is_none())
+        .flat_map(|(index, _)| [1, 2].map(|exp| (index, non_zero_exp(exp))))
+        .map(|(index, exp)| {
+            let board = with_tile(board, index, exp);
+            Candidate {
+                index,
+                exp,
+                value: evaluate(&board),
+                board,
+            }
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.value.total_cmp(&b.value));
+    candidates
+}
+
+/// 局面の静的評価。空きセルが多く、隣接タイルの値が近いほど高い
+pub(super) fn evaluate(board: &Board) -> f32 {
+    let topology = board.topology();
+    let empty = board.iter().filter(|cell| cell.is_none()).count() as f32;
+
+    let mut mergeable = 0.0;
+    let mut roughness = 0.0;
+    for index in 0..board.len() {
+        let Some(exp) = board[index] else {
+            continue;
+        };
+        for &direction in topology.directions() {
+            let Some(neighbor) = topology
+                .neighbor(index, direction)
+                .and_then(|neighbor| board[neighbor])
+            else {
+                continue;
+            };
+            if neighbor == exp {
+                mergeable += 1.0;
+            }
+            roughness += f32::from(exp.get().abs_diff(neighbor.get()));
+        }
+    }
+
+    // 隣接ペアは両側から 2 回数えているので半分にする
+    empty * 16.0 + mergeable * 4.0 - roughness / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::topology::Topology;
+
+    #[test]
+    fn evil_spawner_picks_value_that_blocks_every_move() {
+        // 市松模様に 2 と 4 を並べ、右上の 1 マスだけ空けておく。
+        // そこに 2 を置けば隣と合体できるが、4 を置くと動かせなくなる。
+        let topology = Topology::CLASSIC;
+        let mut board = Board::new(topology);
+        for index in 0..board.len() {
+            let coords = topology.coords(index);
+            let exp = if (coords.x + coords.y) % 2 == 0 { 2 } else { 1 };
+            board[index] = Some(non_zero_exp(exp));
+        }
+        let empty = topology
+            .index_of(IVec2::new(3, 3))
+            .expect("corner must be on the board");
+        board[empty] = None;
+
+        let placed = Spawner::Evil { depth: 1 }.place_tile(&mut board, &mut rand::rng());
+
+        assert_eq!(placed, Some(empty));
+        assert_eq!(board[empty], Some(non_zero_exp(2)));
+        assert!(!board.can_move());
+    }
+
+    #[test]
+    fn evil_spawner_search_is_bounded_on_open_board() {
+        let mut board = Board::new(Topology::CLASSIC);
+        board[0] = Some(non_zero_exp(1));
+
+        let placed = Spawner::Evil { depth: 3 }.place_tile(&mut board, &mut rand::rng());
+
+        assert!(placed.is_some());
+        assert_eq!(board.iter().filter(|cell| cell.is_some()).count(), 2);
+    }
+
+    #[test]
+    fn exhausted_search_falls_back_to_last_completed_depth() {
+        let mut board = Board::new(Topology::CLASSIC);
+        board[0] = Some(non_zero_exp(1));
+        board[5] = Some(non_zero_exp(2));
+
+        // 1 手読みは出現候補 14 × 2 と、その先の 4 方向ずつで 140 局面。
+        // 2 手読みは 200 局面では読み切れない
+        let shallow = Search::new(usize::MAX).worst_spawn(&board, 1);
+        assert!(shallow.is_some());
+        let mut search = Search::new(200);
+        assert_eq!(search.worst_spawn(&board, 3), shallow);
+        assert!(search.exhausted);
+    }
+
+    #[test]
+    fn search_counts_every_board_it_evaluates() {
+        let mut board = Board::new(Topology::CLASSIC);
+        board[0] = Some(non_zero_exp(1));
+        board[5] = Some(non_zero_exp(2));
+
+        let mut search = Search::new(usize::MAX);
+        search.worst_spawn(&board, 1);
+        assert_eq!(search.evaluated, 14 * 2 + 14 * 2 * 4);
+
+        // 深く読ませても、出現候補を除いて上限を超えて局面を作らない
+        for topology in [Topology::CLASSIC, Topology::HEX] {
+            let mut board = Board::new(topology);
+            board[0] = Some(non_zero_exp(1));
+            let root = 2 * (board.len() - 1);
+            let mut search = Search::new(root + 1_000);
+            assert!(search.worst_spawn(&board, 6).is_some());
+            assert!(search.exhausted);
+            assert!(search.evaluated <= root + 1_000);
+        }
+    }
+
+    #[test]
+    fn spawners_return_none_on_full_board() {
+        let mut board = Board::new(Topology::CLASSIC);
+        for cell in board.iter_mut() {
+            *cell = Some(non_zero_exp(1));
+        }
+
+        assert_eq!(
+            Spawner::Random.place_tile(&mut board, &mut rand::rng()),
+            None
+        );
+        assert_eq!(
+            Spawner::Evil { depth: 2 }.place_tile(&mut board, &mut rand::rng()),
+            None
+        );
+    }
+}
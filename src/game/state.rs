@@ -1,9 +1,33 @@
 use bevy::prelude::*;
 
 use super::animation::AnimationPhase;
-use super::board::Board;
+use super::board::{Board, Score};
+use super::input::ActionInput;
+use super::keybindings::Action;
+use super::session::{MatchConfig, Player};
+use super::ui::Press;
 
-#[derive(States, Default, Clone, PartialEq, Eq, Hash, Debug)]
+/// いま表示している画面。起動するとタイトル画面から始まる。
+/// 画面ごとの UI は `DespawnOnExit` を付けて出し、画面を離れると消える
+#[derive(States, Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(super) enum Screen {
+    #[default]
+    Title,
+    /// 人数・盤面・難易度を選んで新しいゲームを始める
+    ModeSelect,
+    Playing,
+    /// 一時停止中。盤面の更新を止めてメニューを出す
+    Paused,
+    Settings,
+    /// 設定画面から開くキー設定
+    KeyConfig,
+    Stats,
+    Replays,
+}
+
+/// 盤面の状態。`Screen::Playing` のあいだだけあり、一時停止やメニューから戻ると `Playing` から始まる
+#[derive(SubStates, Default, Clone, PartialEq, Eq, Hash, Debug)]
+#[source(Screen = Screen::Playing)]
 pub(super) enum GamePhase {
     #[default]
     Playing,
@@ -11,28 +35,123 @@ pub(super) enum GamePhase {
     GameOver,
 }
 
-#[derive(Resource, Default)]
+/// 盤面ごとの 2048 到達済みフラグ
+#[derive(Component, Default)]
 pub(super) struct HasWon(pub(super) bool);
 
+/// 対戦の勝者。ソロや引き分けでは `None`
+#[derive(Resource, Default)]
+pub(super) struct MatchOutcome {
+    pub(super) winner: Option<Player>,
+}
+
 pub(super) fn check_game_state(
-    board: Res<Board>,
-    phase: Res<AnimationPhase>,
-    mut has_won: ResMut<HasWon>,
+    config: Res<MatchConfig>,
+    mut fields: Query<(&Player, &Board, &Score, &AnimationPhase, &mut HasWon)>,
+    mut outcome: ResMut<MatchOutcome>,
     mut next_state: ResMut<NextState<GamePhase>>,
 ) {
-    if *phase != AnimationPhase::Idle {
+    // 2048 到達チェック（盤面ごとに初回のみ）
+    for (player, board, _, phase, mut has_won) in &mut fields {
+        if *phase != AnimationPhase::Idle {
+            continue;
+        }
+
+        if !has_won.0 && board.has_won() {
+            has_won.0 = true;
+            if config.mode.player_count() > 1 {
+                outcome.winner = Some(*player);
+            }
+            next_state.set(GamePhase::Won);
+            return;
+        }
+    }
+
+    // ゲームオーバーチェック（すべての盤面が動かせなくなったら終了）
+    let all_stuck = !fields.is_empty()
+        && fields
+            .iter()
+            .all(|(_, board, _, phase, _)| *phase == AnimationPhase::Idle && !board.can_move());
+    if !all_stuck {
         return;
     }
 
-    // 2048 到達チェック（初回のみ）
-    if !has_won.0 && board.iter().any(|cell| cell.map(|e| e.get()) == Some(11)) {
-        has_won.0 = true;
-        next_state.set(GamePhase::Won);
+    if config.mode.player_count() > 1 {
+        let best = fields.iter().map(|(_, _, score, _, _)| **score).max();
+        let mut leaders = fields
+            .iter()
+            .filter(|(_, _, score, _, _)| Some(***score) == best);
+        outcome.winner = match (leaders.next(), leaders.next()) {
+            (Some((player, ..)), None) => Some(*player),
+            _ => None,
+        };
+    }
+    next_state.set(GamePhase::GameOver);
+}
+
+/// 一時停止キー（ゲームパッドでは Start）でプレイ中と一時停止を切り替える
+pub(super) fn toggle_pause(
+    input: ActionInput,
+    screen: Res<State<Screen>>,
+    phase: Option<Res<State<GamePhase>>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    if !input.just_pressed(Action::Pause) {
         return;
     }
+    match screen.get() {
+        Screen::Playing if phase.is_some_and(|phase| *phase.get() == GamePhase::Playing) => {
+            next_screen.set(Screen::Paused);
+        }
+        Screen::Paused => next_screen.set(Screen::Playing),
+        _ => {}
+    }
+}
+
+/// 一時停止メニューを開く
+pub(super) fn open_pause_menu(_press: On<Press>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Paused);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::state::app::StatesPlugin;
+
+    use super::*;
+
+    fn set_screen(app: &mut App, screen: Screen) {
+        app.world_mut()
+            .resource_mut::<NextState<Screen>>()
+            .set(screen);
+        app.update();
+    }
+
+    #[test]
+    fn game_phase_only_exists_while_playing() {
+        let mut app = App::new();
+        app.add_plugins(StatesPlugin)
+            .init_state::<Screen>()
+            .add_sub_state::<GamePhase>();
+        app.update();
+        assert!(app.world().get_resource::<State<GamePhase>>().is_none());
+
+        set_screen(&mut app, Screen::Playing);
+        app.world_mut()
+            .resource_mut::<NextState<GamePhase>>()
+            .set(GamePhase::GameOver);
+        app.update();
+        assert_eq!(
+            *app.world().resource::<State<GamePhase>>().get(),
+            GamePhase::GameOver
+        );
 
-    // ゲームオーバーチェック
-    if !board.can_move() {
-        next_state.set(GamePhase::GameOver);
+        // 画面を離れると盤面の状態はなくなり、戻るとプレイ中から始まる
+        set_screen(&mut app, Screen::Paused);
+        assert!(app.world().get_resource::<State<GamePhase>>().is_none());
+        set_screen(&mut app, Screen::Playing);
+        assert_eq!(
+            *app.world().resource::<State<GamePhase>>().get(),
+            GamePhase::Playing
+        );
     }
 }
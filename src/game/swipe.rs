@@ -0,0 +1,288 @@
+//! ドラッグ / スワイプ操作。ドラッグ中は動くはずのタイルが指についてきて、
+//! 十分に動かすか素早くはじいて離すとスライドを確定する。途中で戻せば元の位置に戻る。
+//! 距離はセル単位で測るので、ウィンドウの大きさや画面の表示倍率に左右されない。
+
+use bevy::prelude::*;
+use bevy::window::RequestRedraw;
+
+use super::animation::AnimationPhase;
+use super::board::{Board, Direction};
+use super::input::{Slide, drag_direction};
+use super::render::{VisualTile, board_index_to_position, cell_step, direction_vector};
+use super::state::GamePhase;
+use super::topology::Topology;
+
+/// これだけ（セル単位）動かしたら方向を決めてプレビューを始める
+const DEAD_ZONE: f32 = 0.1;
+/// 離したときにこれだけ（セル単位）動かしていればスライドを確定する
+const COMMIT_DISTANCE: f32 = 0.5;
+/// 離す直前にこの速さ（セル / 秒）ではじいていれば、短いドラッグでも確定する
+const FLING_SPEED: f32 = 4.0;
+/// 最後に動かしてから離すまでがこれより長ければ、はじきとみなさない（秒）
+const FLING_WINDOW: f32 = 0.1;
+/// タイルが目標の位置に追いつく速さ。大きいほど機敏に追従する
+const FOLLOW_RATE: f32 = 30.0;
+/// 元の位置に戻ったとみなす距離（セル単位）
+const REST_DISTANCE: f32 = 0.01;
+
+/// 予想したスライドの結果。プレビューを作ったときの盤面を覚えておき、
+/// 盤面が変わったら（取り消しなど）捨てる
+struct Preview {
+    board: Board,
+    /// (移動元, 移動先) のボードインデックス
+    movements: Vec<(usize, usize)>,
+}
+
+impl Preview {
+    fn new(board: &Board, direction: Direction) -> Option<Self> {
+        let result = board.compute_slide(direction);
+        result.changed.then(|| Self {
+            board: board.clone(),
+            movements: result
+                .movements
+                .iter()
+                .map(|movement| (movement.from, movement.to))
+                .collect(),
+        })
+    }
+}
+
+struct ActiveSwipe {
+    field: Entity,
+    topology: Topology,
+    direction: Option<Direction>,
+    /// 盤面が止まっていないときやその方向に動かないときはプレビューしない
+    preview: Option<Preview>,
+    /// 指の位置から決めた進み具合（セル単位）
+    target: f32,
+    /// 表示中の進み具合（セル単位）
+    shown: f32,
+    /// 指を離して元の位置に戻っている途中か
+    released: bool,
+    /// 直近のドラッグ量（セル単位）と、その時刻
+    last_sample: Option<(f32, Vec2)>,
+    /// ドラッグの速さ（セル / 秒）
+    velocity: Vec2,
+}
+
+impl ActiveSwipe {
+    /// プレビュー中のタイルを進み具合 `progress` の位置に置く。
+    /// 指と同じだけ動かし、移動先より先には行かない
+    fn place_tiles(
+        &self,
+        progress: f32,
+        tiles: &mut Query<(&VisualTile, &ChildOf, &mut Transform)>,
+    ) {
+        let Some(preview) = &self.preview else {
+            return;
+        };
+        let distance = progress * cell_step(self.topology);
+        for (tile, child_of, mut transform) in tiles {
+            if child_of.parent() != self.field {
+                continue;
+            }
+            let Some(&(from, to)) = preview
+                .movements
+                .iter()
+                .find(|(from, _)| *from == tile.board_index)
+            else {
+                continue;
+            };
+            let from = board_index_to_position(self.topology, from);
+            let to = board_index_to_position(self.topology, to);
+            let pos = from + (to - from).clamp_length_max(distance);
+            transform.translation.x = pos.x;
+            transform.translation.y = pos.y;
+        }
+    }
+}
+
+/// 進行中のスワイプ
+#[derive(Resource, Default)]
+pub(super) struct Swipe(Option<ActiveSwipe>);
+
+/// ポインター位置のワールド座標
+fn to_world(cameras: &Query<(&Camera, &GlobalTransform)>, position: Vec2) -> Option<Vec2> {
+    cameras
+        .iter()
+        .find_map(|(camera, transform)| camera.viewport_to_world_2d(transform, position).ok())
+}
+
+/// 盤面の上で始まったドラッグだけをスワイプとして扱う。
+/// ボタンやオーバーレイなどの UI から始まったドラッグでは動かさない
+pub(super) fn on_swipe_start(
+    mut drag: On<Pointer<DragStart>>,
+    phase: Option<Res<State<GamePhase>>>,
+    ui: Query<(), With<Node>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    fields: Query<(Entity, &GlobalTransform, &Board)>,
+    mut swipe: ResMut<Swipe>,
+    mut tiles: Query<(&VisualTile, &ChildOf, &mut Transform)>,
+) {
+    // セル背景から親の盤面・ウィンドウへ伝播して同じドラッグを何度も処理しないようにする
+    drag.propagate(false);
+    if drag.button != PointerButton::Primary
+        || !phase.is_some_and(|phase| *phase.get() == GamePhase::Playing)
+        || ui.contains(drag.entity)
+    {
+        return;
+    }
+
+    // 前のスワイプのタイルが戻りきっていなければ元の位置に置く。
+    // 離されていないスワイプが残っていても、終わりの通知を取りこぼしたものとみなして置き換える
+    if let Some(active) = swipe.0.take() {
+        active.place_tiles(0.0, &mut tiles);
+    }
+
+    // ドラッグ開始位置に最も近い盤面を動かす
+    let start_world = to_world(&cameras, drag.pointer_location.position);
+    let field = fields.iter().min_by(|(_, a, _), (_, b, _)| {
+        let distance = |transform: &GlobalTransform| {
+            start_world.map_or(0.0, |start| (transform.translation().x - start.x).abs())
+        };
+        distance(a).total_cmp(&distance(b))
+    });
+    swipe.0 = field.map(|(field, _, board)| ActiveSwipe {
+        field,
+        topology: board.topology(),
+        direction: None,
+        preview: None,
+        target: 0.0,
+        shown: 0.0,
+        released: false,
+        last_sample: None,
+        velocity: Vec2::ZERO,
+    });
+}
+
+/// ドラッグ量から方向と進み具合を決める。開始位置より手前まで戻したら方向を選び直す
+pub(super) fn on_swipe_drag(
+    mut drag: On<Pointer<Drag>>,
+    time: Res<Time>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    fields: Query<(&Board, &AnimationPhase)>,
+    mut swipe: ResMut<Swipe>,
+    mut tiles: Query<(&VisualTile, &ChildOf, &mut Transform)>,
+) {
+    drag.propagate(false);
+    if drag.button != PointerButton::Primary {
+        return;
+    }
+    let Some(active) = swipe.0.as_mut().filter(|active| !active.released) else {
+        return;
+    };
+    let position = drag.pointer_location.position;
+    let (Some(start), Some(current)) = (
+        to_world(&cameras, position - drag.distance),
+        to_world(&cameras, position),
+    ) else {
+        return;
+    };
+    let offset = (current - start) / cell_step(active.topology);
+
+    let now = time.elapsed_secs();
+    if let Some((last_time, last_offset)) = active.last_sample
+        && now > last_time
+    {
+        let velocity = (offset - last_offset) / (now - last_time);
+        active.velocity = active.velocity.lerp(velocity, 0.5);
+    }
+    active.last_sample = Some((now, offset));
+
+    let along = |direction: Option<Direction>| {
+        direction.map_or(0.0, |direction| offset.dot(direction_vector(direction)))
+    };
+    if along(active.direction) <= 0.0 && offset.length() >= DEAD_ZONE {
+        let direction = drag_direction(active.topology, offset);
+        if direction != active.direction {
+            active.place_tiles(0.0, &mut tiles);
+            active.shown = 0.0;
+            active.direction = direction;
+            active.preview = fields
+                .get(active.field)
+                .ok()
+                .filter(|(_, phase)| **phase == AnimationPhase::Idle)
+                .zip(direction)
+                .and_then(|((board, _), direction)| Preview::new(board, direction));
+        }
+    }
+    active.target = along(active.direction).max(0.0);
+}
+
+/// 十分に動かしたかはじいて離したらスライドを確定し、そうでなければ元に戻す
+pub(super) fn on_swipe_end(
+    mut drag_end: On<Pointer<DragEnd>>,
+    time: Res<Time>,
+    phase: Option<Res<State<GamePhase>>>,
+    mut swipe: ResMut<Swipe>,
+    mut slides: MessageWriter<Slide>,
+) {
+    drag_end.propagate(false);
+    if drag_end.button != PointerButton::Primary {
+        return;
+    }
+    let Some(active) = swipe.0.as_mut().filter(|active| !active.released) else {
+        return;
+    };
+
+    if phase.is_some_and(|phase| *phase.get() == GamePhase::Playing)
+        && let Some(direction) = active.direction
+    {
+        let recent = active
+            .last_sample
+            .is_some_and(|(last_time, _)| time.elapsed_secs() - last_time <= FLING_WINDOW);
+        let flung = recent
+            && active.target > 0.0
+            && active.velocity.dot(direction_vector(direction)) >= FLING_SPEED;
+        if active.target >= COMMIT_DISTANCE || flung {
+            // タイルは今の位置からスライドアニメーションで移動先へ向かう
+            slides.write(Slide {
+                field: active.field,
+                direction,
+            });
+            swipe.0 = None;
+            return;
+        }
+    }
+
+    active.released = true;
+    active.target = 0.0;
+}
+
+/// プレビュー中のタイルを指の位置へ追従させ、離したあとは元の位置へ戻す
+pub(super) fn follow_swipe(
+    time: Res<Time>,
+    mut swipe: ResMut<Swipe>,
+    fields: Query<(&Board, &AnimationPhase)>,
+    mut tiles: Query<(&VisualTile, &ChildOf, &mut Transform)>,
+    mut redraw: MessageWriter<RequestRedraw>,
+) {
+    let Some(active) = swipe.0.as_mut() else {
+        return;
+    };
+
+    // ほかの入力で盤面が動いたらスワイプをやめる。タイルはもうその盤面のものではなく、
+    // 動かしていたタイルが合体で消えると、このドラッグの終わりの通知は届かないこともある
+    let stale = active.preview.as_ref().is_some_and(|preview| {
+        !fields
+            .get(active.field)
+            .is_ok_and(|(board, phase)| *phase == AnimationPhase::Idle && *board == preview.board)
+    });
+    if stale || (active.released && active.preview.is_none()) {
+        swipe.0 = None;
+        return;
+    }
+
+    let follow = 1.0 - (-FOLLOW_RATE * time.delta_secs()).exp();
+    active.shown += (active.target - active.shown) * follow;
+    if active.released && active.shown < REST_DISTANCE {
+        active.place_tiles(0.0, &mut tiles);
+        swipe.0 = None;
+        return;
+    }
+
+    active.place_tiles(active.shown, &mut tiles);
+    if (active.target - active.shown).abs() >= REST_DISTANCE {
+        redraw.write(RequestRedraw);
+    }
+}
@@ -0,0 +1,431 @@
+//! 小さな盤面（2×2・2×3・3×3 など）の完全解析テーブル。
+//! 到達可能なすべての局面について、出現タイルの分布（90% で 2、10% で 4）のもとで
+//! 最善を尽くしたときの期待スコアと、目標タイルに到達する確率を求める。
+//! 2 つの値はそれぞれ別の最善方針（スコア最大化・到達確率最大化）での値になる。
+
+use std::io::{self, Read, Write};
+use std::num::NonZero;
+
+use super::board::{Board, Direction, non_zero_exp};
+use super::topology::Topology;
+
+const MAGIC: &[u8; 8] = b"2048TBL1";
+/// 1 局面を u64 に詰めるため、セル数と指数の上限を決めておく
+const MAX_CELLS: usize = 16;
+const MAX_EXP: u8 = 15;
+/// 2 が出る確率（`Board::place_random_tile` と同じ 90%）
+const TWO_PROBABILITY: f64 = 0.9;
+
+/// 最善手を選ぶときの目的
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Objective {
+    Score,
+    Win,
+}
+
+/// 局面（プレイヤーの手番）の値
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Solution {
+    /// この局面から得られる追加スコアの期待値
+    pub expected_score: f64,
+    /// 目標タイルに到達する確率
+    pub win_probability: f64,
+}
+
+/// 盤面を 1 セル 4 ビットで u64 に詰める
+fn encode(board: &Board) -> u64 {
+    board.iter().enumerate().fold(0, |key, (index, cell)| {
+        let exp = cell.map_or(0, NonZero::get);
+        key | u64::from(exp) << (4 * index)
+    })
+}
+
+fn decode(topology: Topology, key: u64) -> Board {
+    let mut board = Board::new(topology);
+    for index in 0..board.len() {
+        let exp = (key >> (4 * index) & 0xf) as u8;
+        if exp != 0 {
+            board[index] = Some(non_zero_exp(exp));
+        }
+    }
+    board
+}
+
+fn reached(board: &Board, target: u8) -> bool {
+    board.iter().flatten().any(|exp| exp.get() >= target)
+}
+
+/// 空きセルに 2 / 4 が出る全パターンと、その確率
+fn spawns(board: &Board) -> Vec<(Board, f64)> {
+    let empty: Vec<usize> = (0..board.len()).filter(|&i| board[i].is_none()).collect();
+    let share = 1.0 / empty.len() as f64;
+    empty
+        .into_iter()
+        .flat_map(|index| {
+            [(1, TWO_PROBABILITY), (2, 1.0 - TWO_PROBABILITY)].map(|(exp, probability)| {
+                let mut next = board.clone();
+                next[index] = Some(non_zero_exp(exp));
+                (next, share * probability)
+            })
+        })
+        .collect()
+}
+
+/// 到達可能な全局面の値。局面のキーで昇順に並べて持つ
+pub struct Tablebase {
+    topology: Topology,
+    target: u8,
+    keys: Vec<u64>,
+    values: Vec<(f32, f32)>,
+}
+
+impl Tablebase {
+    /// `target` は目標タイルの指数（2048 なら 11）
+    pub fn solve(topology: Topology, target: u8) -> Self {
+        assert!(
+            matches!(topology, Topology::Grid { .. }) && topology.cell_count() <= MAX_CELLS,
+            "tablebases are only built for small grid boards"
+        );
+
+        // 盤面の合計は 1 手ごとに 2 か 4 だけ増える。合計ごとの層に分けて、
+        // 前向きに到達局面を集めてから後ろ向きに値を求める
+        let mut layers: Vec<Vec<u64>> = Vec::new();
+        for (board, _) in start_positions(topology) {
+            push_to_layer(&mut layers, &board);
+        }
+        let mut sum = 0;
+        while sum < layers.len() {
+            let mut layer = std::mem::take(&mut layers[sum]);
+            layer.sort_unstable();
+            layer.dedup();
+            for &key in &layer {
+                let board = decode(topology, key);
+                for &direction in topology.directions() {
+                    let result = board.compute_slide(direction);
+                    if result.changed {
+                        for (next, _) in spawns(&result.new_board) {
+                            push_to_layer(&mut layers, &next);
+                        }
+                    }
+                }
+            }
+            layers[sum] = layer;
+            sum += 1;
+        }
+
+        let mut values: Vec<Vec<(f32, f32)>> = vec![Vec::new(); layers.len()];
+        for sum in (0..layers.len()).rev() {
+            values[sum] = layers[sum]
+                .iter()
+                .map(|&key| {
+                    let solution = solve_position(&decode(topology, key), target, |next| {
+                        let layer = layer_of(next);
+                        let index = layers[layer]
+                            .binary_search(&encode(next))
+                            .expect("successor was enumerated");
+                        values[layer][index]
+                    });
+                    (
+                        solution.expected_score as f32,
+                        solution.win_probability as f32,
+                    )
+                })
+                .collect();
+        }
+
+        let total = layers.iter().map(Vec::len).sum();
+        let mut entries: Vec<(u64, (f32, f32))> = Vec::with_capacity(total);
+        for (keys, values) in layers.into_iter().zip(values) {
+            entries.extend(keys.into_iter().zip(values));
+        }
+        entries.sort_unstable_by_key(|(key, _)| *key);
+        let (keys, values) = entries.into_iter().unzip();
+        Self {
+            topology,
+            target,
+            keys,
+            values,
+        }
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn target(&self) -> u8 {
+        self.target
+    }
+
+    /// 収録している局面数
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// プレイヤー手番の局面の値。収録外の局面なら `None`
+    pub fn lookup(&self, board: &Board) -> Option<Solution> {
+        if board.topology() != self.topology {
+            return None;
+        }
+        let index = self.keys.binary_search(&encode(board)).ok()?;
+        let (score, win) = self.values[index];
+        Some(Solution {
+            expected_score: f64::from(score),
+            win_probability: f64::from(win),
+        })
+    }
+
+    /// 新しいゲーム開始時点での値（最初の 2 枚の出方で平均したもの）
+    pub fn start_value(&self) -> Option<Solution> {
+        start_positions(self.topology).into_iter().try_fold(
+            Solution::default(),
+            |total, (board, p)| {
+                let value = self.lookup(&board)?;
+                Some(Solution {
+                    expected_score: total.expected_score + p * value.expected_score,
+                    win_probability: total.win_probability + p * value.win_probability,
+                })
+            },
+        )
+    }
+
+    /// スライド後（出現前）の盤面の値
+    fn afterstate(&self, board: &Board) -> Option<Solution> {
+        spawns(board)
+            .into_iter()
+            .try_fold(Solution::default(), |total, (next, p)| {
+                let value = self.lookup(&next)?;
+                Some(Solution {
+                    expected_score: total.expected_score + p * value.expected_score,
+                    win_probability: total.win_probability + p * value.win_probability,
+                })
+            })
+    }
+
+    /// `objective` について最善の手。収録外の局面や動かせない局面では `None`
+    pub fn best_move(&self, board: &Board, objective: Objective) -> Option<Direction> {
+        let mut best: Option<(Direction, f64)> = None;
+        for &direction in board.topology().directions() {
+            let result = board.compute_slide(direction);
+            if !result.changed {
+                continue;
+            }
+            let after = self.afterstate(&result.new_board)?;
+            let value = match objective {
+                Objective::Score => f64::from(result.score_gained) + after.expected_score,
+                Objective::Win => after.win_probability,
+            };
+            if best.is_none_or(|(_, best)| value > best) {
+                best = Some((direction, value));
+            }
+        }
+        best.map(|(direction, _)| direction)
+    }
+
+    /// キーは差分を LEB128 の可変長整数で、値は f32 の組で書き出す
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let Topology::Grid { width, height } = self.topology else {
+            unreachable!("tablebases are only built for grid boards");
+        };
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[width as u8, height as u8, self.target])?;
+        writer.write_all(&(self.keys.len() as u64).to_le_bytes())?;
+
+        let mut previous = 0;
+        for (&key, &(score, win)) in self.keys.iter().zip(&self.values) {
+            let mut delta = key - previous;
+            previous = key;
+            loop {
+                let byte = (delta & 0x7f) as u8;
+                delta >>= 7;
+                if delta == 0 {
+                    writer.write_all(&[byte])?;
+                    break;
+                }
+                writer.write_all(&[byte | 0x80])?;
+            }
+            writer.write_all(&score.to_le_bytes())?;
+            writer.write_all(&win.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        fn invalid(message: &str) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+        }
+        fn read_f32(reader: &mut impl Read) -> io::Result<f32> {
+            let mut bytes = [0; 4];
+            reader.read_exact(&mut bytes)?;
+            Ok(f32::from_le_bytes(bytes))
+        }
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a tablebase file"));
+        }
+        let mut header = [0; 3];
+        reader.read_exact(&mut header)?;
+        let [width, height, target] = header.map(usize::from);
+        if width == 0 || height == 0 || width * height > MAX_CELLS || target > usize::from(MAX_EXP)
+        {
+            return Err(invalid("invalid tablebase header"));
+        }
+        let mut count = [0; 8];
+        reader.read_exact(&mut count)?;
+        let count = u64::from_le_bytes(count) as usize;
+
+        let mut keys = Vec::with_capacity(count.min(1 << 24));
+        let mut values = Vec::with_capacity(count.min(1 << 24));
+        let mut previous: u64 = 0;
+        for _ in 0..count {
+            let mut delta: u64 = 0;
+            for shift in (0..64).step_by(7) {
+                let mut byte = [0];
+                reader.read_exact(&mut byte)?;
+                delta |= u64::from(byte[0] & 0x7f) << shift;
+                if byte[0] & 0x80 == 0 {
+                    break;
+                }
+            }
+            previous = previous
+                .checked_add(delta)
+                .ok_or_else(|| invalid("tablebase keys overflow"))?;
+            keys.push(previous);
+            values.push((read_f32(reader)?, read_f32(reader)?));
+        }
+
+        Ok(Self {
+            topology: Topology::Grid { width, height },
+            target: target as u8,
+            keys,
+            values,
+        })
+    }
+}
+
+/// 新しいゲームの最初の 2 枚の置き方と、その確率
+fn start_positions(topology: Topology) -> Vec<(Board, f64)> {
+    spawns(&Board::new(topology))
+        .into_iter()
+        .flat_map(|(first, p)| {
+            spawns(&first)
+                .into_iter()
+                .map(move |(second, q)| (second, p * q))
+        })
+        .collect()
+}
+
+/// 合計値の半分を層の番号にする（出現タイルは 2 か 4 なので 1 手で 1 か 2 層進む）
+fn layer_of(board: &Board) -> usize {
+    board.iter().flatten().map(|exp| 1 << (exp.get() - 1)).sum()
+}
+
+fn push_to_layer(layers: &mut Vec<Vec<u64>>, board: &Board) {
+    assert!(
+        board.iter().flatten().all(|exp| exp.get() <= MAX_EXP),
+        "tile exponent does not fit in a tablebase key"
+    );
+    let sum = layer_of(board);
+    if layers.len() <= sum {
+        layers.resize_with(sum + 1, Vec::new);
+    }
+    let layer = &mut layers[sum];
+    layer.push(encode(board));
+    // 同じ局面が何度も現れるので、ときどき重複を取り除いてメモリを抑える
+    if layer.len() == layer.capacity() && layer.len() >= 1 << 16 {
+        layer.sort_unstable();
+        layer.dedup();
+    }
+}
+
+/// 次の局面の値 `value_of` から、プレイヤー手番の局面の値を求める
+fn solve_position(
+    board: &Board,
+    target: u8,
+    mut value_of: impl FnMut(&Board) -> (f32, f32),
+) -> Solution {
+    let won = reached(board, target);
+    let mut best = Solution {
+        expected_score: 0.0,
+        win_probability: if won { 1.0 } else { 0.0 },
+    };
+    for &direction in board.topology().directions() {
+        let result = board.compute_slide(direction);
+        if !result.changed {
+            continue;
+        }
+
+        let mut after = Solution::default();
+        for (next, p) in spawns(&result.new_board) {
+            let (score, win) = value_of(&next);
+            after.expected_score += p * f64::from(score);
+            after.win_probability += p * f64::from(win);
+        }
+
+        best.expected_score = best
+            .expected_score
+            .max(f64::from(result.score_gained) + after.expected_score);
+        if !won {
+            best.win_probability = best.win_probability.max(after.win_probability);
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TINY: Topology = Topology::Grid {
+        width: 2,
+        height: 2,
+    };
+
+    #[test]
+    fn stuck_board_is_worth_nothing() {
+        let table = Tablebase::solve(TINY, 4);
+        let mut board = Board::new(TINY);
+        for (index, exp) in [1, 2, 2, 1].into_iter().enumerate() {
+            board[index] = Some(non_zero_exp(exp));
+        }
+        assert!(!board.can_move());
+
+        // 詰み局面は開始局面から到達できるので収録されている
+        let value = table.lookup(&board).expect("stuck board is reachable");
+        assert_eq!(value.expected_score, 0.0);
+        assert_eq!(value.win_probability, 0.0);
+        assert_eq!(table.best_move(&board, Objective::Score), None);
+    }
+
+    #[test]
+    fn tiny_board_values_are_consistent() {
+        let table = Tablebase::solve(TINY, 5);
+        let start = table.start_value().expect("start positions are solved");
+
+        assert!(start.expected_score > 0.0);
+        assert!((0.0..=1.0).contains(&start.win_probability));
+        // 目標を上げると到達確率は下がる
+        let harder = Tablebase::solve(TINY, 6).start_value().unwrap();
+        assert!(harder.win_probability <= start.win_probability);
+        assert_eq!(harder.expected_score, start.expected_score);
+    }
+
+    #[test]
+    fn tablebase_round_trips_through_binary_file() {
+        let table = Tablebase::solve(TINY, 5);
+        let mut bytes = Vec::new();
+        table.write_to(&mut bytes).unwrap();
+        let loaded = Tablebase::read_from(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(loaded.len(), table.len());
+        assert_eq!(loaded.target(), 5);
+        assert_eq!(loaded.keys, table.keys);
+        assert_eq!(loaded.values, table.values);
+        assert!(Tablebase::read_from(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,14 @@
+//! テストで共有する補助関数
+
+use bevy::prelude::*;
+
+/// 2 つの色が 8 ビットに丸めた誤差の範囲で等しいことを確かめる
+pub(super) fn assert_close(a: Color, b: Color) {
+    let (a, b) = (a.to_srgba(), b.to_srgba());
+    let diff = (a.red - b.red)
+        .abs()
+        .max((a.green - b.green).abs())
+        .max((a.blue - b.blue).abs())
+        .max((a.alpha - b.alpha).abs());
+    assert!(diff < 2.0 / 255.0, "{a:?} != {b:?}");
+}
@@ -0,0 +1,459 @@
+//! 見た目のテーマ。色・フォント・タイルの大きさと隙間を `assets/themes/*.theme.ron` から読み込む。
+//! `file_watcher` を有効にして起動していれば、ファイルを保存したその場でタイル・セル・UI が塗り直される。
+//! テーマごとにライト版とダーク版があり、配色の設定（ライト / ダーク / OS に合わせる）で選ぶ。
+
+use std::num::NonZero;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use bevy::window::{WindowTheme, WindowThemeChanged};
+use serde::Deserialize;
+
+use super::GameFont;
+use super::accessibility::TileAccessibility;
+use super::board::exp_to_value;
+use super::render::{COLOR_BEYOND, beyond_color, text_color, tile_color};
+use super::ui::Press;
+
+/// ヘッダーのボタンで順に切り替えるテーマの (ライト版, ダーク版)。先頭が既定
+const THEME_PATHS: &[(&str, &str)] = &[
+    ("themes/classic.theme.ron", "themes/classic-dark.theme.ron"),
+    ("themes/mint.theme.ron", "themes/mint-dark.theme.ron"),
+];
+
+/// 配色の設定。`GamePlugin` を追加する前にリソースとして挿入すれば既定値を変えられる
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+    /// OS のダークモードの設定に合わせる
+    #[default]
+    System,
+}
+
+impl ColorScheme {
+    pub(super) fn label(self) -> &'static str {
+        match self {
+            Self::Light => "Light",
+            Self::Dark => "Dark",
+            Self::System => "Auto",
+        }
+    }
+
+    fn is_dark(self, system: SystemDarkMode) -> bool {
+        match self {
+            Self::Light => false,
+            Self::Dark => true,
+            Self::System => system.0.unwrap_or(false),
+        }
+    }
+}
+
+/// OS がダークモードか。ウィンドウができるまでは分からない
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(super) struct SystemDarkMode(Option<bool>);
+
+/// 現在のテーマ。テーマファイルを読み込むまでは組み込みの Classic を使う
+#[derive(Resource, Clone, Debug)]
+pub(super) struct Theme {
+    pub(super) name: String,
+    /// タイルの大きさと隙間。盤面はカメラに合わせて伸び縮みするので、効くのは両者の比だけ
+    pub(super) tile_size: f32,
+    pub(super) tile_gap: f32,
+    pub(super) background: Color,
+    pub(super) board: Color,
+    pub(super) empty_cell: Color,
+    /// ホットシートでタイルを置けるセルの色
+    pub(super) placement_target: Color,
+    /// 2, 4, 8, … の順に並べたタイルの色
+    pub(super) tiles: Vec<Color>,
+    /// `tiles` より大きな値のタイルの色
+    pub(super) tile_beyond: Color,
+    pub(super) text_dark: Color,
+    pub(super) text_light: Color,
+    /// この値以下のタイルは暗い文字で書く
+    pub(super) dark_text_up_to: u32,
+    pub(super) score: Color,
+    /// 大きく稼いだときのスコアの色
+    pub(super) highlight: Color,
+    pub(super) button: Color,
+    pub(super) button_hover: Color,
+    pub(super) button_text: Color,
+    pub(super) overlay: Color,
+    pub(super) overlay_text: Color,
+    /// キー設定などのパネルの背景
+    pub(super) panel: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            name: "Classic".to_string(),
+            tile_size: 100.0,
+            tile_gap: 10.0,
+            background: Color::srgb(0.98, 0.97, 0.94),
+            board: Color::srgb(0.733, 0.678, 0.627),
+            empty_cell: tile_color(None),
+            placement_target: Color::srgb(0.902, 0.851, 0.620),
+            tiles: (1..=11).map(|exp| tile_color(NonZero::new(exp))).collect(),
+            tile_beyond: COLOR_BEYOND,
+            text_dark: text_color(NonZero::new(1)),
+            text_light: text_color(NonZero::new(3)),
+            dark_text_up_to: 4,
+            score: Color::srgb(0.467, 0.431, 0.396),
+            highlight: Color::srgb(0.929, 0.694, 0.149),
+            button: Color::srgb(0.557, 0.494, 0.439),
+            button_hover: Color::srgb(0.647, 0.584, 0.529),
+            button_text: Color::WHITE,
+            overlay: Color::srgba(0.0, 0.0, 0.0, 0.5),
+            overlay_text: Color::WHITE,
+            panel: Color::srgba(0.2, 0.18, 0.16, 0.95),
+        }
+    }
+}
+
+impl Theme {
+    pub(super) fn tile(&self, exp: NonZero<u8>) -> Color {
+        self.tiles
+            .get(exp.get() as usize - 1)
+            .copied()
+            .unwrap_or_else(|| {
+                beyond_color(
+                    self.tile_beyond,
+                    (exp.get() as usize - self.tiles.len() - 1) as u32,
+                )
+            })
+    }
+
+    pub(super) fn text(&self, exp: NonZero<u8>) -> Color {
+        if exp_to_value(exp.get()) <= self.dark_text_up_to {
+            self.text_dark
+        } else {
+            self.text_light
+        }
+    }
+
+    fn ui(&self, role: UiColor) -> Color {
+        match role {
+            UiColor::Button => self.button,
+            UiColor::ButtonText => self.button_text,
+            UiColor::Score => self.score,
+            UiColor::Overlay => self.overlay,
+            UiColor::OverlayText => self.overlay_text,
+            UiColor::OverlaySubtext => self.overlay_text.with_alpha(0.8),
+            UiColor::Panel => self.panel,
+            UiColor::Background => self.background,
+        }
+    }
+}
+
+/// UI の色の役割。テーマが変わるとこの役割の色で塗り直す
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum UiColor {
+    Button,
+    ButtonText,
+    Score,
+    Overlay,
+    OverlayText,
+    OverlaySubtext,
+    Panel,
+    /// 盤面を隠す画面の背景。ウィンドウの背景と同じ色
+    Background,
+}
+
+impl UiColor {
+    /// 背景色（`BackgroundColor`）の役割か。そうでなければ文字色（`TextColor`）
+    fn is_background(self) -> bool {
+        matches!(
+            self,
+            Self::Button | Self::Overlay | Self::Panel | Self::Background
+        )
+    }
+}
+
+/// テーマファイルの中身。色は `"#rrggbb"` か `"#rrggbbaa"` で書く
+#[derive(Deserialize)]
+struct ThemeFile {
+    name: String,
+    /// アセットフォルダからの相対パス。省略すると今のフォントのまま
+    #[serde(default)]
+    font: Option<String>,
+    tile_size: f32,
+    tile_gap: f32,
+    background: HexColor,
+    board: HexColor,
+    empty_cell: HexColor,
+    placement_target: HexColor,
+    tiles: Vec<HexColor>,
+    tile_beyond: HexColor,
+    text_dark: HexColor,
+    text_light: HexColor,
+    dark_text_up_to: u32,
+    score: HexColor,
+    highlight: HexColor,
+    button: HexColor,
+    button_hover: HexColor,
+    button_text: HexColor,
+    overlay: HexColor,
+    overlay_text: HexColor,
+    panel: HexColor,
+}
+
+#[derive(Deserialize)]
+#[serde(try_from = "String")]
+struct HexColor(Color);
+
+impl TryFrom<String> for HexColor {
+    type Error = String;
+
+    fn try_from(hex: String) -> Result<Self, Self::Error> {
+        Srgba::hex(&hex)
+            .map(|color| Self(color.into()))
+            .map_err(|err| format!("{hex}: {err}"))
+    }
+}
+
+impl ThemeFile {
+    fn into_theme(self) -> Result<Theme, String> {
+        if !(self.tile_size > 0.0 && self.tile_gap >= 0.0) {
+            return Err("tile_size must be positive and tile_gap non-negative".to_string());
+        }
+        if self.tiles.is_empty() {
+            return Err("tiles must list at least one color".to_string());
+        }
+        Ok(Theme {
+            name: self.name,
+            tile_size: self.tile_size,
+            tile_gap: self.tile_gap,
+            background: self.background.0,
+            board: self.board.0,
+            empty_cell: self.empty_cell.0,
+            placement_target: self.placement_target.0,
+            tiles: self.tiles.into_iter().map(|color| color.0).collect(),
+            tile_beyond: self.tile_beyond.0,
+            text_dark: self.text_dark.0,
+            text_light: self.text_light.0,
+            dark_text_up_to: self.dark_text_up_to,
+            score: self.score.0,
+            highlight: self.highlight.0,
+            button: self.button.0,
+            button_hover: self.button_hover.0,
+            button_text: self.button_text.0,
+            overlay: self.overlay.0,
+            overlay_text: self.overlay_text.0,
+            panel: self.panel.0,
+        })
+    }
+}
+
+fn parse_theme(bytes: &[u8]) -> Result<(Theme, Option<String>), String> {
+    let mut file: ThemeFile = ron::de::from_bytes(bytes).map_err(|err| err.to_string())?;
+    let font = file.font.take();
+    file.into_theme().map(|theme| (theme, font))
+}
+
+/// テーマファイル 1 つ分
+#[derive(Asset, TypePath)]
+pub(super) struct ThemeAsset {
+    theme: Theme,
+    font: Option<Handle<Font>>,
+}
+
+#[derive(Default, TypePath)]
+pub(super) struct ThemeLoader;
+
+impl AssetLoader for ThemeLoader {
+    type Asset = ThemeAsset;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let (theme, font) = parse_theme(&bytes)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        Ok(ThemeAsset {
+            theme,
+            font: font.map(|path| load_context.load(path)),
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["theme.ron"]
+    }
+}
+
+/// 選べるテーマ（ライト版とダーク版の組）と、選んでいるテーマ
+#[derive(Resource)]
+pub(super) struct Themes {
+    handles: Vec<[Handle<ThemeAsset>; 2]>,
+    current: usize,
+}
+
+pub(super) fn load_themes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Themes {
+        handles: THEME_PATHS
+            .iter()
+            .map(|(light, dark)| [asset_server.load(*light), asset_server.load(*dark)])
+            .collect(),
+        current: 0,
+    });
+}
+
+/// 次のテーマに切り替える
+pub(super) fn cycle_theme(_press: On<Press>, mut themes: ResMut<Themes>) {
+    themes.current = (themes.current + 1) % themes.handles.len();
+}
+
+/// ライト → ダーク → OS に合わせる の順に配色を切り替える
+pub(super) fn cycle_color_scheme(_press: On<Press>, mut scheme: ResMut<ColorScheme>) {
+    *scheme = match *scheme {
+        ColorScheme::Light => ColorScheme::Dark,
+        ColorScheme::Dark => ColorScheme::System,
+        ColorScheme::System => ColorScheme::Light,
+    };
+}
+
+/// OS のダークモードの設定を追う。ウィンドウを作ったときに入る `window_theme` を最初の値にし、
+/// その後は OS からの変更の通知に従う
+pub(super) fn track_system_dark_mode(
+    mut changes: MessageReader<WindowThemeChanged>,
+    windows: Query<&Window>,
+    mut system: ResMut<SystemDarkMode>,
+) {
+    let mut dark = system.0.or_else(|| {
+        windows
+            .iter()
+            .find_map(|window| window.window_theme)
+            .map(|theme| theme == WindowTheme::Dark)
+    });
+    if let Some(change) = changes.read().last() {
+        dark = Some(change.theme == WindowTheme::Dark);
+    }
+    system.set_if_neq(SystemDarkMode(dark));
+}
+
+/// 選んでいるテーマの読み込みが終わったときや書き換えられたとき、
+/// 配色の設定や OS のダークモード、タイルの配色が変わったときに、現在のテーマを差し替える
+pub(super) fn sync_theme(
+    mut events: MessageReader<AssetEvent<ThemeAsset>>,
+    themes: Res<Themes>,
+    scheme: Res<ColorScheme>,
+    system: Res<SystemDarkMode>,
+    accessibility: Res<TileAccessibility>,
+    assets: Res<Assets<ThemeAsset>>,
+    mut theme: ResMut<Theme>,
+    mut font: ResMut<GameFont>,
+) {
+    let [light, dark] = &themes.handles[themes.current];
+    let handle = if scheme.is_dark(*system) { dark } else { light };
+    let updated = events
+        .read()
+        .any(|event| event.is_loaded_with_dependencies(handle) || event.is_modified(handle));
+    let settings_changed = themes.is_changed()
+        || scheme.is_changed()
+        || system.is_changed()
+        || accessibility.is_changed();
+    if !updated && !settings_changed {
+        return;
+    }
+    let Some(asset) = assets.get(handle) else {
+        return;
+    };
+
+    let mut next = asset.theme.clone();
+    accessibility.apply(&mut next);
+    *theme = next;
+    if let Some(handle) = &asset.font
+        && *handle != font.0
+    {
+        font.0 = handle.clone();
+    }
+}
+
+/// 役割に合わせて UI を塗る。新しく出した UI はその場で、テーマが変わったらすべて塗り直す
+pub(super) fn paint_ui(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    nodes: Query<(Entity, Ref<UiColor>)>,
+) {
+    for (entity, role) in &nodes {
+        if !theme.is_changed() && !role.is_added() {
+            continue;
+        }
+        let color = theme.ui(*role);
+        if role.is_background() {
+            commands.entity(entity).insert(BackgroundColor(color));
+        } else {
+            commands.entity(entity).insert(TextColor(color));
+        }
+    }
+}
+
+/// テーマのフォントが変わったら、すでにある文字もそのフォントに差し替える
+pub(super) fn apply_theme_font(font: Res<GameFont>, mut texts: Query<&mut TextFont>) {
+    for mut text in &mut texts {
+        text.font = font.0.clone().into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::testing::assert_close;
+
+    #[test]
+    fn classic_theme_file_matches_builtin_theme() {
+        let bytes = include_bytes!("../../assets/themes/classic.theme.ron");
+        let (theme, font) = parse_theme(bytes).unwrap();
+        let builtin = Theme::default();
+
+        assert_eq!(theme.name, builtin.name);
+        assert_eq!(font.as_deref(), Some("fonts/DotGothic16-Regular.ttf"));
+        assert_eq!(theme.tiles.len(), builtin.tiles.len());
+        for (a, b) in theme.tiles.iter().zip(&builtin.tiles) {
+            assert_close(*a, *b);
+        }
+        for exp in 1..=12 {
+            let exp = NonZero::new(exp).unwrap();
+            assert_close(theme.tile(exp), builtin.tile(exp));
+            assert_close(theme.text(exp), builtin.text(exp));
+        }
+        assert_close(theme.overlay, builtin.overlay);
+        assert_close(theme.panel, builtin.panel);
+    }
+
+    #[test]
+    fn bundled_themes_parse() {
+        let themes: [&[u8]; 3] = [
+            include_bytes!("../../assets/themes/classic-dark.theme.ron"),
+            include_bytes!("../../assets/themes/mint.theme.ron"),
+            include_bytes!("../../assets/themes/mint-dark.theme.ron"),
+        ];
+        for bytes in themes {
+            assert!(parse_theme(bytes).is_ok());
+        }
+    }
+
+    #[test]
+    fn system_scheme_follows_os_and_defaults_to_light() {
+        assert!(!ColorScheme::System.is_dark(SystemDarkMode(None)));
+        assert!(ColorScheme::System.is_dark(SystemDarkMode(Some(true))));
+        assert!(!ColorScheme::Light.is_dark(SystemDarkMode(Some(true))));
+        assert!(ColorScheme::Dark.is_dark(SystemDarkMode(Some(false))));
+    }
+
+    #[test]
+    fn invalid_color_is_rejected() {
+        let bytes = include_str!("../../assets/themes/classic.theme.ron")
+            .replace("\"#bbada0\"", "\"#bbadz0\"");
+        let err = parse_theme(bytes.as_bytes()).unwrap_err();
+        assert!(err.contains("#bbadz0"), "{err}");
+    }
+}
@@ -0,0 +1,310 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::iter;
+use std::sync::{OnceLock, PoisonError, RwLock};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::board::Direction;
+
+const GRID_DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
+
+const HEX_DIRECTIONS: [Direction; 6] = [
+    Direction::Up,
+    Direction::UpRight,
+    Direction::DownRight,
+    Direction::Down,
+    Direction::DownLeft,
+    Direction::UpLeft,
+];
+
+/// `Direction` の種類数。隣接表とラインは `direction as usize` で引く
+const DIRECTION_COUNT: usize = 8;
+
+/// 盤面のセル配置。セル座標・隣接関係・有効なスライド方向を定める。
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Reflect, Serialize, Deserialize)]
+pub enum Topology {
+    /// 幅 × 高さの正方格子。座標は (x, y) で、y は上向き
+    Grid { width: usize, height: usize },
+    /// 半径 `radius` の六角形盤面（フラットトップ）。座標はアキシャル座標 (q, r) で、r は上向き
+    Hex { radius: usize },
+}
+
+impl Default for Topology {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+impl Topology {
+    pub const CLASSIC: Self = Self::Grid {
+        width: 4,
+        height: 4,
+    };
+    /// 完全解析テーブルでヒントを出せる小さな盤面
+    pub const MINI: Self = Self::Grid {
+        width: 3,
+        height: 3,
+    };
+    pub const HEX: Self = Self::Hex { radius: 2 };
+
+    pub fn cell_count(&self) -> usize {
+        match *self {
+            Self::Grid { width, height } => width * height,
+            Self::Hex { radius } => 3 * radius * (radius + 1) + 1,
+        }
+    }
+
+    pub fn directions(&self) -> &'static [Direction] {
+        match self {
+            Self::Grid { .. } => &GRID_DIRECTIONS,
+            Self::Hex { .. } => &HEX_DIRECTIONS,
+        }
+    }
+
+    /// セルの座標を返す。Grid は (x, y)、Hex は (q, r)
+    pub fn coords(&self, index: usize) -> IVec2 {
+        match *self {
+            Self::Grid { width, .. } => IVec2::new((index % width) as i32, (index / width) as i32),
+            Self::Hex { .. } => *self
+                .layout()
+                .coords
+                .get(index)
+                .expect("hex cell index out of range"),
+        }
+    }
+
+    pub fn index_of(&self, coords: IVec2) -> Option<usize> {
+        match *self {
+            Self::Grid { width, height } => {
+                let in_bounds =
+                    (0..width as i32).contains(&coords.x) && (0..height as i32).contains(&coords.y);
+                in_bounds.then(|| coords.x as usize + coords.y as usize * width)
+            }
+            Self::Hex { .. } => self.layout().index_of(coords),
+        }
+    }
+
+    /// `index` から `direction` 方向に隣接するセル。盤外や無効な方向なら `None`
+    pub fn neighbor(&self, index: usize, direction: Direction) -> Option<usize> {
+        self.layout().neighbors[index][direction as usize]
+    }
+
+    /// 同じ行（y または r が等しい）のセルをまとめる。行は下から上、行内は左から右に並ぶ
+    pub fn rows(&self) -> Vec<Vec<usize>> {
+        let mut rows: Vec<Vec<usize>> = Vec::new();
+        for index in 0..self.cell_count() {
+            let y = self.coords(index).y;
+            match rows.last_mut() {
+                Some(row) if self.coords(row[0]).y == y => row.push(index),
+                _ => rows.push(vec![index]),
+            }
+        }
+        rows
+    }
+
+    /// `direction` へのスライドで詰められる各ラインのインデックス列を返す。
+    /// 各ラインはスライド先の端が先頭になるよう並ぶ（`slide_line` は先頭に向かってタイルを詰める）。
+    pub fn lines(&self, direction: Direction) -> &'static [Vec<usize>] {
+        &self.layout().lines[direction as usize]
+    }
+
+    fn offset(&self, direction: Direction) -> Option<IVec2> {
+        match self {
+            Self::Grid { .. } => grid_offset(direction),
+            Self::Hex { .. } => hex_offset(direction),
+        }
+    }
+
+    /// このトポロジーの表。初回に作って以降は使い回す。
+    /// 探索の内側で毎回ロックを取らないよう、スレッドごとに直前に引いた表を覚えておく
+    fn layout(&self) -> &'static Layout {
+        static LAYOUTS: OnceLock<RwLock<HashMap<Topology, &'static Layout>>> = OnceLock::new();
+        thread_local! {
+            static LAST: Cell<Option<(Topology, &'static Layout)>> = const { Cell::new(None) };
+        }
+
+        if let Some((_, layout)) = LAST.get().filter(|(topology, _)| topology == self) {
+            return layout;
+        }
+        let layouts = LAYOUTS.get_or_init(Default::default);
+        let cached = layouts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(self)
+            .copied();
+        let layout = cached.unwrap_or_else(|| {
+            *layouts
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(*self)
+                .or_insert_with(|| Box::leak(Box::new(Layout::new(*self))))
+        });
+        LAST.set(Some((*self, layout)));
+        layout
+    }
+}
+
+/// セル座標・隣接セル・方向ごとのラインの表。
+/// 評価関数や探索の内側で毎回座標を数え直したりラインを作り直したりしないよう、トポロジーごとに一度だけ作る
+struct Layout {
+    coords: Vec<IVec2>,
+    /// 座標の外接矩形の左下の角
+    min: IVec2,
+    /// 外接矩形の大きさ
+    size: IVec2,
+    /// 外接矩形内の座標（行優先）からセルのインデックスを引く
+    indices: Vec<Option<usize>>,
+    neighbors: Vec<[Option<usize>; DIRECTION_COUNT]>,
+    lines: [Vec<Vec<usize>>; DIRECTION_COUNT],
+}
+
+impl Layout {
+    fn new(topology: Topology) -> Self {
+        let coords: Vec<IVec2> = match topology {
+            Topology::Grid { width, height } => (0..width * height)
+                .map(|index| IVec2::new((index % width) as i32, (index / width) as i32))
+                .collect(),
+            Topology::Hex { radius } => hex_cells(radius).collect(),
+        };
+        let (min, size) = match coords.iter().copied().reduce(IVec2::min) {
+            Some(min) => {
+                let max = coords.iter().copied().fold(min, IVec2::max);
+                (min, max - min + IVec2::ONE)
+            }
+            None => (IVec2::ZERO, IVec2::ZERO),
+        };
+
+        let mut layout = Self {
+            indices: vec![None; (size.x * size.y) as usize],
+            neighbors: vec![[None; DIRECTION_COUNT]; coords.len()],
+            lines: Default::default(),
+            coords,
+            min,
+            size,
+        };
+        for (index, &cell) in layout.coords.iter().enumerate() {
+            let local = cell - min;
+            layout.indices[(local.x + local.y * size.x) as usize] = Some(index);
+        }
+        for index in 0..layout.coords.len() {
+            for &direction in topology.directions() {
+                layout.neighbors[index][direction as usize] = topology
+                    .offset(direction)
+                    .and_then(|offset| layout.index_of(layout.coords[index] + offset));
+            }
+        }
+        for &direction in topology.directions() {
+            let neighbors = &layout.neighbors;
+            layout.lines[direction as usize] = (0..layout.coords.len())
+                .filter(|&index| neighbors[index][direction as usize].is_none())
+                .map(|start| {
+                    iter::successors(Some(start), |&index| {
+                        neighbors[index][direction.opposite() as usize]
+                    })
+                    .collect()
+                })
+                .collect();
+        }
+        layout
+    }
+
+    fn index_of(&self, coords: IVec2) -> Option<usize> {
+        let local = coords - self.min;
+        let in_bounds = (0..self.size.x).contains(&local.x) && (0..self.size.y).contains(&local.y);
+        in_bounds
+            .then(|| self.indices[(local.x + local.y * self.size.x) as usize])
+            .flatten()
+    }
+}
+
+fn grid_offset(direction: Direction) -> Option<IVec2> {
+    match direction {
+        Direction::Up => Some(IVec2::Y),
+        Direction::Down => Some(IVec2::NEG_Y),
+        Direction::Left => Some(IVec2::NEG_X),
+        Direction::Right => Some(IVec2::X),
+        _ => None,
+    }
+}
+
+fn hex_offset(direction: Direction) -> Option<IVec2> {
+    match direction {
+        Direction::Up => Some(IVec2::new(0, 1)),
+        Direction::Down => Some(IVec2::new(0, -1)),
+        Direction::UpRight => Some(IVec2::new(1, 0)),
+        Direction::DownLeft => Some(IVec2::new(-1, 0)),
+        Direction::DownRight => Some(IVec2::new(1, -1)),
+        Direction::UpLeft => Some(IVec2::new(-1, 1)),
+        Direction::Left | Direction::Right => None,
+    }
+}
+
+/// 半径 `radius` の六角形盤面のセル座標を、r → q の昇順で列挙する
+fn hex_cells(radius: usize) -> impl Iterator<Item = IVec2> {
+    let radius = radius as i32;
+    (-radius..=radius)
+        .flat_map(move |r| (-radius..=radius).map(move |q| IVec2::new(q, r)))
+        .filter(move |cell| (cell.x + cell.y).abs() <= radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_radius_two_has_nineteen_cells() {
+        let topology = Topology::HEX;
+        assert_eq!(topology.cell_count(), 19);
+        assert_eq!(hex_cells(2).count(), 19);
+    }
+
+    #[test]
+    fn hex_lines_cover_every_cell_once() {
+        let topology = Topology::HEX;
+        for &direction in topology.directions() {
+            let mut cells: Vec<usize> = topology
+                .lines(direction)
+                .iter()
+                .flatten()
+                .copied()
+                .collect();
+            cells.sort_unstable();
+            assert_eq!(cells, (0..topology.cell_count()).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn grid_lines_start_at_destination_edge() {
+        let topology = Topology::CLASSIC;
+        let lines = topology.lines(Direction::Left);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], vec![0, 1, 2, 3]);
+        assert!(topology.lines(Direction::UpLeft).is_empty());
+    }
+
+    #[test]
+    fn hex_tables_match_axial_coordinates() {
+        let topology = Topology::HEX;
+        for (index, cell) in hex_cells(2).enumerate() {
+            assert_eq!(topology.coords(index), cell);
+            assert_eq!(topology.index_of(cell), Some(index));
+            for &direction in topology.directions() {
+                let expected = topology.index_of(cell + hex_offset(direction).unwrap());
+                assert_eq!(topology.neighbor(index, direction), expected);
+            }
+        }
+        assert_eq!(topology.index_of(IVec2::new(2, 2)), None);
+        // 2 回目以降は同じ表を借りる
+        assert!(std::ptr::eq(
+            topology.lines(Direction::Up),
+            topology.lines(Direction::Up)
+        ));
+    }
+}
@@ -1,11 +1,14 @@
 use bevy::prelude::*;
-use rand::rng;
 
 use super::GameFont;
-use super::animation::{AnimationPhase, PendingSlide};
-use super::board::{Board, Score};
-use super::render::{VisualTile, spawn_visual_tile};
-use super::{GamePhase, HasWon};
+use super::animation::AnimationPhase;
+use super::board::Score;
+use super::export::{export_gif, export_image};
+use super::menu::{open_screen, open_settings};
+use super::score_effects::ScoreCounter;
+use super::session::{GameMode, GameReset, MatchConfig, Player};
+use super::state::{GamePhase, MatchOutcome, Screen, open_pause_menu};
+use super::theme::{Theme, UiColor};
 
 #[derive(Component)]
 pub(super) struct UIScoreText;
@@ -13,6 +16,10 @@ pub(super) struct UIScoreText;
 #[derive(Component)]
 pub(super) struct NewGameButton;
 
+/// ヘッダーに並ぶボタン。ウィンドウ幅に合わせてまとめてパディングを調整する
+#[derive(Component)]
+pub(super) struct HeaderButton;
+
 #[derive(Component)]
 pub(super) struct HeaderRoot;
 
@@ -22,10 +29,13 @@ pub(super) struct ButtonText;
 #[derive(Component)]
 pub(super) struct OverlayRoot;
 
-const BUTTON_BG: Color = Color::srgb(0.557, 0.494, 0.439);
-const BUTTON_HOVER: Color = Color::srgb(0.647, 0.584, 0.529);
-const SCORE_COLOR: Color = Color::srgb(0.467, 0.431, 0.396);
-const OVERLAY_BG: Color = Color::srgba(0.0, 0.0, 0.0, 0.5);
+/// ホットシートでタイルの置き方を案内するテキスト
+#[derive(Component)]
+pub(super) struct PlacementHint;
+
+/// AI が提案する次の一手
+#[derive(Component)]
+pub(super) struct HintText;
 
 const NARROW_THRESHOLD: f32 = 500.0;
 
@@ -57,75 +67,194 @@ pub(super) fn setup_ui(mut commands: Commands, font: Res<GameFont>) {
                     font_size: 36.0.into(),
                     ..default()
                 },
-                TextColor(SCORE_COLOR),
+                UiColor::Score,
             ));
 
-            // New Game ボタン
+            // ボタン行（Export + GIF + New Game + Menu）。ゲームの設定はタイトルとメニューの画面で変える
             parent
-                .spawn((
-                    NewGameButton,
-                    Button,
-                    Node {
-                        padding: UiRect::axes(Val::Px(24.0), Val::Px(12.0)),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        border_radius: BorderRadius::all(Val::Px(6.0)),
-                        ..default()
-                    },
-                    BackgroundColor(BUTTON_BG),
-                    children![(
-                        ButtonText,
-                        Text::new("New Game"),
-                        TextFont {
-                            font: font.0.clone().into(),
-                            font_size: 24.0.into(),
-                            ..default()
-                        },
-                        TextColor(Color::WHITE),
-                    )],
-                ))
-                .observe(on_new_game_click);
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Val::Px(8.0),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn((
+                            HeaderButton,
+                            Button,
+                            header_button_node(),
+                            UiColor::Button,
+                            children![(
+                                ButtonText,
+                                Text::new("Export"),
+                                header_button_font(&font.0),
+                                UiColor::ButtonText,
+                            )],
+                        ))
+                        .observe(export_image);
+
+                    parent
+                        .spawn((
+                            HeaderButton,
+                            Button,
+                            header_button_node(),
+                            UiColor::Button,
+                            children![(
+                                ButtonText,
+                                Text::new("GIF"),
+                                header_button_font(&font.0),
+                                UiColor::ButtonText,
+                            )],
+                        ))
+                        .observe(export_gif);
+
+                    parent
+                        .spawn((
+                            NewGameButton,
+                            HeaderButton,
+                            Button,
+                            header_button_node(),
+                            UiColor::Button,
+                            children![(
+                                ButtonText,
+                                Text::new("New Game"),
+                                header_button_font(&font.0),
+                                UiColor::ButtonText,
+                            )],
+                        ))
+                        .observe(on_new_game_click);
+
+                    parent
+                        .spawn((
+                            HeaderButton,
+                            Button,
+                            header_button_node(),
+                            UiColor::Button,
+                            children![(
+                                ButtonText,
+                                Text::new("Menu"),
+                                header_button_font(&font.0),
+                                UiColor::ButtonText,
+                            )],
+                        ))
+                        .observe(open_pause_menu);
+                });
         });
+
+    // ホットシートの操作説明（配置待ちの間だけ表示）
+    commands.spawn((
+        PlacementHint,
+        Text::new("Click = 2 / Right-click = 4"),
+        TextFont {
+            font: font.0.clone().into(),
+            font_size: 24.0.into(),
+            ..default()
+        },
+        UiColor::Score,
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(12.0),
+            left: Val::Px(16.0),
+            ..default()
+        },
+        Visibility::Hidden,
+    ));
+
+    commands.spawn((
+        HintText,
+        Text::default(),
+        TextFont {
+            font: font.0.clone().into(),
+            font_size: 24.0.into(),
+            ..default()
+        },
+        UiColor::Score,
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(12.0),
+            right: Val::Px(16.0),
+            ..default()
+        },
+        Visibility::Hidden,
+    ));
 }
 
-fn on_new_game_click(
-    _click: On<Pointer<Click>>,
-    mut commands: Commands,
-    mut board: ResMut<Board>,
-    mut score: ResMut<Score>,
-    mut phase: ResMut<AnimationPhase>,
-    mut pending: ResMut<PendingSlide>,
-    mut has_won: ResMut<HasWon>,
-    mut next_state: ResMut<NextState<GamePhase>>,
-    font: Res<GameFont>,
-    tiles: Query<Entity, With<VisualTile>>,
-) {
-    for entity in &tiles {
-        commands.entity(entity).despawn();
+fn header_button_node() -> Node {
+    Node {
+        padding: UiRect::axes(Val::Px(24.0), Val::Px(12.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        border_radius: BorderRadius::all(Val::Px(6.0)),
+        ..default()
     }
+}
 
-    *board = Board::with_two_tiles(&mut rng());
-    **score = 0;
-    *phase = AnimationPhase::Idle;
-    *pending = PendingSlide::default();
-    has_won.0 = false;
-    next_state.set(GamePhase::Playing);
+fn header_button_font(font: &Handle<Font>) -> TextFont {
+    TextFont {
+        font: font.clone().into(),
+        font_size: 24.0.into(),
+        ..default()
+    }
+}
 
-    for (index, cell) in board.iter().enumerate() {
-        if let Some(exp) = cell {
-            spawn_visual_tile(&mut commands, index, *exp, Vec3::ONE, &font);
-        }
+fn on_new_game_click(_press: On<Press>, mut reset: GameReset) {
+    let config = reset.config();
+    reset.restart(config);
+}
+
+/// 一時停止や 2048 到達の画面を閉じて続ける
+fn on_continue_click(
+    _press: On<Press>,
+    mut next_screen: ResMut<NextState<Screen>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+) {
+    next_screen.set(Screen::Playing);
+    next_phase.set(GamePhase::Playing);
+}
+
+/// ボタンが押された。マウスのクリックからも、ゲームパッドの A からも届く
+#[derive(EntityEvent)]
+pub(super) struct Press {
+    pub(super) entity: Entity,
+}
+
+/// ボタンのクリックを `Press` にする。押したボタンを囲む外側のボタンには伝えない
+pub(super) fn press_clicked_button(
+    mut click: On<Pointer<Click>>,
+    mut commands: Commands,
+    buttons: Query<(), With<Button>>,
+) {
+    if buttons.contains(click.entity) {
+        click.propagate(false);
+        commands.trigger(Press {
+            entity: click.entity,
+        });
     }
 }
 
-fn on_continue_click(_click: On<Pointer<Click>>, mut next_state: ResMut<NextState<GamePhase>>) {
-    next_state.set(GamePhase::Playing);
+/// オーバーレイに並べるボタン
+#[derive(Clone, Copy)]
+enum OverlayButton {
+    Continue,
+    NewGame,
+    Settings,
+    Title,
 }
 
-fn spawn_overlay_button<'a>(
+pub(super) fn spawn_overlay_button<'a>(
     parent: &'a mut ChildSpawnerCommands,
     label: &str,
     font: &Handle<Font>,
+) -> EntityCommands<'a> {
+    spawn_labeled_button(parent, (), label, font)
+}
+
+/// 文字に `marker` を付けたボタン。押すと表示が変わるボタンに使う
+pub(super) fn spawn_labeled_button<'a>(
+    parent: &'a mut ChildSpawnerCommands,
+    marker: impl Bundle,
+    label: &str,
+    font: &Handle<Font>,
 ) -> EntityCommands<'a> {
     parent.spawn((
         Button,
@@ -136,29 +265,33 @@ fn spawn_overlay_button<'a>(
             border_radius: BorderRadius::all(Val::Px(4.0)),
             ..default()
         },
-        BackgroundColor(BUTTON_BG),
+        UiColor::Button,
         children![(
+            marker,
             Text::new(label.to_string()),
             TextFont {
                 font: font.into(),
                 font_size: 20.0.into(),
                 ..default()
             },
-            TextColor(Color::WHITE),
+            UiColor::ButtonText,
         )],
     ))
 }
 
+/// 盤面の上に重ねる画面。`scope` に `DespawnOnExit` を渡すと、その状態を抜けたときに消える
 fn spawn_overlay(
     commands: &mut Commands,
+    scope: impl Bundle,
     title: &str,
-    score_value: u32,
-    show_continue: bool,
+    score_line: &str,
+    buttons: &[OverlayButton],
     font: &Handle<Font>,
 ) {
     commands
         .spawn((
             OverlayRoot,
+            scope,
             Node {
                 width: Val::Percent(100.0),
                 height: Val::Percent(100.0),
@@ -167,20 +300,24 @@ fn spawn_overlay(
                 align_items: AlignItems::Center,
                 ..default()
             },
-            BackgroundColor(OVERLAY_BG),
+            UiColor::Overlay,
             // Z-index で他のUIの上に表示
             ZIndex(10),
         ))
         .with_children(|parent| {
+            // 背景の盤面の明るさやオーバーレイの濃さによらず読めるように、パネルに載せる
             parent
-                .spawn(Node {
-                    flex_direction: FlexDirection::Column,
-                    align_items: AlignItems::Center,
-                    row_gap: Val::Px(16.0),
-                    padding: UiRect::all(Val::Px(32.0)),
-                    border_radius: BorderRadius::all(Val::Px(8.0)),
-                    ..default()
-                })
+                .spawn((
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(16.0),
+                        padding: UiRect::all(Val::Px(32.0)),
+                        border_radius: BorderRadius::all(Val::Px(8.0)),
+                        ..default()
+                    },
+                    UiColor::Panel,
+                ))
                 .with_children(|parent| {
                     // タイトル
                     parent.spawn((
@@ -190,18 +327,18 @@ fn spawn_overlay(
                             font_size: 48.0.into(),
                             ..default()
                         },
-                        TextColor(Color::WHITE),
+                        UiColor::OverlayText,
                     ));
 
                     // スコア
                     parent.spawn((
-                        Text::new(format!("Score: {score_value}")),
+                        Text::new(score_line),
                         TextFont {
                             font: font.clone().into(),
                             font_size: 24.0.into(),
                             ..default()
                         },
-                        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.8)),
+                        UiColor::OverlaySubtext,
                     ));
 
                     // ボタン行
@@ -213,53 +350,168 @@ fn spawn_overlay(
                             ..default()
                         })
                         .with_children(|parent| {
-                            if show_continue {
-                                spawn_overlay_button(parent, "Continue", font)
-                                    .observe(on_continue_click);
+                            for &button in buttons {
+                                match button {
+                                    OverlayButton::Continue => {
+                                        spawn_overlay_button(parent, "Continue", font)
+                                            .observe(on_continue_click);
+                                    }
+                                    OverlayButton::NewGame => {
+                                        spawn_overlay_button(parent, "New Game", font)
+                                            .observe(on_new_game_click);
+                                    }
+                                    OverlayButton::Settings => {
+                                        spawn_overlay_button(parent, "Settings", font)
+                                            .observe(open_settings);
+                                    }
+                                    OverlayButton::Title => {
+                                        spawn_overlay_button(parent, "Title", font)
+                                            .observe(open_screen(Screen::Title));
+                                    }
+                                }
                             }
-                            spawn_overlay_button(parent, "New Game", font)
-                                .observe(on_new_game_click);
                         });
                 });
         });
 }
 
+/// カウントアップを待たない確定したスコア
+fn current_scores<'a>(
+    scores: &'a Query<(&Player, &Score)>,
+) -> impl Iterator<Item = (Player, u32)> + 'a {
+    scores.iter().map(|(player, score)| (*player, **score))
+}
+
+/// スコア表示用の文字列。対戦時はプレイヤーごとに並べる
+fn score_line(mode: GameMode, scores: impl Iterator<Item = (Player, u32)>) -> String {
+    let mut scores: Vec<_> = scores.collect();
+    scores.sort_by_key(|(player, _)| *player);
+    match mode {
+        GameMode::Solo => format!("Score: {}", scores.first().map_or(0, |(_, score)| *score)),
+        GameMode::Versus => scores
+            .iter()
+            .map(|(player, score)| format!("{}: {}", player.label(), score))
+            .collect::<Vec<_>>()
+            .join("  "),
+    }
+}
+
 pub(super) fn spawn_game_over_overlay(
     mut commands: Commands,
-    score: Res<Score>,
+    config: Res<MatchConfig>,
+    outcome: Res<MatchOutcome>,
+    scores: Query<(&Player, &Score)>,
     font: Res<GameFont>,
 ) {
-    spawn_overlay(&mut commands, "Game Over", **score, false, &font.0);
+    let title = match (config.mode, outcome.winner) {
+        (GameMode::Solo, _) => "Game Over".to_string(),
+        (GameMode::Versus, Some(winner)) => format!("{} Wins!", winner.label()),
+        (GameMode::Versus, None) => "Draw".to_string(),
+    };
+    let line = score_line(config.mode, current_scores(&scores));
+    spawn_overlay(
+        &mut commands,
+        DespawnOnExit(GamePhase::GameOver),
+        &title,
+        &line,
+        &[OverlayButton::NewGame, OverlayButton::Title],
+        &font.0,
+    );
 }
 
-pub(super) fn spawn_won_overlay(mut commands: Commands, score: Res<Score>, font: Res<GameFont>) {
-    spawn_overlay(&mut commands, "You Win!", **score, true, &font.0);
+pub(super) fn spawn_won_overlay(
+    mut commands: Commands,
+    config: Res<MatchConfig>,
+    outcome: Res<MatchOutcome>,
+    scores: Query<(&Player, &Score)>,
+    font: Res<GameFont>,
+) {
+    let line = score_line(config.mode, current_scores(&scores));
+    let scope = DespawnOnExit(GamePhase::Won);
+    match outcome.winner {
+        // 対戦では最初に 2048 に到達した時点で決着するため、続行はできない
+        Some(winner) => {
+            let title = format!("{} Wins!", winner.label());
+            let buttons = [OverlayButton::NewGame, OverlayButton::Title];
+            spawn_overlay(&mut commands, scope, &title, &line, &buttons, &font.0);
+        }
+        None => {
+            let buttons = [OverlayButton::Continue, OverlayButton::NewGame];
+            spawn_overlay(&mut commands, scope, "You Win!", &line, &buttons, &font.0);
+        }
+    }
 }
 
-pub(super) fn despawn_overlay(mut commands: Commands, overlay: Query<Entity, With<OverlayRoot>>) {
-    for entity in &overlay {
-        commands.entity(entity).despawn();
-    }
+pub(super) fn spawn_paused_overlay(
+    mut commands: Commands,
+    config: Res<MatchConfig>,
+    scores: Query<(&Player, &Score)>,
+    font: Res<GameFont>,
+) {
+    let line = score_line(config.mode, current_scores(&scores));
+    spawn_overlay(
+        &mut commands,
+        DespawnOnExit(Screen::Paused),
+        "Paused",
+        &line,
+        &[
+            OverlayButton::Continue,
+            OverlayButton::NewGame,
+            OverlayButton::Settings,
+            OverlayButton::Title,
+        ],
+        &font.0,
+    );
 }
 
 pub(super) fn button_hover(
+    theme: Res<Theme>,
     mut query: Query<(&Interaction, &mut BackgroundColor), (Changed<Interaction>, With<Button>)>,
 ) {
     for (interaction, mut color) in &mut query {
-        *color = match *interaction {
-            Interaction::Hovered | Interaction::Pressed => BackgroundColor(BUTTON_HOVER),
-            Interaction::None => BackgroundColor(BUTTON_BG),
+        color.0 = match *interaction {
+            Interaction::Hovered | Interaction::Pressed => theme.button_hover,
+            Interaction::None => theme.button,
         };
     }
 }
 
-pub(super) fn sync_ui_score(score: Res<Score>, mut query: Query<&mut Text, With<UIScoreText>>) {
-    if !score.is_changed() {
+/// ヘッダーのスコアを、カウントアップ中の表示値で更新する
+pub(super) fn sync_ui_score(
+    config: Res<MatchConfig>,
+    counters: Query<(&Player, &ScoreCounter)>,
+    changed: Query<(), Changed<ScoreCounter>>,
+    mut query: Query<&mut Text, With<UIScoreText>>,
+) {
+    if changed.is_empty() {
         return;
     }
 
+    let line = score_line(
+        config.mode,
+        counters
+            .iter()
+            .map(|(player, counter)| (*player, counter.value())),
+    );
     for mut text in &mut query {
-        text.0 = format!("Score: {}", **score);
+        text.0.clone_from(&line);
+    }
+}
+
+pub(super) fn sync_placement_hint(
+    phases: Query<&AnimationPhase>,
+    mut hints: Query<&mut Visibility, With<PlacementHint>>,
+) {
+    let awaiting = phases
+        .iter()
+        .any(|phase| *phase == AnimationPhase::AwaitingPlacement);
+    let visibility = if awaiting {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    for mut hint in &mut hints {
+        hint.set_if_neq(visibility);
     }
 }
 
@@ -268,7 +520,7 @@ pub(super) fn adapt_header_to_window(
     windows: Query<&Window>,
     mut header_query: Query<&mut Node, With<HeaderRoot>>,
     mut score_query: Query<&mut TextFont, With<UIScoreText>>,
-    mut button_query: Query<&mut Node, (With<NewGameButton>, Without<HeaderRoot>)>,
+    mut button_query: Query<&mut Node, (With<HeaderButton>, Without<HeaderRoot>)>,
     mut button_text_query: Query<&mut TextFont, (With<ButtonText>, Without<UIScoreText>)>,
 ) {
     let Some(window) = windows.iter().next() else {
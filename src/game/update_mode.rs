@@ -1,7 +1,35 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy::winit::{UpdateMode, WinitSettings};
 
 use super::animation::AnimationPhase;
+use super::board::Score;
+use super::export::GifExport;
+use super::score_effects::ScoreCounter;
+
+/// 再生中の演出（スコアのポップアップなど）。
+/// 盤面が止まっていても、このエンティティがある間は描画を続ける
+#[derive(Component, Default)]
+pub(super) struct LiveEffect;
+
+/// 画面を更新し続ける必要があるか。書き出し中の GIF も、終わりを拾うまで更新を続ける
+#[derive(SystemParam)]
+pub(super) struct Activity<'w, 's> {
+    phases: Query<'w, 's, &'static AnimationPhase>,
+    effects: Query<'w, 's, (), Or<(With<LiveEffect>, With<GifExport>)>>,
+    counters: Query<'w, 's, (&'static Score, &'static ScoreCounter)>,
+}
+
+impl Activity<'_, '_> {
+    fn is_active(&self) -> bool {
+        self.phases.iter().any(AnimationPhase::is_animating)
+            || !self.effects.is_empty()
+            || self
+                .counters
+                .iter()
+                .any(|(score, counter)| counter.value() != **score)
+    }
+}
 
 #[derive(Resource, Clone)]
 pub(super) struct IdleFocusedUpdateMode(UpdateMode);
@@ -11,20 +39,20 @@ pub(super) fn capture_idle_update_mode(mut commands: Commands, winit_settings: R
 }
 
 pub(super) fn request_redraw_during_animation(
-    phase: Res<AnimationPhase>,
+    activity: Activity,
     mut redraw: MessageWriter<bevy::window::RequestRedraw>,
 ) {
-    if *phase != AnimationPhase::Idle {
+    if activity.is_active() {
         redraw.write(bevy::window::RequestRedraw);
     }
 }
 
 pub(super) fn sync_focused_update_mode(
-    phase: Res<AnimationPhase>,
+    activity: Activity,
     idle_mode: Res<IdleFocusedUpdateMode>,
     mut winit_settings: ResMut<WinitSettings>,
 ) {
-    let desired_mode = if *phase == AnimationPhase::Idle {
+    let desired_mode = if !activity.is_active() {
         idle_mode.0
     } else {
         UpdateMode::Continuous
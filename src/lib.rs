@@ -0,0 +1,4 @@
+//! 2048 のルールと Bevy プラグイン。
+//! ルール（`game::board` など）はウィンドウを持たないフロントエンドからも使える。
+
+pub mod game;
@@ -1,7 +1,5 @@
 #![cfg_attr(not(feature = "dev"), windows_subsystem = "windows")]
 
-mod game;
-
 use std::time::Duration;
 
 #[cfg(feature = "dev_native")]
@@ -14,7 +12,21 @@ use bevy::{
 #[cfg(feature = "dev_native")]
 use bevy_inspector_egui::{bevy_egui::EguiPlugin, quick::WorldInspectorPlugin};
 
+use bevy_2048::game;
+
+#[cfg(not(target_arch = "wasm32"))]
+const GIF_USAGE: &str =
+    "usage: bevy_2048 --gif [--fps N] [--last N | --all] [--out DIR] FILE.replay.ron...";
+
 fn main() -> AppExit {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if args.first().is_some_and(|arg| arg == "--gif") {
+            return export_gifs(args.into_iter().skip(1));
+        }
+    }
+
     let mut app = App::new();
 
     app.add_plugins(
@@ -51,3 +63,94 @@ fn main() -> AppExit {
 
     app.run()
 }
+
+/// `--gif` の引数
+#[cfg(not(target_arch = "wasm32"))]
+struct GifOptions {
+    settings: game::GifSettings,
+    out: Option<std::path::PathBuf>,
+    files: Vec<std::path::PathBuf>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_gif_args(mut args: impl Iterator<Item = String>) -> Result<GifOptions, String> {
+    fn value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+        value
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| format!("{flag} needs a value"))
+    }
+
+    let mut options = GifOptions {
+        settings: game::GifSettings::default(),
+        out: None,
+        files: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fps" => options.settings.fps = value(&arg, args.next())?,
+            "--last" => options.settings.last_moves = Some(value(&arg, args.next())?),
+            "--all" => options.settings.last_moves = None,
+            "--out" => options.out = Some(value(&arg, args.next())?),
+            _ if arg.starts_with("--") => return Err(format!("unknown argument: {arg}")),
+            _ => options.files.push(arg.into()),
+        }
+    }
+
+    if options.files.is_empty() {
+        return Err("no replay files given".to_string());
+    }
+    if options.settings.fps == 0 {
+        return Err("--fps must be at least 1".to_string());
+    }
+    Ok(options)
+}
+
+/// リプレイファイルをアニメーション GIF に書き出し、ゲームは起動せずに終える。
+/// GIF は `--out` のフォルダ（省略するとリプレイと同じフォルダ）に `<名前>.gif` として保存する
+#[cfg(not(target_arch = "wasm32"))]
+fn export_gifs(args: impl Iterator<Item = String>) -> AppExit {
+    const FONT: &[u8] = include_bytes!("../assets/fonts/DotGothic16-Regular.ttf");
+
+    let options = match parse_gif_args(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{message}\n{GIF_USAGE}");
+            return AppExit::from_code(2);
+        }
+    };
+
+    let mut failed = false;
+    for file in &options.files {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let stem = name
+            .strip_suffix(&format!(".{}", game::replay::REPLAY_EXTENSION))
+            .unwrap_or(&name);
+        let dir = options
+            .out
+            .as_deref()
+            .or_else(|| file.parent())
+            .unwrap_or(std::path::Path::new("."));
+        let out = dir.join(format!("{stem}.gif"));
+
+        let result = std::fs::read_to_string(file)
+            .map_err(|err| err.to_string())
+            .and_then(|text| game::replay::Replay::from_ron(&text))
+            .and_then(|replay| game::replay_gif(&replay, &options.settings, FONT))
+            .and_then(|gif| {
+                std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+                std::fs::write(&out, gif).map_err(|err| err.to_string())
+            });
+        match result {
+            Ok(()) => println!("saved {}", out.display()),
+            Err(err) => {
+                eprintln!("failed to export {}: {err}", file.display());
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        AppExit::error()
+    } else {
+        AppExit::Success
+    }
+}